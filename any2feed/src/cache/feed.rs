@@ -0,0 +1,503 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use bytes::Bytes;
+use http_server::auth::AUTH_TOKEN_PARAM;
+use http_server::{HTTPRequest, HTTPResponse};
+
+use crate::cache::hash_key;
+use crate::feed_sources::utils::timestamp_now;
+
+/// Default limit of cached feed responses
+pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+/// Cache of rendered feed responses, shared by all feed sources
+#[derive(Debug)]
+pub struct FeedCacheStorage {
+    entries: Mutex<HashMap<String, CachedFeed>>,
+    /// Expiration of files on disk by file name
+    files: Mutex<HashMap<String, u64>>,
+    /// Also persist entries to disk
+    path: Option<PathBuf>,
+    /// Max entries in memory and on disk, soonest expiring are evicted first
+    max_entries: usize,
+}
+
+#[derive(Debug, Clone)]
+struct CachedFeed {
+    expires: u64,
+    content_type: Option<String>,
+    headers: HashMap<String, String>,
+    content: Bytes,
+}
+
+impl CachedFeed {
+    fn to_response(&self) -> HTTPResponse {
+        HTTPResponse {
+            status: 200,
+            content: Some(self.content.clone()),
+            content_type: self.content_type.clone(),
+            headers: self.headers.clone(),
+            ..HTTPResponse::default()
+        }
+    }
+
+    /// File layout: `{key}\n{expires}\n{content_type}\n{headers}\n{content}`,
+    /// headers are `name: value` separated by `\r`
+    fn to_bytes(&self, key: &str) -> Vec<u8> {
+        let mut data = Vec::with_capacity(key.len() + self.content.len() + 64);
+        data.extend_from_slice(key.as_bytes());
+        data.push(b'\n');
+        data.extend_from_slice(self.expires.to_string().as_bytes());
+        data.push(b'\n');
+        data.extend_from_slice(self.content_type.as_deref().unwrap_or_default().as_bytes());
+        data.push(b'\n');
+        let headers = self
+            .headers
+            .iter()
+            .map(|(k, v)| format!("{k}: {v}"))
+            .collect::<Vec<String>>()
+            .join("\r");
+        data.extend_from_slice(headers.as_bytes());
+        data.push(b'\n');
+        data.extend_from_slice(&self.content);
+        data
+    }
+
+    fn from_bytes(key: &str, data: &[u8]) -> Option<CachedFeed> {
+        let mut parts = data.splitn(5, |b| *b == b'\n');
+        if parts.next()? != key.as_bytes() {
+            // Коллизия хеша
+            return None;
+        }
+        let expires = String::from_utf8_lossy(parts.next()?).parse().ok()?;
+        let content_type = String::from_utf8_lossy(parts.next()?).to_string();
+        let headers = String::from_utf8_lossy(parts.next()?)
+            .split('\r')
+            .filter_map(|h| h.split_once(": "))
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        let content = Bytes::copy_from_slice(parts.next()?);
+        Some(CachedFeed {
+            expires,
+            content_type: (!content_type.is_empty()).then_some(content_type),
+            headers,
+            content,
+        })
+    }
+}
+
+/// Drop expired entries and the soonest expiring above `max`, return removed keys
+fn evict<T>(
+    entries: &mut HashMap<String, T>,
+    expires: impl Fn(&T) -> u64,
+    now: u64,
+    max: usize,
+) -> Vec<String> {
+    let mut removed: Vec<String> = entries
+        .iter()
+        .filter(|(_, e)| expires(e) <= now)
+        .map(|(k, _)| k.clone())
+        .collect();
+    if entries.len() - removed.len() > max {
+        let mut alive: Vec<(u64, &String)> = entries
+            .iter()
+            .map(|(k, e)| (expires(e), k))
+            .filter(|(e, _)| *e > now)
+            .collect();
+        alive.sort();
+        let over = alive.len() - max;
+        removed.extend(alive.into_iter().take(over).map(|(_, k)| k.clone()));
+    }
+    for key in removed.iter() {
+        entries.remove(key);
+    }
+    removed
+}
+
+impl FeedCacheStorage {
+    pub fn new(path: Option<PathBuf>, max_entries: usize) -> FeedCacheStorage {
+        let files = path.as_ref().and_then(|path| match Self::scan(path) {
+            Ok(files) => Some(files),
+            Err(e) => {
+                log::error!("Feed cache {:?} init failed: {:?}", path, e);
+                None
+            }
+        });
+        let storage = FeedCacheStorage {
+            entries: Mutex::default(),
+            path: files.is_some().then_some(path).flatten(),
+            files: Mutex::new(files.unwrap_or_default()),
+            max_entries,
+        };
+        storage.sweep(timestamp_now());
+        storage
+    }
+
+    /// Read expiration of files left from previous run
+    fn scan(path: &Path) -> io::Result<HashMap<String, u64>> {
+        fs::create_dir_all(path)?;
+        let mut files = HashMap::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            let expires = fs::read(entry.path()).ok().and_then(|data| {
+                let line = data.split(|b| *b == b'\n').nth(1)?;
+                String::from_utf8_lossy(line).parse().ok()
+            });
+            match expires {
+                Some(expires) => {
+                    files.insert(name, expires);
+                }
+                // Битый файл
+                None => {
+                    let _ = fs::remove_file(entry.path());
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    /// Remove expired and over limit entries from memory and disk
+    fn sweep(&self, now: u64) {
+        evict(
+            &mut self.entries.lock().unwrap_or_else(|e| e.into_inner()),
+            |e| e.expires,
+            now,
+            self.max_entries,
+        );
+        let Some(path) = self.path.as_ref() else {
+            return;
+        };
+        let removed = evict(
+            &mut self.files.lock().unwrap_or_else(|e| e.into_inner()),
+            |e| *e,
+            now,
+            self.max_entries,
+        );
+        for name in removed {
+            let _ = fs::remove_file(path.join(name));
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<HTTPResponse> {
+        let now = timestamp_now();
+        {
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some(entry) = entries.get(key) {
+                if entry.expires > now {
+                    return Some(entry.to_response());
+                }
+                entries.remove(key);
+            }
+        }
+        let path = self.path.as_ref()?.join(hash_key(key));
+        let entry = CachedFeed::from_bytes(key, &fs::read(&path).ok()?)?;
+        if entry.expires > now {
+            let response = entry.to_response();
+            let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+            entries.insert(key.to_string(), entry);
+            evict(&mut entries, |e| e.expires, now, self.max_entries);
+            Some(response)
+        } else {
+            self.files
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&hash_key(key));
+            let _ = fs::remove_file(path);
+            None
+        }
+    }
+
+    fn put(&self, key: &str, ttl: Duration, response: &HTTPResponse) {
+        let Some(content) = response.content.as_ref() else {
+            return;
+        };
+        if response.status != 200 || key.contains('\n') || self.max_entries == 0 {
+            return;
+        }
+        let now = timestamp_now();
+        let entry = CachedFeed {
+            expires: now + ttl.as_secs(),
+            content_type: response.content_type.clone(),
+            headers: response.headers.clone(),
+            content: content.clone(),
+        };
+        if let Some(path) = self.path.as_ref() {
+            let name = hash_key(key);
+            match fs::write(path.join(&name), entry.to_bytes(key)) {
+                Ok(_) => {
+                    self.files
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .insert(name, entry.expires);
+                }
+                Err(e) => log::warn!("Feed cache write failed: {:?}", e),
+            }
+        }
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(key.to_string(), entry);
+        // Чистим протухшее и лишнее
+        self.sweep(now);
+    }
+}
+
+/// Feed cache with ttl of specific feed source
+#[derive(Debug, Clone)]
+pub struct FeedCache {
+    storage: Arc<FeedCacheStorage>,
+    ttl: Duration,
+}
+
+impl FeedCache {
+    pub fn new(storage: Arc<FeedCacheStorage>, ttl: Duration) -> FeedCache {
+        FeedCache { storage, ttl }
+    }
+
+    /// Key by external url and query, without `nocache` param.
+    /// Links in feed depend on base url, so it is part of key.
+    /// Value of auth `token` is hidden, only presence of it changes links
+    /// ```
+    /// use http_server::HTTPRequest;
+    /// use any2feed::cache::feed::FeedCache;
+    /// let lines = vec![
+    ///     "GET /telegram/feed/foo/?nocache=1&b=2&a=1 HTTP/1.1".to_string(),
+    ///     "Host: example.com".to_string(),
+    /// ];
+    /// let r = HTTPRequest::parse(&lines).unwrap();
+    /// assert_eq!(FeedCache::key(&r), "http://example.com/telegram/feed/foo/?a=1&b=2");
+    /// let lines = vec![
+    ///     "GET /telegram/feed/foo/?a=1&token=secret HTTP/1.1".to_string(),
+    ///     "Host: example.com".to_string(),
+    /// ];
+    /// let r = HTTPRequest::parse(&lines).unwrap();
+    /// assert_eq!(
+    ///     FeedCache::key(&r),
+    ///     "http://example.com/telegram/feed/foo/?a=1&token=***"
+    /// );
+    /// ```
+    pub fn key(request: &HTTPRequest) -> String {
+        let mut query: Vec<(&String, &String)> = request
+            .query_params
+            .iter()
+            .filter(|(k, _)| k.as_str() != "nocache")
+            .collect();
+        query.sort();
+        let query = query
+            .into_iter()
+            .map(|(k, v)| match k.as_str() {
+                AUTH_TOKEN_PARAM => format!("{k}=***"),
+                _ => format!("{k}={v}"),
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+        let mut url = request.absolute_url(&request.path);
+        // Секрет не должен попасть в ключ и имя файла кеша
+        url.set_query(None);
+        format!("{url}?{query}")
+    }
+
+    /// `?nocache=1` skip cached response
+    pub fn is_bypass(request: &HTTPRequest) -> bool {
+        request
+            .query_params
+            .get("nocache")
+            .is_some_and(|v| !matches!(v.as_str(), "" | "0" | "false"))
+    }
+
+    /// Cached response by [`FeedCache::key`] of request, `None` on miss or bypass
+    fn lookup(&self, key: &str, request: &HTTPRequest) -> Option<HTTPResponse> {
+        if FeedCache::is_bypass(request) {
+            return None;
+        }
+        let response = self.storage.get(key)?;
+        log::debug!("Feed cache hit key={:?}", key);
+        Some(response)
+    }
+
+    fn put(&self, key: &str, response: &HTTPResponse) {
+        self.storage.put(key, self.ttl, response);
+    }
+}
+
+/// Return cached response or render it with `view` and cache
+pub fn cached_view<F>(
+    cache: Option<&FeedCache>,
+    request: &HTTPRequest,
+    view: F,
+) -> http_server::Result<HTTPResponse>
+where
+    F: FnOnce() -> http_server::Result<HTTPResponse>,
+{
+    let Some(cache) = cache else {
+        return view();
+    };
+    let key = FeedCache::key(request);
+    match cache.lookup(&key, request) {
+        Some(response) => Ok(response),
+        None => view().inspect(|response| cache.put(&key, response)),
+    }
+}
+
+/// Async variant of [`cached_view`]
+#[cfg(feature = "async")]
+pub async fn cached_view_async<F, Fut>(
+    cache: Option<&FeedCache>,
+    request: &HTTPRequest<'_>,
+    view: F,
+) -> http_server::Result<HTTPResponse>
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = http_server::Result<HTTPResponse>>,
+{
+    let Some(cache) = cache else {
+        return view().await;
+    };
+    let key = FeedCache::key(request);
+    match cache.lookup(&key, request) {
+        Some(response) => Ok(response),
+        None => view().await.inspect(|response| cache.put(&key, response)),
+    }
+}
+
+/// Wrap route callback with feed cache
+pub fn cached<F>(
+    cache: Option<FeedCache>,
+    view: F,
+) -> impl Fn(&HTTPRequest) -> http_server::Result<HTTPResponse> + Send + Sync + 'static
+where
+    F: Fn(&HTTPRequest) -> http_server::Result<HTTPResponse> + Send + Sync + 'static,
+{
+    move |r| cached_view(cache.as_ref(), r, || view(r))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::env;
+
+    fn request(full_path: &str) -> HTTPRequest<'static> {
+        let (path, query) = full_path.split_once('?').unwrap_or((full_path, ""));
+        HTTPRequest {
+            path: path.to_string(),
+            full_path: full_path.to_string(),
+            query_params: query
+                .split('&')
+                .filter_map(|p| p.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            ..HTTPRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_cached_view() {
+        let cache = FeedCache::new(
+            Arc::new(FeedCacheStorage::new(None, DEFAULT_MAX_ENTRIES)),
+            Duration::from_secs(60),
+        );
+        let calls = Cell::new(0);
+        let view = || {
+            calls.set(calls.get() + 1);
+            Ok(HTTPResponse::with_content("feed").set_content_type("text/xml"))
+        };
+
+        let r = cached_view(Some(&cache), &request("/feed/"), view).unwrap();
+        assert_eq!(r.content, Some(Bytes::from("feed")));
+        let r = cached_view(Some(&cache), &request("/feed/"), view).unwrap();
+        assert_eq!(r.content_type, Some("text/xml".to_string()));
+        assert_eq!(calls.get(), 1);
+
+        // Other key
+        cached_view(Some(&cache), &request("/feed/?page=2"), view).unwrap();
+        assert_eq!(calls.get(), 2);
+        // Bypass
+        cached_view(Some(&cache), &request("/feed/?nocache=1"), view).unwrap();
+        assert_eq!(calls.get(), 3);
+        // Without cache
+        cached_view(None, &request("/feed/"), view).unwrap();
+        assert_eq!(calls.get(), 4);
+    }
+
+    #[test]
+    fn test_expired() {
+        let cache = FeedCache::new(
+            Arc::new(FeedCacheStorage::new(None, DEFAULT_MAX_ENTRIES)),
+            Duration::ZERO,
+        );
+        let calls = Cell::new(0);
+        let view = || {
+            calls.set(calls.get() + 1);
+            Ok(HTTPResponse::with_content("feed"))
+        };
+        cached_view(Some(&cache), &request("/feed/"), view).unwrap();
+        cached_view(Some(&cache), &request("/feed/"), view).unwrap();
+        assert_eq!(calls.get(), 2);
+    }
+
+    #[test]
+    fn test_on_disk() {
+        let path = env::temp_dir().join("any2feed_test_feed_cache");
+        let _ = fs::remove_dir_all(&path);
+        let ttl = Duration::from_secs(60);
+        let cache = FeedCache::new(
+            Arc::new(FeedCacheStorage::new(
+                Some(path.clone()),
+                DEFAULT_MAX_ENTRIES,
+            )),
+            ttl,
+        );
+        cached_view(Some(&cache), &request("/feed/"), || {
+            Ok(HTTPResponse::with_content("feed")
+                .set_header("Last-Modified", "Sun, 09 Sep 2001 01:46:40 GMT"))
+        })
+        .unwrap();
+
+        // Restarted
+        let cache = FeedCache::new(
+            Arc::new(FeedCacheStorage::new(Some(path), DEFAULT_MAX_ENTRIES)),
+            ttl,
+        );
+        let r = cached_view(Some(&cache), &request("/feed/"), || unreachable!()).unwrap();
+        assert_eq!(r.content, Some(Bytes::from("feed")));
+        assert_eq!(
+            r.header("Last-Modified"),
+            Some("Sun, 09 Sep 2001 01:46:40 GMT")
+        );
+    }
+
+    #[test]
+    fn test_max_entries() {
+        let path = env::temp_dir().join("any2feed_test_feed_cache_max");
+        let _ = fs::remove_dir_all(&path);
+        let storage = Arc::new(FeedCacheStorage::new(Some(path.clone()), 2));
+        let response = HTTPResponse::with_content("feed");
+        storage.put("a", Duration::from_secs(10), &response);
+        storage.put("b", Duration::from_secs(30), &response);
+        storage.put("c", Duration::from_secs(20), &response);
+        // Вытеснен самый скоро протухающий
+        assert!(storage.get("a").is_none());
+        assert!(storage.get("b").is_some());
+        assert!(storage.get("c").is_some());
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 2);
+
+        // Протухшие файлы удаляются при старте
+        let expired = CachedFeed {
+            expires: timestamp_now() - 1,
+            content_type: None,
+            headers: HashMap::new(),
+            content: Bytes::from("feed"),
+        };
+        fs::write(path.join(hash_key("d")), expired.to_bytes("d")).unwrap();
+        let storage = FeedCacheStorage::new(Some(path.clone()), 1);
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 1);
+        assert!(storage.get("b").is_some());
+    }
+}
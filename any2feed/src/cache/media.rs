@@ -0,0 +1,536 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use bytes::Bytes;
+use http_server::range::parse_range;
+use http_server::{HTTPRequest, HTTPResponse};
+
+use crate::cache::hash_key;
+use crate::feed_sources::utils::MAX_BUFFERED_SIZE;
+
+/// On disk cache for proxied media with LRU eviction.
+///
+/// Every entry is a file named by hash of logical media id (not by upstream url,
+/// because upstream urls with tokens expire), layout:
+/// ```text
+/// {key}\n{content_type}\n{content}
+/// ```
+/// Streamed bodies are written while sent to client, see [`MediaCache::tee`]
+#[derive(Debug)]
+pub struct MediaCache {
+    path: PathBuf,
+    max_size: u64,
+    index: Mutex<CacheIndex>,
+}
+
+#[derive(Debug, Default)]
+struct CacheIndex {
+    entries: HashMap<String, IndexEntry>,
+    size: u64,
+    // Счетчик обращений, вместо времени
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct IndexEntry {
+    size: u64,
+    accessed: u64,
+}
+
+impl CacheIndex {
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    fn touch(&mut self, name: &str) -> bool {
+        let tick = self.next_tick();
+        if let Some(entry) = self.entries.get_mut(name) {
+            entry.accessed = tick;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn insert(&mut self, name: String, size: u64) {
+        let accessed = self.next_tick();
+        if let Some(old) = self.entries.insert(name, IndexEntry { size, accessed }) {
+            self.size -= old.size;
+        }
+        self.size += size;
+    }
+
+    fn remove(&mut self, name: &str) {
+        if let Some(old) = self.entries.remove(name) {
+            self.size -= old.size;
+        }
+    }
+
+    /// Remove least recently used entries until size fits, returns removed names
+    fn evict(&mut self, max_size: u64) -> Vec<String> {
+        let mut removed = Vec::new();
+        if self.size <= max_size {
+            return removed;
+        }
+        let mut entries: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .map(|(name, e)| (e.accessed, name.clone()))
+            .collect();
+        entries.sort();
+        for (_, name) in entries {
+            if self.size <= max_size {
+                break;
+            }
+            self.remove(&name);
+            removed.push(name);
+        }
+        removed
+    }
+}
+
+impl MediaCache {
+    /// Open cache in `path`, restore index from existing files
+    pub fn new(path: &Path, max_size: u64) -> io::Result<MediaCache> {
+        fs::create_dir_all(path)?;
+        let mut files: Vec<(SystemTime, String, u64)> = Vec::new();
+        for entry in fs::read_dir(path)? {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            if !meta.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.ends_with(".tmp") {
+                // Недописанный файл
+                let _ = fs::remove_file(entry.path());
+                continue;
+            }
+            let modified = meta.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            files.push((modified, name, meta.len()));
+        }
+        // Older files will be evicted first
+        files.sort();
+        let mut index = CacheIndex::default();
+        for (_, name, size) in files {
+            index.insert(name, size);
+        }
+        let cache = MediaCache {
+            path: path.to_path_buf(),
+            max_size,
+            index: Mutex::new(CacheIndex::default()),
+        };
+        cache.evict(&mut index);
+        log::debug!(
+            "MediaCache path={:?} entries={} size={}",
+            path,
+            index.entries.len(),
+            index.size
+        );
+        *cache.index.lock().unwrap() = index;
+        Ok(cache)
+    }
+
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.path.join(name)
+    }
+
+    fn evict(&self, index: &mut CacheIndex) {
+        for name in index.evict(self.max_size) {
+            log::trace!("MediaCache evict {:?}", name);
+            let _ = fs::remove_file(self.entry_path(&name));
+        }
+    }
+
+    /// Cached response for media id with `Accept-Ranges: bytes`.
+    ///
+    /// Large entries are streamed from file, `Range` of them is served here
+    pub fn get(&self, key: &str, request: &HTTPRequest) -> Option<HTTPResponse> {
+        let name = hash_key(key);
+        if !self
+            .index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .touch(&name)
+        {
+            return None;
+        }
+        let (content_type, mut reader, len) = match self.open(key, &name) {
+            Ok(Some(entry)) => entry,
+            // Коллизия хеша
+            Ok(None) => return None,
+            Err(e) => {
+                log::warn!("MediaCache read fail key={:?} e={:?}", key, e);
+                self.index
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .remove(&name);
+                return None;
+            }
+        };
+        log::debug!("MediaCache hit key={:?}", key);
+        let response = if len <= MAX_BUFFERED_SIZE {
+            let mut content = Vec::with_capacity(len as usize);
+            reader.read_to_end(&mut content).ok()?;
+            HTTPResponse {
+                status: 200,
+                content: Some(Bytes::from(content)),
+                ..HTTPResponse::default()
+            }
+        } else {
+            // Валидаторов у файла нет, с If-Range отдаем целиком
+            let range = request
+                .header("Range")
+                .filter(|_| request.header("If-Range").is_none())
+                .and_then(|range| parse_range(range, len as usize));
+            match range {
+                None => HTTPResponse::with_stream(reader, Some(len)),
+                Some(Err(_)) => {
+                    return Some(
+                        HTTPResponse::new(416)
+                            .set_header("Content-Range", format!("bytes */{len}").as_str()),
+                    )
+                }
+                Some(Ok((start, end))) => {
+                    reader.seek(SeekFrom::Current(start as i64)).ok()?;
+                    let length = (end - start + 1) as u64;
+                    let mut response = HTTPResponse::with_stream(reader.take(length), Some(length))
+                        .set_header(
+                            "Content-Range",
+                            format!("bytes {start}-{end}/{len}").as_str(),
+                        );
+                    response.status = 206;
+                    response
+                }
+            }
+        };
+        Some(HTTPResponse {
+            content_type: (!content_type.is_empty()).then_some(content_type),
+            ..response.set_header("Accept-Ranges", "bytes")
+        })
+    }
+
+    /// Open entry file, returns content type, reader at start of content and its length
+    fn open(&self, key: &str, name: &str) -> io::Result<Option<(String, BufReader<File>, u64)>> {
+        let file = File::open(self.entry_path(name))?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut entry_key = Vec::new();
+        reader.read_until(b'\n', &mut entry_key)?;
+        if entry_key.strip_suffix(b"\n") != Some(key.as_bytes()) {
+            return Ok(None);
+        }
+        let mut content_type = String::new();
+        reader.read_line(&mut content_type)?;
+        let head_len = (entry_key.len() + content_type.len()) as u64;
+        let content_type = content_type.trim_end_matches('\n').to_string();
+        Ok(Some((
+            content_type,
+            reader,
+            file_len.saturating_sub(head_len),
+        )))
+    }
+
+    /// Temporary file for entry being written
+    fn tmp_path(&self, name: &str) -> PathBuf {
+        let tick = self
+            .index
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .next_tick();
+        self.entry_path(format!("{name}.{tick}.tmp").as_str())
+    }
+
+    /// Move written file to entry and add it to index
+    fn commit(&self, key: &str, name: String, tmp_path: &Path, size: u64) -> io::Result<()> {
+        fs::rename(tmp_path, self.entry_path(&name))?;
+        let mut index = self.index.lock().unwrap_or_else(|e| e.into_inner());
+        index.insert(name, size);
+        self.evict(&mut index);
+        log::debug!("MediaCache put key={:?} size={}", key, size);
+        Ok(())
+    }
+
+    /// Store successful response for media id
+    pub fn put(&self, key: &str, response: &HTTPResponse) -> io::Result<()> {
+        let Some(content) = response.content.as_ref() else {
+            return Ok(());
+        };
+        let content_type = response.content_type.as_deref().unwrap_or_default();
+        let size = (key.len() + content_type.len() + 2 + content.len()) as u64;
+        if response.status != 200 || size > self.max_size || key.contains('\n') {
+            return Ok(());
+        }
+        let name = hash_key(key);
+        let tmp_path = self.tmp_path(&name);
+
+        let mut data = Vec::with_capacity(size as usize);
+        data.extend_from_slice(key.as_bytes());
+        data.push(b'\n');
+        data.extend_from_slice(content_type.as_bytes());
+        data.push(b'\n');
+        data.extend_from_slice(content);
+        fs::write(&tmp_path, data)?;
+        self.commit(key, name, &tmp_path, size)
+    }
+
+    /// Store response for media id, streamed body is written to cache while it is sent to client
+    /// and stored only if read to the end
+    pub fn tee(self: &Arc<Self>, key: &str, mut response: HTTPResponse) -> HTTPResponse {
+        if response.content.is_some() {
+            if let Err(e) = self.put(key, &response) {
+                log::warn!("Media cache put failed: {:?}", e);
+            }
+            return response;
+        }
+        let content_type = response.content_type.as_deref().unwrap_or_default();
+        let size = (key.len() + content_type.len() + 2) as u64;
+        let Some(stream) = response.content_stream.as_mut() else {
+            return response;
+        };
+        if response.status != 200
+            || key.contains('\n')
+            || stream.length.is_some_and(|l| size + l > self.max_size)
+        {
+            return response;
+        }
+        let name = hash_key(key);
+        let tmp_path = self.tmp_path(&name);
+        let file = File::create(&tmp_path).and_then(|file| {
+            let mut file = BufWriter::new(file);
+            file.write_all(key.as_bytes())?;
+            file.write_all(b"\n")?;
+            file.write_all(content_type.as_bytes())?;
+            file.write_all(b"\n")?;
+            Ok(file)
+        });
+        let file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("Media cache put failed: {:?}", e);
+                let _ = fs::remove_file(&tmp_path);
+                return response;
+            }
+        };
+        let reader = std::mem::replace(&mut stream.reader, Box::new(io::empty()));
+        stream.reader = Box::new(TeeReader {
+            cache: Arc::clone(self),
+            key: key.to_string(),
+            name,
+            tmp_path,
+            file: Some(file),
+            size,
+            length: stream.length,
+            written: 0,
+            reader,
+        });
+        response
+    }
+}
+
+/// Reader of streamed body, copies read data to cache entry
+struct TeeReader {
+    cache: Arc<MediaCache>,
+    key: String,
+    name: String,
+    tmp_path: PathBuf,
+    /// `None` after entry is stored or failed
+    file: Option<BufWriter<File>>,
+    /// Size of entry with head
+    size: u64,
+    /// Expected length of content
+    length: Option<u64>,
+    written: u64,
+    reader: Box<dyn Read + Send>,
+}
+
+impl TeeReader {
+    fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(());
+        };
+        if data.is_empty() {
+            if self.length.is_some_and(|l| l != self.written) {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "Stream ended before length",
+                ));
+            }
+        } else {
+            self.written += data.len() as u64;
+            self.size += data.len() as u64;
+            if self.size > self.cache.max_size {
+                return Err(io::Error::other("Too large for cache"));
+            }
+            file.write_all(data)?;
+            // Ожидаемую длину дочитывать до EOF не будут
+            if self.length != Some(self.written) {
+                return Ok(());
+            }
+        }
+        let mut file = self.file.take().unwrap();
+        file.flush()?;
+        drop(file);
+        let name = self.name.clone();
+        self.cache
+            .commit(&self.key, name, &self.tmp_path, self.size)
+    }
+}
+
+impl Read for TeeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        if let Err(e) = self.write(&buf[..n]) {
+            log::debug!("MediaCache skip stream key={:?} e={:?}", self.key, e);
+            self.file = None;
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+        Ok(n)
+    }
+}
+
+impl Drop for TeeReader {
+    fn drop(&mut self) {
+        if self.file.take().is_some() {
+            // Клиент не дочитал
+            let _ = fs::remove_file(&self.tmp_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn cache_dir(name: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("any2feed_test_media_cache_{name}"));
+        let _ = fs::remove_dir_all(&path);
+        path
+    }
+
+    fn response(content: &str) -> HTTPResponse {
+        HTTPResponse::with_content(content).set_content_type("image/jpeg")
+    }
+
+    #[test]
+    fn test_put_get() {
+        let path = cache_dir("put_get");
+        let cache = MediaCache::new(&path, 1024).unwrap();
+        assert!(cache
+            .get("telegram/foo/1/0-url", &HTTPRequest::default())
+            .is_none());
+
+        cache.put("telegram/foo/1/0-url", &response("img")).unwrap();
+        let cached = cache
+            .get("telegram/foo/1/0-url", &HTTPRequest::default())
+            .unwrap();
+        assert_eq!(cached.status, 200);
+        assert_eq!(cached.content, Some(Bytes::from("img")));
+        assert_eq!(cached.content_type, Some("image/jpeg".to_string()));
+
+        // Persistent
+        let cache = MediaCache::new(&path, 1024).unwrap();
+        assert!(cache
+            .get("telegram/foo/1/0-url", &HTTPRequest::default())
+            .is_some());
+    }
+
+    #[test]
+    fn test_skip_not_ok() {
+        let path = cache_dir("skip_not_ok");
+        let cache = MediaCache::new(&path, 1024).unwrap();
+        let mut r = response("partial");
+        r.status = 206;
+        cache.put("foo", &r).unwrap();
+        assert!(cache.get("foo", &HTTPRequest::default()).is_none());
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let path = cache_dir("lru_eviction");
+        let content = "x".repeat(100);
+        // Each entry ~117 bytes, fits only 2
+        let cache = MediaCache::new(&path, 250).unwrap();
+        cache.put("key/1", &response(&content)).unwrap();
+        cache.put("key/2", &response(&content)).unwrap();
+        // key/1 used recently
+        assert!(cache.get("key/1", &HTTPRequest::default()).is_some());
+        cache.put("key/3", &response(&content)).unwrap();
+
+        assert!(cache.get("key/1", &HTTPRequest::default()).is_some());
+        assert!(cache.get("key/2", &HTTPRequest::default()).is_none());
+        assert!(cache.get("key/3", &HTTPRequest::default()).is_some());
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 2);
+    }
+
+    /// Content larger than buffered, bytes differ by position
+    fn large_content() -> Vec<u8> {
+        (0..MAX_BUFFERED_SIZE + 1000)
+            .map(|i| (i % 251) as u8)
+            .collect()
+    }
+
+    fn read_stream(response: HTTPResponse) -> Vec<u8> {
+        let mut content = Vec::new();
+        let mut stream = response.content_stream.unwrap();
+        stream.reader.read_to_end(&mut content).unwrap();
+        content
+    }
+
+    #[test]
+    fn test_tee_stream() {
+        let path = cache_dir("tee_stream");
+        let cache = Arc::new(MediaCache::new(&path, 16 * 1024 * 1024).unwrap());
+        let content = large_content();
+        let length = content.len() as u64;
+        let stream = |length| {
+            HTTPResponse::with_stream(io::Cursor::new(large_content()), length)
+                .set_content_type("video/mp4")
+        };
+
+        // Клиент не дочитал
+        let mut r = cache.tee("video/1", stream(Some(length)));
+        let mut partial = [0; 10];
+        r.content_stream
+            .as_mut()
+            .unwrap()
+            .reader
+            .read_exact(&mut partial)
+            .unwrap();
+        drop(r);
+        assert!(cache.get("video/1", &HTTPRequest::default()).is_none());
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 0);
+
+        let r = cache.tee("video/1", stream(Some(length)));
+        assert!(read_stream(r) == content);
+        let r = cache.tee("video/2", stream(None));
+        assert!(read_stream(r) == content);
+
+        let cached = cache.get("video/2", &HTTPRequest::default()).unwrap();
+        assert_eq!(cached.content_type, Some("video/mp4".to_string()));
+        assert_eq!(cached.header("Accept-Ranges"), Some("bytes"));
+        assert!(read_stream(cached) == content);
+
+        // Range из файла
+        let lines = vec![
+            "GET / HTTP/1.1".to_string(),
+            "Range: bytes=4194304-4194313".to_string(),
+        ];
+        let request = HTTPRequest::parse(&lines).unwrap();
+        let cached = cache.get("video/1", &request).unwrap();
+        assert_eq!(cached.status, 206);
+        assert_eq!(
+            cached.header("Content-Range"),
+            Some(format!("bytes 4194304-4194313/{length}").as_str())
+        );
+        assert_eq!(read_stream(cached), &content[4194304..=4194313]);
+    }
+}
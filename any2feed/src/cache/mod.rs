@@ -0,0 +1,18 @@
+//! Shared caches for feed sources
+use http_server::utils::fnv1a;
+
+pub mod feed;
+pub mod media;
+
+pub use feed::{cached, FeedCache, FeedCacheStorage};
+pub use media::MediaCache;
+
+/// Stable FNV-1a hash, used as file name for cache entries
+/// ```
+/// use any2feed::cache::hash_key;
+/// assert_eq!(hash_key("foo"), "dcb27518fed9d577");
+/// assert_ne!(hash_key("foo"), hash_key("bar"));
+/// ```
+pub fn hash_key(key: &str) -> String {
+    format!("{:016x}", fnv1a(key.as_bytes()))
+}
@@ -44,9 +44,15 @@ pub enum Commands {
 #[derive(Debug, Args)]
 #[command(author, version, about, long_about = None)]
 pub struct RunServer {
+    /// Server listen addresses, e.g. `0.0.0.0`, `[::]`, `192.168.1.2:8080`
+    #[arg(short, long, value_delimiter = ',')]
+    pub bind: Option<Vec<String>>,
     /// Server listen port
     #[arg(short, long)]
     pub port: Option<u16>,
+    /// Externally visible base url for feed links, e.g. `https://example.com/feeds/`
+    #[arg(long)]
+    pub public_url: Option<String>,
     /// Server num threads
     #[arg(long)]
     pub threads: Option<u8>,
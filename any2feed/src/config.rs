@@ -1,26 +1,182 @@
 use std::collections::HashMap;
 use std::env;
 use std::ffi::OsString;
+use std::fmt::{Display, Formatter};
 use std::fs::read_to_string;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 use clap::Parser;
+use http_server::{Auth, TlsConfig};
+use reqwest::Url;
 use serde::Deserialize;
 
+use crate::cache::feed::DEFAULT_MAX_ENTRIES;
+use crate::cache::{FeedCache, FeedCacheStorage, MediaCache};
 use crate::cli::{Commands, CLI};
+use crate::feed_sources::signed_url::{UrlSigner, DEFAULT_SIGNED_URL_TTL};
 use crate::feed_sources::{FeedSourceList, FeedSourceManager};
 use crate::logging;
 
+/// Invalid combination of options in config
+#[derive(Debug, PartialEq)]
+pub struct ConfigError(pub String);
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 #[derive(Debug, Default, Deserialize)]
 pub struct HttpServerConfig {
+    /// Listen addresses, default `127.0.0.1`
+    pub bind: Option<Vec<String>>,
     pub port: Option<u16>,
+    /// Externally visible base url, e.g. behind reverse proxy
+    pub public_url: Option<String>,
+    /// Build links from `X-Forwarded-*` headers of reverse proxy
+    pub trust_forwarded: Option<bool>,
     pub threads: Option<u8>,
+    /// Keep-alive idle timeout in seconds, 0 - disable
+    pub keep_alive: Option<u64>,
+    /// Time in seconds to finish in-flight requests on shutdown
+    pub shutdown_timeout: Option<u64>,
+    /// Time limit of request in seconds, 0 - no limit
+    pub request_timeout: Option<u64>,
+    /// PEM certificate chain for HTTPS, with `tls_key`
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    #[serde(flatten)]
+    pub auth: AuthConfig,
+    /// Secret for signing media proxy urls, random on each start if not set
+    pub url_secret: Option<String>,
+    /// Lifetime of signed media urls in seconds, 0 - never expire
+    pub signed_url_ttl: Option<u64>,
+}
+
+/// Access control in `[server]`, or override in feed source section
+#[derive(Debug, Default, Deserialize, PartialEq, Clone)]
+pub struct AuthConfig {
+    /// Basic auth, with `auth_password`
+    pub auth_user: Option<String>,
+    pub auth_password: Option<String>,
+    /// Secret for `?token=` query param, keeps working in feed readers
+    pub auth_token: Option<String>,
+}
+
+impl AuthConfig {
+    /// `None` if nothing configured, half of Basic pair is rejected by [`AuthConfig::validate`]
+    pub fn auth(&self) -> Option<Auth> {
+        let basic = self.auth_user.clone().zip(self.auth_password.clone());
+        let auth = Auth {
+            basic,
+            token: self.auth_token.clone(),
+        };
+        (!auth.is_public()).then_some(auth)
+    }
+
+    /// `section` is config section for error message
+    pub fn validate(&self, section: &str) -> Result<(), ConfigError> {
+        if self.auth_user.is_some() != self.auth_password.is_some() {
+            return Err(ConfigError(format!(
+                "Both {section}.auth_user and {section}.auth_password required for Basic auth"
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl HttpServerConfig {
+    /// Parsed `public_url`, invalid is rejected by [`MainConfig::validate`]
+    pub fn public_url(&self) -> Result<Option<Url>, ConfigError> {
+        self.public_url
+            .as_deref()
+            .map(|u| {
+                Url::parse(u)
+                    .map_err(|e| ConfigError(format!("Invalid server.public_url {u:?}: {e}")))
+            })
+            .transpose()
+    }
+
+    /// `None` if not configured, half of pair is rejected by [`MainConfig::validate`]
+    pub fn tls(&self) -> Option<TlsConfig> {
+        Some(TlsConfig {
+            cert: self.tls_cert.clone()?,
+            key: self.tls_key.clone()?,
+        })
+    }
+
+    pub fn url_signer(&self) -> UrlSigner {
+        let ttl = self
+            .signed_url_ttl
+            .map_or(DEFAULT_SIGNED_URL_TTL, Duration::from_secs);
+        match self.url_secret.as_ref() {
+            Some(secret) => UrlSigner::new(secret.as_bytes(), ttl),
+            None => {
+                log::warn!(
+                    "server.url_secret not set, media urls in feeds are valid until restart"
+                );
+                UrlSigner::default().with_ttl(ttl)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CacheConfig {
+    /// Cache directory, default `{temp_dir}/any2feed`
+    pub path: Option<PathBuf>,
+    /// Max size of media cache in megabytes, 0 - disable
+    pub media_max_size: Option<u64>,
+    /// Default ttl of rendered feeds in seconds, 0 - disable
+    pub feed_ttl: Option<u64>,
+    /// Store rendered feeds also on disk, survives restart
+    pub feed_on_disk: Option<bool>,
+    /// Max number of cached feeds, default 1000
+    pub feed_max_entries: Option<usize>,
+}
+
+impl CacheConfig {
+    pub fn path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| env::temp_dir().join("any2feed"))
+    }
+
+    pub fn media_cache(&self) -> Option<Arc<MediaCache>> {
+        let max_size = self.media_max_size.unwrap_or(512) * 1024 * 1024;
+        if max_size == 0 {
+            return None;
+        }
+        let path = self.path().join("media");
+        match MediaCache::new(&path, max_size) {
+            Ok(cache) => Some(Arc::new(cache)),
+            Err(e) => {
+                log::error!("Media cache {:?} init failed: {:?}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn feed_cache_storage(&self) -> Arc<FeedCacheStorage> {
+        let path = self
+            .feed_on_disk
+            .unwrap_or(false)
+            .then(|| self.path().join("feed"));
+        let max_entries = self.feed_max_entries.unwrap_or(DEFAULT_MAX_ENTRIES);
+        Arc::new(FeedCacheStorage::new(path, max_entries))
+    }
 }
 
 #[derive(Debug, Default, Deserialize)]
 pub struct MainConfig {
     // Make optional
     pub server: HttpServerConfig,
+    pub cache: Option<CacheConfig>,
     pub verbose: Option<u8>,
     pub log_file: Option<PathBuf>,
     pub config_text: Option<String>,
@@ -32,6 +188,14 @@ pub struct MainConfig {
 #[derive(Debug, Default, Deserialize, PartialEq)]
 pub struct FeedSourceOption {
     pub disable: Option<bool>,
+    /// Override `[cache] feed_ttl` for this source
+    pub cache_ttl: Option<u64>,
+    /// Disable `[server]` auth for this source
+    pub public: Option<bool>,
+    /// Respond with feed containing error entry instead of error status
+    pub error_feed: Option<bool>,
+    #[serde(flatten)]
+    pub auth: AuthConfig,
 }
 
 impl MainConfig {
@@ -41,10 +205,27 @@ impl MainConfig {
         config
     }
 
+    /// Check options which can't be checked by deserialize
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.server.tls_cert.is_some() != self.server.tls_key.is_some() {
+            return Err(ConfigError(
+                "Both server.tls_cert and server.tls_key required for HTTPS".to_string(),
+            ));
+        }
+        self.server.public_url()?;
+        self.server.auth.validate("server")?;
+        for (name, option) in &self.feed_sources {
+            option.auth.validate(name)?;
+        }
+        Ok(())
+    }
+
     fn merge_with_cli(mut self, cli: &CLI) -> Self {
         match &cli.command {
             Commands::Run(server_cfg) => {
+                self.server.bind = server_cfg.bind.clone().or(self.server.bind);
                 self.server.port = server_cfg.port;
+                self.server.public_url = server_cfg.public_url.clone().or(self.server.public_url);
                 self.server.threads = server_cfg.threads;
             }
         }
@@ -74,9 +255,44 @@ impl MainConfig {
         });
         sources.collect()
     }
+
+    /// Feed cache ttl for source, `None` if disabled
+    pub fn feed_cache_ttl(&self, name: &str) -> Option<Duration> {
+        let cache = self.cache.as_ref()?;
+        let ttl = self
+            .feed_sources
+            .get(name)
+            .and_then(|f_o| f_o.cache_ttl)
+            .or(cache.feed_ttl)
+            .unwrap_or(300);
+        (ttl > 0).then(|| Duration::from_secs(ttl))
+    }
+
+    /// Auth override for source routes, `None` - `[server]` auth is used
+    pub fn feed_source_auth(&self, name: &str) -> Option<Auth> {
+        let option = self.feed_sources.get(name)?;
+        if option.public.unwrap_or(false) {
+            return Some(Auth::default());
+        }
+        option.auth.auth()
+    }
+
+    /// Render errors of source feed routes as feed
+    pub fn feed_source_error_feed(&self, name: &str) -> bool {
+        self.feed_sources
+            .get(name)
+            .and_then(|f_o| f_o.error_feed)
+            .unwrap_or(false)
+    }
+
+    /// Feed cache for source with its ttl
+    pub fn feed_cache(&self, storage: &Arc<FeedCacheStorage>, name: &str) -> Option<FeedCache> {
+        self.feed_cache_ttl(name)
+            .map(|ttl| FeedCache::new(Arc::clone(storage), ttl))
+    }
 }
 
-pub fn load_config_from_args<I, T>(args: I) -> MainConfig
+pub fn load_config_from_args<I, T>(args: I) -> Result<MainConfig, ConfigError>
 where
     I: IntoIterator<Item = T>,
     T: Into<OsString> + Clone,
@@ -85,16 +301,17 @@ where
     // TODO find config in other locations
     let config_str = read_to_string(&cli.config).unwrap();
     let config: MainConfig = MainConfig::load(&config_str).merge_with_cli(&cli);
+    config.validate()?;
 
     logging::logging_init(&config);
     log::debug!("CLI: {:?}", &cli);
     log::debug!("CONFIG: {:?}", &config);
 
-    config
+    Ok(config)
 }
 
 /// load config and initialize logging
-pub fn load_config() -> MainConfig {
+pub fn load_config() -> Result<MainConfig, ConfigError> {
     load_config_from_args(env::args_os())
 }
 
@@ -116,13 +333,98 @@ threads = 5
         "#;
         write("/tmp/config.toml", config_str).unwrap();
         let args = "any2feed --config /tmp/config.toml run --port 123 --threads 10".split(' ');
-        let config = load_config_from_args(args);
+        let config = load_config_from_args(args).unwrap();
         assert_eq!(config.config_text, Some(config_str.to_string()));
         assert_eq!(config.verbose, Some(1));
         assert_eq!(config.server.port, Some(123));
         assert_eq!(config.server.threads, Some(10));
     }
 
+    #[test]
+    fn test_auth() {
+        let config_str = r#"
+        [server]
+        auth_user = "user"
+        auth_password = "pass"
+        [telegram]
+        public = true
+        [mewe]
+        auth_token = "secret"
+        [booru]
+        "#;
+        let c = MainConfig::load(config_str);
+        let auth = c.server.auth.auth().unwrap();
+        assert_eq!(auth.basic, Some(("user".to_string(), "pass".to_string())));
+        assert_eq!(auth.token, None);
+        assert!(c.feed_source_auth("telegram").unwrap().is_public());
+        let mewe = c.feed_source_auth("mewe").unwrap();
+        assert_eq!(mewe.basic, None);
+        assert_eq!(mewe.token, Some("secret".to_string()));
+        assert!(c.feed_source_auth("booru").is_none());
+        assert!(c.validate().is_ok());
+
+        let c = MainConfig::load("[server]\nauth_user = \"user\"");
+        assert_eq!(
+            c.validate().unwrap_err().to_string(),
+            "Both server.auth_user and server.auth_password required for Basic auth"
+        );
+        let c = MainConfig::load("[server]\n[mewe]\nauth_password = \"pass\"");
+        assert_eq!(
+            c.validate().unwrap_err().to_string(),
+            "Both mewe.auth_user and mewe.auth_password required for Basic auth"
+        );
+    }
+
+    #[test]
+    fn test_tls() {
+        let c = MainConfig::load("[server]");
+        assert!(c.validate().is_ok());
+        assert!(c.server.tls().is_none());
+
+        let c = MainConfig::load("[server]\ntls_cert = \"cert.pem\"\ntls_key = \"key.pem\"");
+        assert!(c.validate().is_ok());
+        let tls = c.server.tls().unwrap();
+        assert_eq!(tls.cert, PathBuf::from("cert.pem"));
+        assert_eq!(tls.key, PathBuf::from("key.pem"));
+
+        let c = MainConfig::load("[server]\ntls_cert = \"cert.pem\"");
+        assert_eq!(
+            c.validate().unwrap_err().to_string(),
+            "Both server.tls_cert and server.tls_key required for HTTPS"
+        );
+    }
+
+    #[test]
+    fn test_public_url() {
+        let c = MainConfig::load("[server]\npublic_url = \"https://example.com/feeds/\"");
+        assert!(c.validate().is_ok());
+        let url = c.server.public_url().unwrap().unwrap();
+        assert_eq!(url.as_str(), "https://example.com/feeds/");
+
+        let c = MainConfig::load("[server]\npublic_url = \"example.com\"");
+        assert_eq!(
+            c.validate().unwrap_err().to_string(),
+            "Invalid server.public_url \"example.com\": relative URL without a base"
+        );
+    }
+
+    #[test]
+    fn test_url_signer() {
+        let media_url = "http://cdn.com/1.jpg";
+        let c = MainConfig::load("[server]\nurl_secret = \"secret\"");
+        let mut url = reqwest::Url::parse("http://localhost/media/").unwrap();
+        c.server.url_signer().sign(&mut url, "1", media_url);
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        // Same secret after restart
+        let signer = MainConfig::load("[server]\nurl_secret = \"secret\"")
+            .server
+            .url_signer();
+        assert!(signer.verify("1", media_url, &query));
+        // Random secret
+        let signer = MainConfig::load("[server]").server.url_signer();
+        assert!(!signer.verify("1", media_url, &query));
+    }
+
     #[test]
     fn test_feed_sources() {
         let config_str = r#"
@@ -142,6 +444,77 @@ threads = 5
         assert_eq!(fs[0].name(), "telegram".to_string());
     }
 
+    #[test]
+    fn test_cache() {
+        let c = MainConfig::load("[server]");
+        assert!(c.cache.is_none());
+        assert!(!c.feed_sources.contains_key("cache"));
+
+        let c = MainConfig::load(
+            r#"
+        [server]
+        [cache]
+        path = "/tmp/any2feed_test_config_cache"
+        media_max_size = 0
+        "#,
+        );
+        let cache = c.cache.unwrap();
+        assert_eq!(
+            cache.path(),
+            PathBuf::from("/tmp/any2feed_test_config_cache")
+        );
+        assert!(cache.media_cache().is_none());
+    }
+
+    #[test]
+    fn test_feed_cache_ttl() {
+        let c = MainConfig::load("[server]\n[telegram]");
+        assert_eq!(c.feed_cache_ttl("telegram"), None);
+
+        let c = MainConfig::load(
+            r#"
+        [server]
+        [cache]
+        [telegram]
+        [mewe]
+        cache_ttl = 60
+        [booru]
+        cache_ttl = 0
+        "#,
+        );
+        assert_eq!(c.feed_cache_ttl("telegram"), Some(Duration::from_secs(300)));
+        assert_eq!(c.feed_cache_ttl("mewe"), Some(Duration::from_secs(60)));
+        assert_eq!(c.feed_cache_ttl("booru"), None);
+
+        let c = MainConfig::load(
+            r#"
+        [server]
+        [cache]
+        feed_ttl = 0
+        [telegram]
+        [mewe]
+        cache_ttl = 60
+        "#,
+        );
+        assert_eq!(c.feed_cache_ttl("telegram"), None);
+        assert_eq!(c.feed_cache_ttl("mewe"), Some(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn test_feed_source_error_feed() {
+        let c = MainConfig::load(
+            r#"
+        [server]
+        [telegram]
+        error_feed = true
+        [mewe]
+        "#,
+        );
+        assert!(c.feed_source_error_feed("telegram"));
+        assert!(!c.feed_source_error_feed("mewe"));
+        assert!(!c.feed_source_error_feed("booru"));
+    }
+
     #[test]
     fn test_feed_sources_disabled() {
         let config_str = r#"
@@ -1,7 +1,9 @@
+use crate::feed_sources::signed_url::UrlSigner;
+use crate::feed_sources::utils::generator;
 use ::feed::Entry;
 use booru_rs::client::generic::model::{Image, ImageSize};
 use booru_rs::client::generic::BooruPostModel;
-use chrono::Local;
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
 use feed::{Category, Content, Element, Feed, Link, Person};
 use reqwest::Url;
 use std::borrow::Cow;
@@ -10,10 +12,26 @@ use std::borrow::Cow;
 pub struct Context {
     pub proxy_url: Option<Url>,
     pub key: Option<String>,
+    /// Site key of media proxy route, see [`media_id`]
+    pub site: String,
+    pub signer: UrlSigner,
 }
 
-pub fn build_proxy_url(media_url: &str, mut proxy_url: Url) -> String {
+/// Signed id of media proxied for `site`.
+/// Not path of proxy url, it contains prefix of reverse proxy which route doesn't see
+pub fn media_id(site: &str) -> String {
+    format!("booru/{site}")
+}
+
+/// Signed proxy url of media from `site`
+pub fn build_proxy_url(
+    media_url: &str,
+    mut proxy_url: Url,
+    site: &str,
+    signer: &UrlSigner,
+) -> String {
     proxy_url.query_pairs_mut().append_pair("url", media_url);
+    signer.sign(&mut proxy_url, &media_id(site), media_url);
     proxy_url.to_string()
 }
 
@@ -25,10 +43,12 @@ pub fn danbooru_post_to_entry(post: Box<dyn BooruPostModel>, context: Option<&Co
         let sample = post.images().sample.unwrap().url;
         let sample = if let Some(Context {
             proxy_url: Some(proxy_url),
+            site,
+            signer,
             ..
         }) = context
         {
-            build_proxy_url(&sample, proxy_url.clone())
+            build_proxy_url(&sample, proxy_url.clone(), site, signer)
         } else {
             sample.to_string()
         };
@@ -82,14 +102,14 @@ pub fn danbooru_post_to_entry(post: Box<dyn BooruPostModel>, context: Option<&Co
         // For intersection feeds
         post_id.push_str(key)
     }
-    let mut entry = Entry::new(
-        post_id,
-        post.tags().join(" "),
-        post.created().unwrap().to_string(),
-    );
-    entry.published = post.created().map(|c| Element(c.to_string()));
+    // booru-rs отдает время из `time`, а не chrono
+    let created: Option<DateTime<FixedOffset>> = post
+        .created()
+        .map(|c| Utc.timestamp_opt(c.unix_timestamp(), 0).unwrap().into());
+    let mut entry = Entry::new(post_id, post.tags().join(" "), created.unwrap());
+    entry.published = created.map(Element);
     entry.content = Some(Content::Html(content));
-    entry.link = Some(Link::new(post.post_url().unwrap().into()));
+    entry.link = vec![Link::new(post.post_url().unwrap().into())];
     let categories: Vec<Category> = post
         .tags()
         .iter()
@@ -97,7 +117,7 @@ pub fn danbooru_post_to_entry(post: Box<dyn BooruPostModel>, context: Option<&Co
         .collect();
     entry.categories = Some(Element(categories));
     if let Some(artist) = post.artist() {
-        entry.author = Element(Person::new(artist.to_string(), None, None));
+        entry.author = vec![Person::new(artist.to_string(), None, None)];
     }
     entry
 }
@@ -109,9 +129,10 @@ pub fn booru_posts_to_feed(posts: Vec<Box<dyn BooruPostModel>>, context: Option<
         .collect();
 
     let mut feed = Feed {
-        updated: Local::now().to_rfc3339(),
+        entries: entry_list,
+        generator: Some(generator()),
         ..Feed::default()
     };
-    feed.entries = entry_list;
+    feed.set_updated_from_entries();
     feed
 }
@@ -1,3 +1,4 @@
+use crate::cache::FeedCache;
 use crate::feed_sources::booru::config::BooruConfig;
 use crate::feed_sources::booru::routes::{route_feed, route_media_proxy, route_opml};
 use ::feed::opml::Outline;
@@ -5,6 +6,7 @@ use http_server::Route;
 use std::sync::Arc;
 
 use crate::feed_sources::error::FeedSourceError;
+use crate::feed_sources::signed_url::UrlSigner;
 use crate::feed_sources::traits::FeedSource;
 
 pub mod config;
@@ -15,6 +17,10 @@ pub mod routes;
 #[derive(Debug, Default)]
 pub struct BooruFeedSource {
     pub(crate) config: Option<Arc<BooruConfig>>,
+    pub(crate) feed_cache: Option<FeedCache>,
+    /// Render errors of feed routes as feed
+    pub(crate) error_feed: bool,
+    pub(crate) url_signer: UrlSigner,
 }
 
 impl FeedSource for BooruFeedSource {
@@ -29,6 +35,18 @@ impl FeedSource for BooruFeedSource {
         Ok(())
     }
 
+    fn with_feed_cache(&mut self, cache: FeedCache) {
+        self.feed_cache = Some(cache);
+    }
+
+    fn with_error_feed(&mut self, enabled: bool) {
+        self.error_feed = enabled;
+    }
+
+    fn with_url_signer(&mut self, signer: UrlSigner) {
+        self.url_signer = signer;
+    }
+
     fn routes(&self) -> Vec<Route> {
         vec![route_feed(self), route_opml(self), route_media_proxy(self)]
     }
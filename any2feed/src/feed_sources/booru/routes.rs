@@ -1,11 +1,13 @@
+use crate::cache::cached;
 use crate::feed_sources::booru::config::BooruSiteConfig;
-use crate::feed_sources::booru::feed::{booru_posts_to_feed, Context};
+use crate::feed_sources::booru::feed::{booru_posts_to_feed, media_id, Context};
 use crate::feed_sources::booru::BooruFeedSource;
-use crate::feed_sources::utils::response_from_reqwest_response;
+use crate::feed_sources::error::{reqwest_error, upstream_status_error};
+use crate::feed_sources::utils::{feed_response, response_from_reqwest_response, with_error_feed};
 use booru_rs::client::generic::BooruOptionBuilder;
 use booru_rs::manager::Engine;
 use feed::opml::{Outline, OPML};
-use feed::{Attribute, CDATAElement, Element, Person};
+use feed::{Attribute, CDATAElement, Person};
 use http_server::{HTTPError, HTTPResponse, Route};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Url;
@@ -14,18 +16,15 @@ use std::sync::Arc;
 
 pub fn route_feed(feed_source: &BooruFeedSource) -> Route {
     let config = Arc::clone(feed_source.config.as_ref().unwrap());
-    Route::new("/booru/feed/(.+)/(.+)/", move |r| {
-        let key = r
-            .path_params
-            .as_ref()
-            .unwrap()
-            .get("1")
-            .unwrap()
-            .as_ref()
-            .unwrap();
-        let tag = r.path_params.as_ref().unwrap().get("2").unwrap().to_owned();
-
-        let config = config.sites.get(key).unwrap();
+    let signer = feed_source.url_signer.clone();
+    let view = cached(feed_source.feed_cache.clone(), move |r| {
+        let key: String = r.path_param("key")?;
+        let tag: Option<String> = r.path_param_opt("tag")?;
+
+        let config = config
+            .sites
+            .get(&key)
+            .ok_or_else(|| HTTPError::not_found(format!("Booru site {key} not configured")))?;
 
         let mut builder = config.engine.builder().proxy(config.proxy.as_ref());
 
@@ -52,34 +51,33 @@ pub fn route_feed(feed_source: &BooruFeedSource) -> Route {
             }
         }
 
-        let posts = builder.get();
+        let posts = builder.get().map_err(|e| {
+            HTTPError::bad_gateway(format!("Booru {key} unavailable")).with_cause(e)
+        })?;
 
-        if let Ok(posts) = posts {
-            let mut proxy_url: Option<Url> = None;
-            if config.proxy.is_some() {
-                let mut url = r.url();
-                url.set_path(format!("/booru/media/{key}/").as_str());
-                proxy_url = Some(url);
-            }
-            let context = Context {
-                proxy_url,
-                ..Context::default()
-            };
-            let mut feed = booru_posts_to_feed(posts, Some(&context));
-            let base_url = Url::parse(builder.base_url().as_str()).unwrap();
-            let host = base_url.host_str().unwrap().to_string();
-
-            feed.title = CDATAElement(host.clone());
-            feed.author = Element(Person::new(host, None, None));
-
-            let content = feed.to_string();
-            let response =
-                HTTPResponse::with_content(content.as_str()).set_content_type("text/xml");
-            Ok(response)
-        } else {
-            Err(HTTPError::InvalidRequest)
+        let mut proxy_url: Option<Url> = None;
+        if config.proxy.is_some() {
+            proxy_url = Some(r.absolute_url(format!("/booru/media/{key}/").as_str()));
         }
-    })
+        let context = Context {
+            proxy_url,
+            site: key.clone(),
+            signer: signer.clone(),
+            ..Context::default()
+        };
+        let mut feed = booru_posts_to_feed(posts, Some(&context));
+        let base_url = Url::parse(builder.base_url().as_str()).unwrap();
+        let host = base_url.host_str().unwrap().to_string();
+
+        feed.title = CDATAElement(host.clone());
+        feed.author = vec![Person::new(host, None, None)];
+
+        feed_response(r, &feed)
+    });
+    Route::new(
+        r"/booru/feed/{key}/{tag:.+}(?:/|\.rss)",
+        with_error_feed(feed_source.error_feed, view),
+    )
 }
 
 pub(crate) fn route_opml(feed_source: &BooruFeedSource) -> Route {
@@ -87,8 +85,6 @@ pub(crate) fn route_opml(feed_source: &BooruFeedSource) -> Route {
     Route::new("/booru.opml", move |r| {
         let capacity = config.sites.len();
         let mut outlines: Vec<Outline> = Vec::with_capacity(capacity);
-        let mut url = r.url();
-        url.set_path("/booru/feed");
         for (key, site) in config.sites.iter() {
             let site_title = site
                 .url
@@ -101,10 +97,10 @@ pub(crate) fn route_opml(feed_source: &BooruFeedSource) -> Route {
 
             let mut site_o = Outline::new(site_title.as_str());
             for (tag_key, _) in site.tags.iter() {
-                site_o.outlines.push(Outline::new(tag_key).add_child(
-                    &site_title,
-                    Some(format!("{url}/{key}/{tag_key}/").as_str()),
-                ))
+                let url = r.absolute_url(format!("/booru/feed/{key}/{tag_key}/").as_str());
+                site_o
+                    .outlines
+                    .push(Outline::new(tag_key).add_child(&site_title, Some(url.as_str())))
             }
             outlines.push(site_o)
         }
@@ -121,22 +117,27 @@ pub(crate) fn route_opml(feed_source: &BooruFeedSource) -> Route {
 
 pub fn route_media_proxy(feed_source: &BooruFeedSource) -> Route {
     let config = Arc::clone(feed_source.config.as_ref().unwrap());
-    Route::new(r#"/booru/media/(.+)/"#, move |r| {
-        let key = r
-            .path_params
-            .as_ref()
-            .unwrap()
-            .get("1")
-            .unwrap()
-            .as_ref()
-            .unwrap();
-
-        let media_url = Url::parse(r.query_params.get("url").unwrap()).map_err(|e| {
+    let signer = feed_source.url_signer.clone();
+    Route::new("/booru/media/{key}/", move |r| {
+        let key: String = r.path_param("key")?;
+
+        let Some(media_url) = r.query_params.get("url") else {
+            return Err(HTTPError::InvalidRequest);
+        };
+        // Проксируем только ссылки, которые сами отдали в ленте
+        if !signer.verify(&media_id(&key), media_url, &r.query_params) {
+            log::warn!("Booru media proxy: invalid signature key={:?}", key);
+            return Err(HTTPError::new(
+                403,
+                "Invalid or expired media url signature",
+            ));
+        }
+        let media_url = Url::parse(media_url).map_err(|e| {
             log::error!("{:?}", e);
             HTTPError::InvalidRequest
         })?;
 
-        let site: Option<&BooruSiteConfig> = config.sites.get(key);
+        let site: Option<&BooruSiteConfig> = config.sites.get(&key);
 
         let builder = if let Some(site) = site {
             site.engine.builder().proxy(site.proxy.as_ref())
@@ -160,14 +161,70 @@ pub fn route_media_proxy(feed_source: &BooruFeedSource) -> Route {
             .get(media_url.as_str())
             .headers(proxy_headers.clone())
             .send()
-            .unwrap();
+            .map_err(|e| reqwest_error(e, "Booru media"))?;
 
         match media_res.status().as_u16() {
             200..=299 => Ok(response_from_reqwest_response(media_res)),
-            _ => {
-                dbg!(&media_url, &media_res);
-                Err(HTTPError::InvalidRequest)
-            }
+            status => Err(upstream_status_error(status, "Booru media").with_cause(media_url)),
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed_sources::booru::feed::build_proxy_url;
+    use crate::feed_sources::traits::FeedSource;
+    use http_server::{HTTPRequest, ServerConfig};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_media_proxy_signature_behind_prefix() {
+        let mut feed_source = BooruFeedSource::default();
+        feed_source.with_config("[booru]").unwrap();
+        let route = route_media_proxy(&feed_source);
+
+        // Ссылка в ленте строится с префиксом реверс-прокси
+        let mut feed_request = HTTPRequest {
+            config: Some(Arc::new(ServerConfig {
+                trust_forwarded: true,
+                ..ServerConfig::default()
+            })),
+            ..HTTPRequest::default()
+        };
+        feed_request
+            .headers
+            .insert("Host".to_string(), "example.com".to_string());
+        feed_request
+            .headers
+            .insert("X-Forwarded-Prefix".to_string(), "/feeds".to_string());
+        let proxy_url = feed_request.absolute_url("/booru/media/danbooru/");
+        assert_eq!(proxy_url.path(), "/feeds/booru/media/danbooru/");
+        // Невалидный url, чтобы роут не ходил в сеть после проверки подписи
+        let url = build_proxy_url("invalid", proxy_url, "danbooru", &feed_source.url_signer);
+        let url = Url::parse(&url).unwrap();
+
+        // Прокси срезает префикс
+        let media_request = |query: HashMap<String, String>| HTTPRequest {
+            path: "/booru/media/danbooru/".to_string(),
+            path_params: Some(HashMap::from([(
+                "key".to_string(),
+                Some("danbooru".to_string()),
+            )])),
+            query_params: query,
+            ..HTTPRequest::default()
+        };
+        let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let error = route.run_cb(&media_request(query.clone())).unwrap_err();
+        assert_eq!(error.status(), 400);
+
+        let mut other_site = media_request(query);
+        other_site.path_params = Some(HashMap::from([(
+            "key".to_string(),
+            Some("gelbooru".to_string()),
+        )]));
+        let error = route.run_cb(&other_site).unwrap_err();
+        assert_eq!(error.status(), 403);
+    }
+}
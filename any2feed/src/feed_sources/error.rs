@@ -1,3 +1,4 @@
+use http_server::HTTPError;
 use toml::de::Error as TomlError;
 
 #[derive(Debug)]
@@ -22,3 +23,31 @@ impl From<TomlError> for FeedSourceError {
         }
     }
 }
+
+/// Error of upstream `status` as client error.
+/// Rejected credentials are upstream failure, 401 is only for auth of any2feed itself
+/// ```
+/// use any2feed::feed_sources::error::upstream_status_error;
+/// assert_eq!(upstream_status_error(404, "Telegram channel foo").status(), 404);
+/// assert_eq!(upstream_status_error(403, "Mewe").to_string(), "502 Bad Gateway: Mewe access denied, check credentials");
+/// assert_eq!(upstream_status_error(500, "Mewe").to_string(), "502 Bad Gateway: Mewe responded with status 500");
+/// ```
+pub fn upstream_status_error(status: u16, upstream: &str) -> HTTPError {
+    match status {
+        404 | 410 => HTTPError::not_found(format!("{upstream} not found")),
+        401 | 403 => HTTPError::bad_gateway(format!("{upstream} access denied, check credentials")),
+        _ => HTTPError::bad_gateway(format!("{upstream} responded with status {status}")),
+    }
+}
+
+/// Failed request to upstream as client error: timeout, status or unavailable
+pub fn reqwest_error(error: reqwest::Error, upstream: &str) -> HTTPError {
+    let http_error = if error.is_timeout() {
+        HTTPError::gateway_timeout(format!("{upstream} timed out"))
+    } else if let Some(status) = error.status() {
+        upstream_status_error(status.as_u16(), upstream)
+    } else {
+        HTTPError::bad_gateway(format!("{upstream} unavailable"))
+    };
+    http_error.with_cause(error)
+}
@@ -1,5 +1,8 @@
-use crate::feed_sources::error::{FeedSourceError, FeedSourceErrorKind};
-use mewe_api::MeweApiError;
+use crate::feed_sources::error::{
+    reqwest_error, upstream_status_error, FeedSourceError, FeedSourceErrorKind,
+};
+use http_server::HTTPError;
+use mewe_api::{ApiErrorKind, MeweApiError};
 
 impl From<MeweApiError> for FeedSourceError {
     fn from(value: MeweApiError) -> Self {
@@ -10,3 +13,17 @@ impl From<MeweApiError> for FeedSourceError {
         }
     }
 }
+
+/// Mewe api error as client error, expired session is 502
+pub fn mewe_http_error(error: MeweApiError, upstream: &str) -> HTTPError {
+    match error {
+        MeweApiError::ApiError {
+            kind: ApiErrorKind::StatusError(status),
+        } => upstream_status_error(status, upstream),
+        MeweApiError::ApiError {
+            kind: ApiErrorKind::IdentifyFail,
+        } => HTTPError::bad_gateway("Mewe session expired, update cookies"),
+        MeweApiError::ReqwestError(e) => reqwest_error(e, upstream),
+        e => HTTPError::bad_gateway(format!("{upstream} unexpected response")).with_cause(e),
+    }
+}
@@ -1,10 +1,11 @@
-use chrono::{Local, TimeZone, Utc};
+use chrono::{TimeZone, Utc};
 use mewe_api::Url;
-use regex::Regex;
+use regex::{Captures, Regex};
 
 use feed::{Attribute, CDATAElement, Category, Content, Element, Entry, Feed, Link, Person};
 
 use crate::feed_sources::traits::RenderContent;
+use crate::feed_sources::utils::generator;
 use mewe_api::json::{MeweApiFeedList, MeweApiPost};
 use mewe_api::utils::replace_user_mention_to_name;
 
@@ -23,9 +24,9 @@ pub fn mewe_post_to_entry(post: &MeweApiPost) -> Option<Entry> {
         post.edited_at
             .map(|e| Utc.timestamp_opt(e as i64, 0).unwrap())
             .unwrap_or(post.updated_at)
-            .to_rfc3339(),
+            .into(),
     );
-    entry.published = Some(Element(post.created_at.to_rfc3339()));
+    entry.published = Some(Element(post.created_at.into()));
     let mut categories: Vec<Category> = Vec::with_capacity(2);
     if let Some(hash_tags) = &post.hash_tags {
         let it = hash_tags.iter().map(|t| Category {
@@ -51,9 +52,9 @@ pub fn mewe_post_to_entry(post: &MeweApiPost) -> Option<Entry> {
         entry.content = Some(Content::Html(content));
     }
     if let Some(author) = author {
-        entry.author = Element(Person::new(author.name.clone(), None, None));
+        entry.author = vec![Person::new(author.name.clone(), None, None)];
     }
-    entry.link = post_url.map(Link::new);
+    entry.link = post_url.map(Link::new).into_iter().collect();
 
     if let Some(hash_tags) = &post.hash_tags {
         let categories = hash_tags
@@ -66,7 +67,7 @@ pub fn mewe_post_to_entry(post: &MeweApiPost) -> Option<Entry> {
 }
 
 // Быстрофункция чтобы добить наконец функциональность до смотрибельного
-pub fn mewe_feed_to_feed(feed_list: &Vec<MeweApiFeedList>) -> Option<Feed> {
+pub fn mewe_feed_to_feed(feed_list: &[MeweApiFeedList]) -> Option<Feed> {
     let mut entries: Vec<Entry> = Vec::with_capacity(feed_list.len() * 10);
     for list in feed_list.iter() {
         for post in list.feed.iter() {
@@ -75,18 +76,19 @@ pub fn mewe_feed_to_feed(feed_list: &Vec<MeweApiFeedList>) -> Option<Feed> {
         }
     }
 
-    let feed = Feed {
+    let mut feed = Feed {
         id: "https://mewe.com/myworld".to_string(),
         title: CDATAElement("Mewe feed".to_string()),
-        updated: Local::now().to_rfc3339(),
-        author: Element(Person {
+        author: vec![Person {
             name: "Mewe".to_string(),
             ..Person::default()
-        }),
+        }],
         entries,
         link: Vec::with_capacity(3),
+        generator: Some(generator()),
         ..Feed::default()
     };
+    feed.set_updated_from_entries();
     Some(feed)
 }
 
@@ -103,12 +105,30 @@ pub fn mewe_feed_to_feed(feed_list: &Vec<MeweApiFeedList>) -> Option<Feed> {
 /// </video>
 /// "#;
 /// assert_eq!(new_text, expect_text);
+///
+/// // Query of proxy url moved to the end
+/// let new_text = replace_mewe_media_urls(&text, &"http://127.0.0.1:12345/mewe/media?token=foo");
+/// assert!(new_text.contains("/mewe/media/api/v2/photo/c...0/200x300/img?static=0&mime=image/png&token=foo\""));
+/// assert!(new_text.contains("/gplus7.mp4?_dummy=1&token=foo\""));
+///
+/// // Escaped quotes of JSON Feed
+/// let text = r#"<img src=\"https://mewe.com/api/v2/photo/c...0/img\" />"#;
+/// let new_text = replace_mewe_media_urls(text, "http://127.0.0.1:12345/mewe/media");
+/// assert_eq!(new_text, r#"<img src=\"http://127.0.0.1:12345/mewe/media/api/v2/photo/c...0/img\" />"#);
 /// ```
 pub fn replace_mewe_media_urls(text: &str, new_url: &str) -> String {
-    let re =
-        Regex::new(r#"(?P<host>https://mewe.com)(?P<m>/api/v2/(?:photo|proxy/video|doc/shared)/)"#)
-            .unwrap();
-    let res = re.replace_all(text, &format!("{new_url}$m"));
+    let re = Regex::new(
+        r#"(?P<host>https://mewe.com)(?P<m>/api/v2/(?:photo|proxy/video|doc/shared)/[^"'\s<>\]\\]*)"#,
+    )
+    .unwrap();
+    let Some((new_url, query)) = new_url.split_once('?') else {
+        return re.replace_all(text, &format!("{new_url}$m")).to_string();
+    };
+    let res = re.replace_all(text, |c: &Captures| {
+        let m = &c["m"];
+        let sep = if m.contains('?') { '&' } else { '?' };
+        format!("{new_url}{m}{sep}{query}")
+    });
     res.to_string()
 }
 
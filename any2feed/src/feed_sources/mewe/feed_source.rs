@@ -3,6 +3,7 @@ use std::sync::Arc;
 
 use http_server::Route;
 
+use crate::cache::{FeedCache, MediaCache};
 use crate::feed_sources::error::FeedSourceError;
 use crate::feed_sources::mewe::config::Config;
 use crate::feed_sources::mewe::routes::{route_feed, route_media_proxy, route_opml};
@@ -12,6 +13,10 @@ use mewe_api::MeweApi;
 #[derive(Debug, Default)]
 pub struct MeweFeedSource {
     api: Option<Arc<MeweApi>>,
+    pub(crate) media_cache: Option<Arc<MediaCache>>,
+    pub(crate) feed_cache: Option<FeedCache>,
+    /// Render errors of feed routes as feed
+    pub(crate) error_feed: bool,
 }
 
 impl MeweFeedSource {
@@ -34,6 +39,18 @@ impl FeedSource for MeweFeedSource {
         Ok(())
     }
 
+    fn with_media_cache(&mut self, cache: Arc<MediaCache>) {
+        self.media_cache = Some(cache);
+    }
+
+    fn with_feed_cache(&mut self, cache: FeedCache) {
+        self.feed_cache = Some(cache);
+    }
+
+    fn with_error_feed(&mut self, enabled: bool) {
+        self.error_feed = enabled;
+    }
+
     fn routes(&self) -> Vec<Route> {
         vec![route_opml(self), route_feed(self), route_media_proxy(self)]
     }
@@ -1,33 +1,97 @@
+#[cfg(not(feature = "async"))]
+use crate::cache::cached;
+#[cfg(feature = "async")]
+use crate::cache::feed::cached_view_async;
+use crate::feed_sources::mewe::error::mewe_http_error;
 use crate::feed_sources::mewe::feed::{mewe_feed_to_feed, replace_mewe_media_urls};
 use crate::feed_sources::mewe::feed_source::MeweFeedSource;
+#[cfg(feature = "async")]
+use crate::feed_sources::utils::error_feed_result;
+#[cfg(not(feature = "async"))]
+use crate::feed_sources::utils::with_error_feed;
+use crate::feed_sources::utils::{
+    response_from_reqwest_response, set_feed_last_modified, with_accept_ranges, FeedFormat,
+};
 use feed::opml::{Outline, OPML};
 use feed::{CDATAElement, Link, LinkRel};
-use http_server::utils::path_params_to_vec;
-use http_server::HTTPError::{InvalidRequest, NotFound};
-use http_server::{HTTPError, HTTPResponse, Route};
-use mewe_api::json::{MeweApiFeedListNextPageLink, MeweApiHref};
+use http_server::HTTPError::NotFound;
+use http_server::{HTTPRequest, HTTPResponse, Route};
+use mewe_api::api::{API_MEWE_ALLFEED, API_MEWE_GROUP_FEED, API_MEWE_USER_FEED};
+use mewe_api::json::{MeweApiFeedList, MeweApiFeedListNextPageLink, MeweApiHref};
 use mewe_api::utils::update_query;
+#[cfg(feature = "async")]
+use mewe_api::AsyncMeweApi;
 use mewe_api::Url;
 use std::collections::HashMap;
+#[cfg(not(feature = "async"))]
 use std::thread;
 use std::time::Duration;
 
+const FEED_PATTERN: &str = r"/mewe/feed/{kind:me|user|group}(?:/{id:.+}|)(?:/|\.rss)";
+/// Pause before feed of user or group, after request of its info
+const FEED_DELAY: Duration = Duration::from_millis(100);
+
+enum FeedKind {
+    Me,
+    /// By invite id
+    User(String),
+    Group(String),
+}
+
+/// Params of feed route
+struct FeedParams {
+    kind: FeedKind,
+    /// Next page url from previous page
+    page_url: Option<String>,
+    limit: Option<usize>,
+    pages: Option<usize>,
+}
+
+impl FeedParams {
+    fn from_request(r: &HTTPRequest) -> http_server::Result<FeedParams> {
+        let kind: String = r.path_param("kind")?;
+        let id: Option<String> = r.path_param_opt("id")?;
+        let kind = match (kind.as_str(), id) {
+            ("me", _) => FeedKind::Me,
+            ("user", Some(invite_id)) => FeedKind::User(invite_id),
+            ("group", Some(id)) => FeedKind::Group(id),
+            _ => {
+                return Err(NotFound);
+            }
+        };
+        Ok(FeedParams {
+            kind,
+            page_url: r.query_params.get("page_url").cloned(),
+            limit: r.query_param("limit")?,
+            pages: r.query_param("pages")?,
+        })
+    }
+
+    /// Page of feed on mewe
+    fn rel_url(&self) -> String {
+        match &self.kind {
+            FeedKind::Me => "https://mewe.com/myworld".to_string(),
+            FeedKind::User(invite_id) => format!("https://mewe.com/i/{invite_id}"),
+            FeedKind::Group(id) => format!("https://mewe.com/group/{id}"),
+        }
+    }
+}
+
 pub fn route_opml(feed_source: &MeweFeedSource) -> Route {
     let mewe_api = feed_source.api();
     Route::new("/mewe.opml", move |r| {
-        let mut url = r.url();
-        url.set_path("/mewe/feed");
+        let url = |path: String| r.absolute_url(format!("/mewe/feed{path}").as_str());
 
         let mut groups = Outline::new("Groups");
         let groups_outlines = mewe_api
             .fetch_groups()
-            .unwrap()
+            .map_err(|e| mewe_http_error(e, "Mewe groups"))?
             .confirmed_groups
             .iter()
             .map(|g| {
                 Outline::new(g.name.as_str()).add_child(
                     g.name.as_str(),
-                    Some(format!("{}/group/{}/", url, g.id).as_str()),
+                    Some(url(format!("/group/{}/", g.id)).as_str()),
                 )
             })
             .collect();
@@ -36,12 +100,12 @@ pub fn route_opml(feed_source: &MeweFeedSource) -> Route {
         let mut users = Outline::new("Users");
         let users_outlines = mewe_api
             .get_contacts(true)
-            .unwrap()
+            .map_err(|e| mewe_http_error(e, "Mewe contacts"))?
             .iter()
             .map(|g| {
                 Outline::new(g.name.as_str()).add_child(
                     g.name.as_str(),
-                    Some(format!("{}/user/{}/", url, g.contact_invite_id).as_str()),
+                    Some(url(format!("/user/{}/", g.contact_invite_id)).as_str()),
                 )
             })
             .collect();
@@ -51,7 +115,7 @@ pub fn route_opml(feed_source: &MeweFeedSource) -> Route {
             Outline::new("Mewe feeds")
                 .add_outline(
                     Outline::new("Home feed")
-                        .add_child("Home feed", Some(format!("{url}/me/").as_str())),
+                        .add_child("Home feed", Some(url("/me/".to_string()).as_str())),
                 )
                 .add_outline(groups)
                 .add_outline(users),
@@ -62,139 +126,171 @@ pub fn route_opml(feed_source: &MeweFeedSource) -> Route {
     })
 }
 
+/// Feed response of fetched pages
+fn feed_response(
+    r: &HTTPRequest,
+    params: &FeedParams,
+    title: String,
+    mewe_feeds: &[MeweApiFeedList],
+) -> http_server::Result<HTTPResponse> {
+    let mut feeds = mewe_feed_to_feed(mewe_feeds).unwrap();
+
+    feeds.title = CDATAElement(title);
+
+    {
+        // Next page pagination
+        let next_page = mewe_feeds.last().and_then(|f| f.links.as_ref());
+        let mut req_url = r.url();
+        if let Some(MeweApiFeedListNextPageLink {
+            next_page: Some(MeweApiHref { href }),
+        }) = next_page
+        {
+            let href = format!("https://mewe.com{}", href);
+            let query = HashMap::from([("page_url", href.as_str())]);
+            update_query(&mut req_url, &query);
+            feeds
+                .link
+                .push(Link::with_rel(req_url.to_string(), LinkRel::Next))
+        }
+    }
+
+    let rel_url = params.rel_url();
+    let feed_type = {
+        let u = Url::parse(rel_url.as_str()).unwrap();
+        u.path().to_string()
+    };
+
+    feeds.link.push(Link::with_rel(rel_url, LinkRel::Alternate));
+    feeds
+        .link
+        .push(Link::with_rel(r.url().to_string(), LinkRel::_Self));
+
+    for entry in feeds.entries.iter_mut() {
+        // Делаем id уникальными в разрезе каждого фида,
+        // чтобы иметь возможность иметь дубликаты в разных фидах.
+        entry.id = format!("{}/{}", feed_type, entry.id);
+    }
+
+    let format = FeedFormat::from_request(r)?;
+    let res = format.render(&feeds);
+    let new_url = r.absolute_url("/mewe/media");
+    let res = replace_mewe_media_urls(res.as_str(), new_url.as_str());
+    let response = HTTPResponse::with_content(res.as_str()).set_content_type(format.content_type());
+    Ok(set_feed_last_modified(response, &feeds))
+}
+
+#[cfg(not(feature = "async"))]
 pub fn route_feed(feed_source: &MeweFeedSource) -> Route {
     let mewe_api = feed_source.api();
-    Route::new("/mewe/feed/(me|user|group)/(?:(.+)/|)", move |r| {
-        // TODO переработать эту простыню и покрыть тестами
-        let page_url = r.query_params.get("page_url");
-
-        let limit = r.query_params.get("limit").and_then(|l| l.parse().ok());
-        let pages = r.query_params.get("pages").and_then(|l| l.parse().ok());
-
-        let path_parts = path_params_to_vec(r.path_params.as_ref().unwrap());
-        let mut user_id: Option<String> = None;
-        let (rel_url, title) = match path_parts[1..=2] {
-            [Some("me"), ..] => (
-                "https://mewe.com/myworld".to_string(),
-                "Mewe me feed".to_string(),
-            ),
-            [Some("user"), Some(invite_id)] => {
-                if let Ok(info) = mewe_api.fetch_contact_info(invite_id) {
-                    user_id = Some(info.id); // Апи получения информации по id пользователя не нашел
-                    (format!("https://mewe.com/i/{invite_id}"), info.name)
-                } else {
-                    return Err(NotFound);
-                }
-            }
-            [Some("group"), Some(id)] => {
-                if let Ok(info) = mewe_api.fetch_group_info(id) {
-                    (format!("https://mewe.com/group/{id}"), info.name)
-                } else {
-                    return Err(NotFound);
-                }
+    let view = cached(feed_source.feed_cache.clone(), move |r| {
+        let params = FeedParams::from_request(r)?;
+        let (title, feed_url) = match &params.kind {
+            FeedKind::Me => ("Mewe me feed".to_string(), API_MEWE_ALLFEED.to_string()),
+            FeedKind::User(invite_id) => {
+                let info = mewe_api
+                    .fetch_contact_info(invite_id)
+                    .map_err(|e| mewe_http_error(e, &format!("Mewe user {invite_id}")))?;
+                // Апи получения информации по id пользователя не нашел
+                let url = API_MEWE_USER_FEED.replace("{user_id}", &info.id);
+                (info.name, url)
             }
-            _ => {
-                return Err(NotFound);
+            FeedKind::Group(id) => {
+                let info = mewe_api
+                    .fetch_group_info(id)
+                    .map_err(|e| mewe_http_error(e, &format!("Mewe group {id}")))?;
+                (info.name, API_MEWE_GROUP_FEED.replace("{group_id}", id))
             }
         };
 
-        let mewe_feeds = if let Some(next_page) = page_url {
+        let mewe_feeds = if let Some(next_page) = params.page_url.as_deref() {
             // Паджинация
-            mewe_api.fetch_feeds(next_page.as_str(), None, None)
+            mewe_api.fetch_feeds(next_page, None, None)
         } else {
-            if path_parts[1] != Some("me") {
+            if !matches!(params.kind, FeedKind::Me) {
                 // Немного подождем чтоб не мучать мивач
-                thread::sleep(Duration::from_millis(100));
-            }
-            match path_parts[1..=2] {
-                [Some("me"), ..] => mewe_api.get_my_feeds(limit, pages),
-                [Some("user"), Some(_id)] => {
-                    mewe_api.get_user_feed(user_id.unwrap().as_str(), limit, pages)
-                }
-                [Some("group"), Some(id)] => mewe_api.get_group_feed(id, limit, pages),
-                _ => {
-                    return Err(NotFound);
-                }
+                thread::sleep(FEED_DELAY);
             }
+            mewe_api.fetch_feeds(&feed_url, params.limit, params.pages)
         };
-        let Ok(mewe_feeds) = mewe_feeds else {
-            log::error!("Fetch mewe feed failed: {:?}", mewe_feeds);
-            return Err(InvalidRequest)
-        };
-        let mut feeds = mewe_feed_to_feed(&mewe_feeds).unwrap();
-
-        feeds.title = CDATAElement(title);
-
-        {
-            // Next page pagination
-            let next_page = mewe_feeds.last().and_then(|f| f.links.as_ref());
-            let mut req_url = r.url();
-            if let Some(MeweApiFeedListNextPageLink {
-                next_page: Some(MeweApiHref { href }),
-            }) = next_page
-            {
-                let href = format!("https://mewe.com{}", href);
-                let query = HashMap::from([("page_url", href.as_str())]);
-                update_query(&mut req_url, &query);
-                feeds
-                    .link
-                    .push(Link::with_rel(req_url.to_string(), LinkRel::Next))
-            }
-        }
+        let mewe_feeds = mewe_feeds.map_err(|e| mewe_http_error(e, "Mewe feed"))?;
+        feed_response(r, &params, title, &mewe_feeds)
+    });
+    Route::new(FEED_PATTERN, with_error_feed(feed_source.error_feed, view))
+}
 
-        let feed_type = {
-            let u = Url::parse(rel_url.as_str()).unwrap();
-            u.path().to_string()
-        };
+/// Feeds are fetched concurrently, async client shares session with blocking one
+#[cfg(feature = "async")]
+pub fn route_feed(feed_source: &MeweFeedSource) -> Route {
+    let mewe_api = AsyncMeweApi::from_blocking(&feed_source.api());
+    let cache = feed_source.feed_cache.clone();
+    let error_feed = feed_source.error_feed;
+    Route::new_async(FEED_PATTERN, move |r| {
+        let mewe_api = mewe_api.clone();
+        let cache = cache.clone();
+        async move {
+            let result = cached_view_async(cache.as_ref(), &r, || async {
+                let params = FeedParams::from_request(&r)?;
+                let (title, feed_url) = match &params.kind {
+                    FeedKind::Me => ("Mewe me feed".to_string(), API_MEWE_ALLFEED.to_string()),
+                    FeedKind::User(invite_id) => {
+                        let info = mewe_api
+                            .fetch_contact_info(invite_id)
+                            .await
+                            .map_err(|e| mewe_http_error(e, &format!("Mewe user {invite_id}")))?;
+                        let url = API_MEWE_USER_FEED.replace("{user_id}", &info.id);
+                        (info.name, url)
+                    }
+                    FeedKind::Group(id) => {
+                        let info = mewe_api
+                            .fetch_group_info(id)
+                            .await
+                            .map_err(|e| mewe_http_error(e, &format!("Mewe group {id}")))?;
+                        (info.name, API_MEWE_GROUP_FEED.replace("{group_id}", id))
+                    }
+                };
 
-        feeds.link.push(Link::with_rel(rel_url, LinkRel::Alternate));
-        feeds
-            .link
-            .push(Link::with_rel(r.url().to_string(), LinkRel::_Self));
-
-        for entry in feeds.entries.iter_mut() {
-            let mut u = r.url();
-            u.query_pairs_mut().clear();
-            // Делаем id уникальными в разрезе каждого фида,
-            // чтобы иметь возможность иметь дубликаты в разных фидах.
-            entry.id = format!("{}/{}", feed_type, entry.id);
+                let mewe_feeds = if let Some(next_page) = params.page_url.as_deref() {
+                    mewe_api.fetch_feeds(next_page, None, None).await
+                } else {
+                    if !matches!(params.kind, FeedKind::Me) {
+                        tokio::time::sleep(FEED_DELAY).await;
+                    }
+                    mewe_api
+                        .fetch_feeds(&feed_url, params.limit, params.pages)
+                        .await
+                };
+                let mewe_feeds = mewe_feeds.map_err(|e| mewe_http_error(e, "Mewe feed"))?;
+                feed_response(&r, &params, title, &mewe_feeds)
+            })
+            .await;
+            error_feed_result(error_feed, &r, result)
         }
-
-        let res = feeds.to_string();
-        let new_url = format!("http://{}/mewe/media", r.config.as_ref().unwrap().addr());
-        let res = replace_mewe_media_urls(res.as_str(), new_url.as_str());
-        let response = HTTPResponse::with_content(res.as_str()).set_content_type("text/xml");
-        Ok(response)
     })
 }
 
 pub fn route_media_proxy(feed_source: &MeweFeedSource) -> Route {
     let mewe_api = feed_source.api();
-    Route::new("/mewe/media/(.*)", move |r| {
-        let path = &r.path_params.as_ref().unwrap().get("1").unwrap();
-        let path = path.as_ref().unwrap();
-        let media_res = mewe_api.get(format!("https://mewe.com/{path}").as_str());
-        if let Ok(media_res) = media_res {
-            match media_res.status().as_u16() {
-                200 => {
-                    let media_headers: HashMap<String, String> = media_res
-                        .headers()
-                        .iter()
-                        .map(|(k, v)| (k.to_string(), v.to_str().unwrap().to_string()))
-                        .collect();
-                    let content_type = media_headers.get("content-type").cloned();
-                    Ok(HTTPResponse {
-                        status: 200,
-                        content: Some(media_res.bytes().unwrap()),
-                        content_type,
-                        headers: media_headers,
-                    })
-                }
-                404 => Err(NotFound),
-                _ => Err(HTTPError::InvalidRequest),
-            }
-        } else {
-            Err(HTTPError::InvalidRequest)
+    let media_cache = feed_source.media_cache.clone();
+    Route::new("/mewe/media/{path:.*}", move |r| {
+        let path: String = r.path_param("path")?;
+        let cache_key = format!("mewe/{path}");
+        if let Some(response) = media_cache.as_ref().and_then(|c| c.get(&cache_key, r)) {
+            return Ok(response);
+        }
+        // Range пробрасываем, большие видео отдаем потоком
+        let range_headers: Vec<(&str, &str)> = ["Range", "If-Range"]
+            .into_iter()
+            .filter_map(|h| r.header(h).map(|v| (h, v)))
+            .collect();
+        let media_url = format!("https://mewe.com/{path}");
+        // Ошибочные статусы api возвращает как ошибку
+        let media_res = mewe_api
+            .get_with_headers(media_url.as_str(), &range_headers)
+            .map_err(|e| mewe_http_error(e, "Mewe media"))?;
+        let mut response = response_from_reqwest_response(media_res);
+        if let Some(cache) = media_cache.as_ref() {
+            response = cache.tee(&cache_key, response);
         }
+        Ok(with_accept_ranges(response))
     })
 }
@@ -4,6 +4,7 @@ use crate::feed_sources::telegram::TelegramFeedSource;
 use crate::feed_sources::traits::FeedSource;
 
 pub mod error;
+pub mod signed_url;
 pub mod traits;
 pub mod utils;
 
@@ -0,0 +1,178 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::Arc;
+use std::time::Duration;
+
+use hmac::{Hmac, Mac};
+use reqwest::Url;
+use sha2::Sha256;
+
+use crate::feed_sources::utils::timestamp_now;
+
+/// Default lifetime of signed media urls, feed readers load images much later
+pub const DEFAULT_SIGNED_URL_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Signs media proxy urls, so proxy routes serve only urls generated by feeds.
+///
+/// Signature is HMAC-SHA256 over media id, target url and expiry,
+/// passed in `exp` and `sig` query params
+#[derive(Clone)]
+pub struct UrlSigner {
+    key: Arc<[u8]>,
+    /// Zero - never expire
+    ttl: Duration,
+}
+
+impl Debug for UrlSigner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UrlSigner").field("ttl", &self.ttl).finish()
+    }
+}
+
+impl Default for UrlSigner {
+    /// Random secret, urls are valid until restart
+    fn default() -> Self {
+        // Без зависимости от rand: RandomState инициализируется случайным ключом ОС
+        let key: Vec<u8> = (0..4)
+            .flat_map(|i| {
+                let mut hasher = RandomState::new().build_hasher();
+                hasher.write_u64(i ^ timestamp_now());
+                hasher.finish().to_le_bytes()
+            })
+            .collect();
+        UrlSigner::new(&key, DEFAULT_SIGNED_URL_TTL)
+    }
+}
+
+impl UrlSigner {
+    pub fn new(secret: &[u8], ttl: Duration) -> UrlSigner {
+        UrlSigner {
+            key: Arc::from(secret),
+            ttl,
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> UrlSigner {
+        self.ttl = ttl;
+        self
+    }
+
+    fn signature(&self, id: &str, target: &str, expires: u64) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.key).unwrap();
+        mac.update(format!("{id}\n{target}\n{expires}").as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Expiry rounded up to day, so rendered feed stays the same during the day
+    fn expires(&self) -> u64 {
+        if self.ttl.is_zero() {
+            return 0;
+        }
+        (timestamp_now() + self.ttl.as_secs()) / DAY * DAY + DAY
+    }
+
+    /// Add `exp` and `sig` params to `proxy_url` of `target` media with `id`
+    pub fn sign(&self, proxy_url: &mut Url, id: &str, target: &str) {
+        let expires = self.expires();
+        let signature = self.signature(id, target, expires);
+        proxy_url
+            .query_pairs_mut()
+            .append_pair("exp", expires.to_string().as_str())
+            .append_pair("sig", signature.as_str());
+    }
+
+    /// Check signature from query params of proxy request
+    /// ```
+    /// use std::collections::HashMap;
+    /// use std::time::Duration;
+    /// use reqwest::Url;
+    /// use any2feed::feed_sources::signed_url::UrlSigner;
+    ///
+    /// let signer = UrlSigner::new(b"secret", Duration::from_secs(60));
+    /// let mut url = Url::parse("http://localhost/media/?url=https://cdn.com/1.jpg").unwrap();
+    /// signer.sign(&mut url, "1", "https://cdn.com/1.jpg");
+    /// let query: HashMap<String, String> = url.query_pairs().into_owned().collect();
+    ///
+    /// assert!(signer.verify("1", "https://cdn.com/1.jpg", &query));
+    /// assert!(!signer.verify("2", "https://cdn.com/1.jpg", &query));
+    /// assert!(!signer.verify("1", "https://evil.com/1.jpg", &query));
+    /// assert!(!UrlSigner::new(b"other", Duration::ZERO).verify("1", "https://cdn.com/1.jpg", &query));
+    /// assert!(!signer.verify("1", "https://cdn.com/1.jpg", &HashMap::new()));
+    /// ```
+    pub fn verify(&self, id: &str, target: &str, query: &HashMap<String, String>) -> bool {
+        let (Some(expires), Some(signature)) = (
+            query.get("exp").and_then(|e| e.parse::<u64>().ok()),
+            query.get("sig"),
+        ) else {
+            return false;
+        };
+        if expires != 0 && expires < timestamp_now() {
+            return false;
+        }
+        let expected = self.signature(id, target, expires);
+        // Сравнение за постоянное время
+        expected.len() == signature.len()
+            && expected
+                .bytes()
+                .zip(signature.bytes())
+                .fold(0, |acc, (a, b)| acc | (a ^ b))
+                == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_query(signer: &UrlSigner) -> HashMap<String, String> {
+        let mut url = Url::parse("http://localhost/media/").unwrap();
+        signer.sign(&mut url, "id", "http://cdn.com/1.jpg");
+        url.query_pairs().into_owned().collect()
+    }
+
+    #[test]
+    fn test_expires() {
+        let signer = UrlSigner::new(b"secret", Duration::from_secs(60));
+        let query = signed_query(&signer);
+        let expires: u64 = query.get("exp").unwrap().parse().unwrap();
+        assert_eq!(expires % DAY, 0);
+        assert!(expires > timestamp_now() + 60);
+
+        // Tampered expiry
+        let mut tampered = query.clone();
+        tampered.insert("exp".to_string(), (expires + DAY).to_string());
+        assert!(!signer.verify("id", "http://cdn.com/1.jpg", &tampered));
+
+        // Expired
+        let mut expired = query;
+        expired.insert("exp".to_string(), "1".to_string());
+        expired.insert(
+            "sig".to_string(),
+            signer.signature("id", "http://cdn.com/1.jpg", 1),
+        );
+        assert!(!signer.verify("id", "http://cdn.com/1.jpg", &expired));
+    }
+
+    #[test]
+    fn test_never_expire() {
+        let signer = UrlSigner::new(b"secret", Duration::ZERO);
+        let query = signed_query(&signer);
+        assert_eq!(query.get("exp"), Some(&"0".to_string()));
+        assert!(signer.verify("id", "http://cdn.com/1.jpg", &query));
+    }
+
+    #[test]
+    fn test_random_secret() {
+        let query = signed_query(&UrlSigner::default());
+        assert!(!UrlSigner::default().verify("id", "http://cdn.com/1.jpg", &query));
+    }
+}
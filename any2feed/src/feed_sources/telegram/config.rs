@@ -53,6 +53,11 @@ impl Config {
             pages: config_toml.pages,
         }
     }
+
+    /// Num pages for channel, per channel config overrides default
+    pub fn channel_pages(&self, slug: &str) -> Option<usize> {
+        self.channels.get(slug).and_then(|c| c.pages).or(self.pages)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -134,6 +139,9 @@ mod test {
         let config = Config::load(config_str);
         dbg!(&config);
         assert_eq!(config.pages, Some(1));
+        assert_eq!(config.channel_pages("channel_name"), Some(5));
+        assert_eq!(config.channel_pages("channel_name_2"), Some(1));
+        assert_eq!(config.channel_pages("unknown_channel"), Some(1));
         // TODO compare 2 hashmap
         // assert_eq!(config.channels, HashMap::from([
         //     ("foo_channel", ExtraChannelConfig::default()),
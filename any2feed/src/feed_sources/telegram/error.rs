@@ -0,0 +1,15 @@
+use crate::feed_sources::error::{reqwest_error, upstream_status_error};
+use http_server::HTTPError;
+use telegram::error::TelegramApiError;
+use telegram::TelegramApiErrorKind;
+
+/// Telegram preview api error as client error
+pub fn telegram_http_error(error: TelegramApiError, upstream: &str) -> HTTPError {
+    match error {
+        TelegramApiError::ApiError {
+            kind: TelegramApiErrorKind::StatusError(status),
+        } => upstream_status_error(status, upstream),
+        TelegramApiError::ReqwestError(e) => reqwest_error(e, upstream),
+        e => HTTPError::bad_gateway(format!("{upstream} unexpected response")).with_cause(e),
+    }
+}
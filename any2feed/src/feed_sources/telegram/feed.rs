@@ -1,31 +1,52 @@
+use crate::feed_sources::signed_url::UrlSigner;
 use crate::feed_sources::traits::RenderContent;
-use crate::feed_sources::utils::timestamp_now;
+use crate::feed_sources::utils::{generator, timestamp_now};
 use chrono::Local;
-use feed::{CDATAElement, Content, Element, Entry, Feed, Link, Person};
+use feed::{CDATAElement, Content, Entry, Feed, Link, Person};
 use reqwest::Url;
 use telegram::data::{Channel, ChannelPost, Media};
 
 pub struct Context {
     pub proxy_url: Url,
+    pub signer: UrlSigner,
 }
 
-/// Меняем у поста все медиа ссылки на прокси перед отрисовкой
-pub fn set_proxy_url(mut post: ChannelPost, proxy_url: &Url) -> ChannelPost {
+/// Меняем у поста все медиа ссылки на подписанные ссылки прокси перед отрисовкой
+pub fn set_proxy_url(mut post: ChannelPost, proxy_url: &Url, signer: &UrlSigner) -> ChannelPost {
     let mut url = proxy_url.clone();
     url.path_segments_mut().unwrap().extend(post.id.split('/'));
     url.query_pairs_mut()
         .append_pair("t", timestamp_now().to_string().as_str());
+    let post_id = post.id.clone();
+    let build_media_url = |media_url: &str, i: usize, field: &str| {
+        let mut url = url.clone();
+        url.query_pairs_mut().append_pair("url", media_url);
+        url.path_segments_mut()
+            .unwrap()
+            .push(format!("{i}-{field}").as_str())
+            .push("");
+        // id совпадает с путем, который проверяет роут прокси
+        signer.sign(
+            &mut url,
+            format!("{post_id}/{i}-{field}").as_str(),
+            media_url,
+        );
+        url.to_string()
+    };
     let mut medias = post.get_media_list_mut();
     for (i, media) in medias.iter_mut().enumerate() {
-        media_set_proxy_urls(&url, media, i);
+        media_set_proxy_urls(&build_media_url, media, i);
     }
     return post;
 
-    fn media_set_proxy_urls(proxy_url: &Url, media: &mut Media, i: usize) {
+    fn media_set_proxy_urls<F>(build_media_url: &F, media: &mut Media, i: usize)
+    where
+        F: Fn(&str, usize, &str) -> String,
+    {
         use Media::*;
         match media {
             Photo(ref mut url) | Voice(ref mut url) => {
-                *url = build_media_url(proxy_url.clone(), url, i, "url").to_string();
+                *url = build_media_url(url, i, "url");
             }
             Video {
                 ref mut url,
@@ -35,27 +56,39 @@ pub fn set_proxy_url(mut post: ChannelPost, proxy_url: &Url) -> ChannelPost {
                 ref mut url,
                 ref mut thumb_url,
             } => {
-                *url = build_media_url(proxy_url.clone(), url, i, "url").to_string();
-                *thumb_url =
-                    build_media_url(proxy_url.clone(), thumb_url, i, "thumb_url").to_string();
+                *url = build_media_url(url, i, "url");
+                *thumb_url = build_media_url(thumb_url, i, "thumb_url");
             }
             VideoTooBig { ref mut thumb_url } => {
-                *thumb_url =
-                    build_media_url(proxy_url.clone(), thumb_url, i, "thumb_url").to_string();
+                *thumb_url = build_media_url(thumb_url, i, "thumb_url");
             }
         }
     }
+}
 
-    fn build_media_url(mut proxy_url: Url, url: &str, i: usize, prefix: &str) -> Url {
-        proxy_url.query_pairs_mut().append_pair("url", url);
-        proxy_url
-            .path_segments_mut()
-            .unwrap()
-            .push(format!("{i}-{prefix}").as_str())
-            .push("");
-
-        proxy_url
-    }
+/// Url of next (older) page of paged feed
+/// https://www.rfc-editor.org/rfc/rfc5005#section-3
+/// ```
+/// use any2feed::feed_sources::telegram::feed::next_page_url;
+/// use reqwest::Url;
+/// let url = Url::parse("http://localhost/telegram/feed/foo/?before=20&bar=1").unwrap();
+/// assert_eq!(
+///     next_page_url(url, 10).as_str(),
+///     "http://localhost/telegram/feed/foo/?bar=1&before=10"
+/// );
+/// ```
+pub fn next_page_url(mut feed_url: Url, before: usize) -> Url {
+    let query: Vec<(String, String)> = feed_url
+        .query_pairs()
+        .filter(|(k, _)| k != "before")
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    feed_url
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(query)
+        .append_pair("before", before.to_string().as_str());
+    feed_url
 }
 
 pub fn channel_post_to_entry(post: ChannelPost, context: Option<&Context>) -> Entry {
@@ -66,14 +99,26 @@ pub fn channel_post_to_entry(post: ChannelPost, context: Option<&Context>) -> En
         .collect::<Vec<_>>()
         .join(" ");
 
-    let mut entry = Entry::new(post.id.clone(), title, post.datetime.clone());
+    let updated = post.datetime.parse().unwrap_or_else(|e| {
+        log::warn!("Invalid post datetime {:?}: {e}", post.datetime);
+        Local::now().into()
+    });
+    let mut entry = Entry::new(post.id.clone(), title, updated);
 
-    entry.link = Some(Link::new(post.preview_url()));
-    let post = if let Some(Context { proxy_url }) = context {
-        set_proxy_url(post, proxy_url)
+    entry.link = vec![Link::new(post.preview_url())];
+    let post = if let Some(Context { proxy_url, signer }) = context {
+        set_proxy_url(post, proxy_url, signer)
     } else {
         post
     };
+    // Голосовые и видео как вложения, для подкаст приложений
+    entry
+        .link
+        .extend(post.get_media_list().into_iter().filter_map(|m| match m {
+            Media::Voice(url) => Some(Link::enclosure(url.clone(), "audio/ogg", None)),
+            Media::Video { url, .. } => Some(Link::enclosure(url.clone(), "video/mp4", None)),
+            _ => None,
+        }));
 
     entry.content = Some(Content::Html(post.render().unwrap()));
 
@@ -86,14 +131,14 @@ pub fn channel_post_to_entry(post: ChannelPost, context: Option<&Context>) -> En
 pub fn channel_to_feed(channel: &Channel, context: Option<&Context>) -> Feed {
     let mut feed = Feed {
         title: CDATAElement(channel.title.clone()),
-        updated: Local::now().to_rfc3339(),
-        author: Element(Person::new(
+        author: vec![Person::new(
             channel.title.clone(),
             Some(channel.preview_url()),
             None,
-        )),
+        )],
         // todo logo
         subtitle: Some(Content::Text(channel.description.clone())),
+        generator: Some(generator()),
         ..Feed::default()
     };
     feed.link.push(Link::new(channel.preview_url()));
@@ -102,13 +147,14 @@ pub fn channel_to_feed(channel: &Channel, context: Option<&Context>) -> Feed {
         .iter()
         .map(|p| {
             let mut e = channel_post_to_entry(p.clone(), context);
-            e.author = Element(Person::new(
+            e.author = vec![Person::new(
                 channel.title.clone(),
                 Some(channel.preview_url()),
                 None,
-            ));
+            )];
             e
         })
         .collect();
+    feed.set_updated_from_entries();
     feed
 }
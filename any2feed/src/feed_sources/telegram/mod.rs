@@ -1,9 +1,12 @@
 mod config;
+mod error;
 pub mod feed;
 mod render;
 mod routes;
 
+use crate::cache::{FeedCache, MediaCache};
 use crate::feed_sources::error::FeedSourceError;
+use crate::feed_sources::signed_url::UrlSigner;
 use crate::feed_sources::telegram::config::Config;
 use crate::feed_sources::telegram::routes::{route_feed, route_media_proxy, route_opml};
 use crate::feed_sources::traits::FeedSource;
@@ -14,6 +17,11 @@ use std::sync::Arc;
 #[derive(Debug, Default)]
 pub struct TelegramFeedSource {
     pub(crate) config: Option<Arc<Config>>,
+    pub(crate) media_cache: Option<Arc<MediaCache>>,
+    pub(crate) feed_cache: Option<FeedCache>,
+    /// Render errors of feed routes as feed
+    pub(crate) error_feed: bool,
+    pub(crate) url_signer: UrlSigner,
 }
 
 impl FeedSource for TelegramFeedSource {
@@ -29,6 +37,22 @@ impl FeedSource for TelegramFeedSource {
         Ok(())
     }
 
+    fn with_media_cache(&mut self, cache: Arc<MediaCache>) {
+        self.media_cache = Some(cache);
+    }
+
+    fn with_feed_cache(&mut self, cache: FeedCache) {
+        self.feed_cache = Some(cache);
+    }
+
+    fn with_error_feed(&mut self, enabled: bool) {
+        self.error_feed = enabled;
+    }
+
+    fn with_url_signer(&mut self, signer: UrlSigner) {
+        self.url_signer = signer;
+    }
+
     fn routes(&self) -> Vec<Route> {
         vec![route_feed(self), route_opml(self), route_media_proxy(self)]
     }
@@ -1,44 +1,109 @@
-use crate::feed_sources::telegram::feed::{channel_to_feed, Context};
+#[cfg(not(feature = "async"))]
+use crate::cache::cached;
+#[cfg(feature = "async")]
+use crate::cache::feed::cached_view_async;
+use crate::feed_sources::error::{reqwest_error, upstream_status_error};
+use crate::feed_sources::signed_url::UrlSigner;
+use crate::feed_sources::telegram::error::telegram_http_error;
+use crate::feed_sources::telegram::feed::{channel_to_feed, next_page_url, Context};
 use crate::feed_sources::telegram::TelegramFeedSource;
-use crate::feed_sources::utils::response_from_reqwest_response;
+#[cfg(feature = "async")]
+use crate::feed_sources::utils::error_feed_result;
+#[cfg(not(feature = "async"))]
+use crate::feed_sources::utils::with_error_feed;
+use crate::feed_sources::utils::{
+    feed_response, response_from_reqwest_response, with_accept_ranges,
+};
 use feed::opml::{Outline, OPML};
-use feed::Attribute;
-use http_server::utils::path_params_to_vec;
-use http_server::HTTPError::NotFound;
-use http_server::{HTTPError, HTTPResponse, Route};
+use feed::{Attribute, Link, LinkRel};
+use http_server::{HTTPError, HTTPRequest, HTTPResponse, Route};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Method;
 use std::str::FromStr;
 use std::sync::Arc;
+use telegram::data::Channel;
 use telegram::preview_api::TelegramChannelPreviewApi;
+#[cfg(feature = "async")]
+use telegram::preview_api_async::AsyncTelegramChannelPreviewApi;
 
+/// Feed response of fetched channel
+fn channel_response(
+    r: &HTTPRequest,
+    channel: &Channel,
+    upstream: &str,
+    signer: &UrlSigner,
+) -> http_server::Result<HTTPResponse> {
+    if channel.title.is_empty() && channel.posts.is_empty() {
+        // Для несуществующего канала телеграм отдает страницу без превью
+        return Err(HTTPError::not_found(format!(
+            "{upstream} not found or has no public preview"
+        )));
+    }
+    let proxy_url = r.absolute_url("/telegram/media");
+
+    let context = Context {
+        proxy_url,
+        signer: signer.clone(),
+    };
+    let mut feed = channel_to_feed(channel, Some(&context));
+    if let Some(before) = channel.before {
+        feed.link.push(Link::with_rel(
+            next_page_url(r.url(), before).to_string(),
+            LinkRel::Next,
+        ));
+    }
+    feed.link
+        .push(Link::with_rel(r.url().to_string(), LinkRel::_Self));
+    feed_response(r, &feed)
+}
+
+#[cfg(not(feature = "async"))]
 pub fn route_feed(feed_source: &TelegramFeedSource) -> Route {
     let config = Arc::clone(feed_source.config.as_ref().unwrap());
-    Route::new("/telegram/feed/(.+)/", move |r| {
-        // TODO надо что то сделать с этой цепочкой, не очень красиво
-        let channel_slug = r
-            .path_params
-            .as_ref()
-            .unwrap()
-            .get("1")
-            .unwrap()
-            .as_ref()
-            .unwrap();
+    let signer = feed_source.url_signer.clone();
+    let view = cached(feed_source.feed_cache.clone(), move |r| {
+        let channel_slug: String = r.path_param("slug")?;
+        let before: Option<usize> = r.query_param("before")?;
         let api = TelegramChannelPreviewApi::new(channel_slug.as_str());
-        let channel = api.fetch(config.pages);
-        if let Ok(channel) = channel {
-            let mut proxy_url = r.url();
-            proxy_url.set_path("/telegram/media");
+        let upstream = format!("Telegram channel {channel_slug}");
+        let channel = api
+            .fetch_before(before, config.channel_pages(&channel_slug))
+            .map_err(|e| telegram_http_error(e, &upstream))?;
+        channel_response(r, &channel, &upstream, &signer)
+    });
+    Route::new(
+        r"/telegram/feed/{slug}(?:/|\.rss)",
+        with_error_feed(feed_source.error_feed, view),
+    )
+}
 
-            let context = Context { proxy_url };
-            let feed = channel_to_feed(&channel, Some(&context));
-            let content = feed.to_string();
-            let response =
-                HTTPResponse::with_content(content.as_str()).set_content_type("text/xml");
-            Ok(response)
-        } else {
-            // TODO handle kind HostUnreachable
-            Err(HTTPError::InvalidRequest)
+/// Channels are fetched concurrently with one shared client
+#[cfg(feature = "async")]
+pub fn route_feed(feed_source: &TelegramFeedSource) -> Route {
+    let config = Arc::clone(feed_source.config.as_ref().unwrap());
+    let signer = feed_source.url_signer.clone();
+    let cache = feed_source.feed_cache.clone();
+    let error_feed = feed_source.error_feed;
+    let client = AsyncTelegramChannelPreviewApi::build_client();
+    Route::new_async(r"/telegram/feed/{slug}(?:/|\.rss)", move |r| {
+        let config = Arc::clone(&config);
+        let signer = signer.clone();
+        let cache = cache.clone();
+        let client = client.clone();
+        async move {
+            let result = cached_view_async(cache.as_ref(), &r, || async {
+                let channel_slug: String = r.path_param("slug")?;
+                let before: Option<usize> = r.query_param("before")?;
+                let api = AsyncTelegramChannelPreviewApi::with_client(client, &channel_slug);
+                let upstream = format!("Telegram channel {channel_slug}");
+                let channel = api
+                    .fetch_before(before, config.channel_pages(&channel_slug))
+                    .await
+                    .map_err(|e| telegram_http_error(e, &upstream))?;
+                channel_response(&r, &channel, &upstream, &signer)
+            })
+            .await;
+            error_feed_result(error_feed, &r, result)
         }
     })
 }
@@ -47,11 +112,9 @@ pub(crate) fn route_opml(feed_source: &TelegramFeedSource) -> Route {
     let config = Arc::clone(feed_source.config.as_ref().unwrap());
     Route::new("/telegram.opml", move |r| {
         let mut outlines: Vec<Outline> = Vec::with_capacity(config.channels.len());
-        let mut url = r.url();
-        url.set_path("/telegram/feed");
         for slug in config.channels.keys() {
-            outlines
-                .push(Outline::new(slug).add_child(slug, Some(format!("{url}/{slug}/").as_str())))
+            let url = r.absolute_url(format!("/telegram/feed/{slug}/").as_str());
+            outlines.push(Outline::new(slug).add_child(slug, Some(url.as_str())))
         }
         let opml = OPML::new("Telegram channels").add_outline(Outline {
             title: Attribute("Telegram channels".to_string()),
@@ -78,22 +141,38 @@ https://t.me/{channel}/{id}?embed=1&mode=tme&userpic=true
 Рабочий пример:
 url=https://cdn4.telegram-cdn.org/file/CPXsqnVMJaZ8SPo6_eLY_Zh_l2vsH9wwEr7sguC5KOLvgGw6eog_MzJXcgR-rltwEsRzGwH5ZFTIveq483rlNGeTkenkV9tLnmNyAkbI5h2ZCpmVTKRDZ87V_88HNm5aaKonpYgcwJW8AfQdIoCC3Nml8g3NJyU6NZi0Qe8Rf7Tw4x41mV74c2EBDBvwLs_k1q5oOMkYNH5rQXc0J4BtxWqaES4tf5R0Y4P5BxLbMYlw-3txRq1Oa4xFLjC_bcXkhM5aDnjm6LPVt0T-5YG_-Ra_WIXkYDdg73cZNGoalkPEAtUqcz-ez9t1ouUFokhBJ8pCsrqtg-bdJtJgE4SpGQ.jpg
 */
-pub fn route_media_proxy(_feed_source: &TelegramFeedSource) -> Route {
-    // TODO cache
+pub fn route_media_proxy(feed_source: &TelegramFeedSource) -> Route {
+    let media_cache = feed_source.media_cache.clone();
+    let signer = feed_source.url_signer.clone();
     Route::new(
-        r#"/telegram/media/([\w_]+)/(\d+)/(\d+)-(url|thumb_url)/"#,
+        r#"/telegram/media/{channel:[\w_]+}/{post_id:\d+}/{index:\d+}-{field:url|thumb_url}/"#,
         move |r| {
-            let path_parts: Vec<&str> = path_params_to_vec(r.path_params.as_ref().unwrap())
-                .into_iter()
-                .map(|v| v.unwrap())
-                .collect();
-            let [channel_slug, post_id, media_index, field] = path_parts[1..] else { unreachable!() };
-            let post_id: usize = post_id.parse().unwrap();
-            let media_index: usize = media_index.parse().unwrap();
+            let channel_slug: String = r.path_param("channel")?;
+            let post_id: usize = r.path_param("post_id")?;
+            let media_index: usize = r.path_param("index")?;
+            let field: String = r.path_param("field")?;
+
+            let Some(media_url) = r.query_params.get("url") else {
+                return Err(HTTPError::InvalidRequest);
+            };
+            // Без подписи были бы открытым прокси, да и кеш можно было бы подменить
+            let media_id = format!("{channel_slug}/{post_id}/{media_index}-{field}");
+            if !signer.verify(&media_id, media_url, &r.query_params) {
+                log::warn!("Telegram media proxy: invalid signature id={:?}", media_id);
+                return Err(HTTPError::new(
+                    403,
+                    "Invalid or expired media url signature",
+                ));
+            }
 
-            let api = TelegramChannelPreviewApi::new(channel_slug);
-            let _timestamp: usize = r.query_params.get("t").map(|t| t.parse().unwrap()).unwrap();
-            let mut media_url = r.query_params.get("url").unwrap().clone();
+            let cache_key = format!("telegram/{media_id}");
+            if let Some(response) = media_cache.as_ref().and_then(|c| c.get(&cache_key, r)) {
+                return Ok(response);
+            }
+
+            let api = TelegramChannelPreviewApi::new(&channel_slug);
+            let _timestamp: Option<usize> = r.query_param("t")?;
+            let mut media_url = media_url.clone();
 
             let mut proxy_headers: HeaderMap =
                 HeaderMap::from_iter(r.headers.iter().map(|(k, v)| {
@@ -113,26 +192,33 @@ pub fn route_media_proxy(_feed_source: &TelegramFeedSource) -> Route {
                     .request(Method::GET, media_url.as_str())
                     .headers(proxy_headers.clone())
                     .send()
-                    .unwrap();
+                    .map_err(|e| reqwest_error(e, "Telegram media"))?;
 
                 match media_res.status().as_u16() {
                     200..=299 => {
-                        return Ok(response_from_reqwest_response(media_res));
+                        let mut response = response_from_reqwest_response(media_res);
+                        if let Some(cache) = media_cache.as_ref() {
+                            response = cache.tee(&cache_key, response);
+                        }
+                        return Ok(with_accept_ranges(response));
                     }
+                    // Range upstream not satisfiable
+                    416 => return Ok(response_from_reqwest_response(media_res)),
                     404 => {
                         if i != 0 {
                             break;
                         }
                         // Пробуем получить актуальный урл
-                        media_url = api.try_get_new_media_url(post_id, media_index, field);
+                        media_url = api.try_get_new_media_url(post_id, media_index, &field);
                     }
-                    _ => {
-                        dbg!(&media_url, &media_res);
-                        return Err(HTTPError::InvalidRequest);
+                    status => {
+                        return Err(
+                            upstream_status_error(status, "Telegram media").with_cause(media_url)
+                        );
                     }
                 }
             }
-            Err(NotFound)
+            Err(HTTPError::not_found("Telegram media not found"))
         },
     )
 }
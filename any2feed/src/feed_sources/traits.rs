@@ -1,11 +1,22 @@
+use crate::cache::{FeedCache, MediaCache};
 use crate::feed_sources::error::FeedSourceError;
+use crate::feed_sources::signed_url::UrlSigner;
 use feed::opml::Outline;
 use http_server::Route;
+use std::sync::Arc;
 
 pub trait FeedSource {
     fn name(&self) -> String;
     /// Initialize with config
     fn with_config(&mut self, toml: &str) -> Result<(), FeedSourceError>;
+    /// Shared media cache for media proxy routes
+    fn with_media_cache(&mut self, _cache: Arc<MediaCache>) {}
+    /// Cache for rendered feeds with ttl of this source
+    fn with_feed_cache(&mut self, _cache: FeedCache) {}
+    /// Respond with feed containing error entry instead of error status
+    fn with_error_feed(&mut self, _enabled: bool) {}
+    /// Signer of media proxy urls
+    fn with_url_signer(&mut self, _signer: UrlSigner) {}
     /// Initialize routes
     fn routes(&self) -> Vec<Route>;
 
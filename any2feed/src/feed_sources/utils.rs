@@ -1,7 +1,13 @@
-use http_server::response;
+use chrono::Local;
+use feed::{CDATAElement, Content, Entry, Feed, Generator, Link, LinkRel, Person};
+use http_server::response::ContentStream;
+use http_server::{response, HTTPError, HTTPRequest, HTTPResponse};
 use std::collections::HashMap;
 use std::time::SystemTime;
 
+/// Larger proxied bodies are streamed to client without buffering
+pub const MAX_BUFFERED_SIZE: u64 = 4 * 1024 * 1024;
+
 pub fn response_from_reqwest_response(
     response: reqwest::blocking::Response,
 ) -> response::HTTPResponse {
@@ -12,12 +18,207 @@ pub fn response_from_reqwest_response(
         .collect();
     let content_type = media_headers.get("content-type").cloned();
     let status = response.status().as_u16();
-    let content = response.bytes().unwrap();
-    response::HTTPResponse {
-        status,
-        content: Some(content),
-        content_type,
-        headers: media_headers,
+    let length = response.content_length();
+    // Небольшие ответы буферизуем, их можно закешировать и отдать по Range
+    if status == 200 && length.is_some_and(|l| l <= MAX_BUFFERED_SIZE) {
+        let content = response.bytes().unwrap();
+        response::HTTPResponse {
+            status,
+            content: Some(content),
+            content_type,
+            headers: media_headers,
+            ..response::HTTPResponse::default()
+        }
+    } else {
+        response::HTTPResponse {
+            status,
+            content_type,
+            headers: media_headers,
+            content_stream: Some(ContentStream::new(response, length)),
+            ..response::HTTPResponse::default()
+        }
+    }
+}
+
+/// Mark buffered proxied response as ranged, `Range` of it is served by server.
+/// Streamed body can't be ranged, its upstream headers are kept
+pub fn with_accept_ranges(response: response::HTTPResponse) -> response::HTTPResponse {
+    if response.content.is_some() {
+        response.set_header("Accept-Ranges", "bytes")
+    } else {
+        response
+    }
+}
+
+/// Output format of feed routes: `?format=rss|json` or `.rss` path suffix, Atom by default.
+/// Feed route patterns end with `(?:/|\.rss)`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    #[default]
+    Atom,
+    Rss,
+    /// JSON Feed 1.1
+    Json,
+}
+
+impl FeedFormat {
+    /// ```
+    /// use any2feed::feed_sources::utils::FeedFormat;
+    /// use http_server::HTTPRequest;
+    /// let format = |path: &str| {
+    ///     let lines = vec![format!("GET {path} HTTP/1.1")];
+    ///     FeedFormat::from_request(&HTTPRequest::parse(&lines).unwrap())
+    /// };
+    /// assert_eq!(format("/telegram/feed/foo/").unwrap(), FeedFormat::Atom);
+    /// assert_eq!(format("/telegram/feed/foo.rss").unwrap(), FeedFormat::Rss);
+    /// assert_eq!(format("/telegram/feed/foo/?format=rss").unwrap(), FeedFormat::Rss);
+    /// assert_eq!(format("/telegram/feed/foo.rss?format=atom").unwrap(), FeedFormat::Atom);
+    /// assert_eq!(format("/telegram/feed/foo/?format=json").unwrap(), FeedFormat::Json);
+    /// assert!(format("/telegram/feed/foo/?format=foo").is_err());
+    /// ```
+    pub fn from_request(request: &HTTPRequest) -> http_server::Result<FeedFormat> {
+        match request.query_params.get("format").map(|f| f.as_str()) {
+            Some("atom") => Ok(FeedFormat::Atom),
+            Some("rss") => Ok(FeedFormat::Rss),
+            Some("json") => Ok(FeedFormat::Json),
+            Some(format) => Err(HTTPError::new(
+                400,
+                format!("Unknown feed format {format:?}, expected atom, rss or json"),
+            )),
+            None if request.path.ends_with(".rss") => Ok(FeedFormat::Rss),
+            None => Ok(FeedFormat::Atom),
+        }
+    }
+
+    pub fn render(&self, feed: &Feed) -> String {
+        match self {
+            FeedFormat::Atom => feed.to_string(),
+            FeedFormat::Rss => feed.to_rss(),
+            FeedFormat::Json => feed.to_json(),
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Atom => "text/xml",
+            FeedFormat::Rss => "application/rss+xml",
+            FeedFormat::Json => "application/feed+json",
+        }
+    }
+}
+
+/// any2feed with version, `generator` of feeds built by sources
+/// ```
+/// use any2feed::feed_sources::utils::{generator, FeedFormat};
+/// let feed = feed::Feed {
+///     generator: Some(generator()),
+///     ..feed::Feed::default()
+/// };
+/// let generator = generator();
+/// assert_eq!(generator.name.0, "any2feed");
+/// assert_eq!(generator.version.unwrap().0, env!("CARGO_PKG_VERSION"));
+///
+/// let atom = FeedFormat::Atom.render(&feed);
+/// assert!(atom.contains(&format!(
+///     r#"<generator uri="https://github.com/Apkawa/rust-any2feed" version="{}">any2feed</generator>"#,
+///     env!("CARGO_PKG_VERSION")
+/// )));
+/// ```
+pub fn generator() -> Generator {
+    Generator::new(
+        env!("CARGO_PKG_NAME").to_string(),
+        Some(env!("CARGO_PKG_REPOSITORY").to_string()),
+        Some(env!("CARGO_PKG_VERSION").to_string()),
+    )
+}
+
+/// Feed response in requested format with `Last-Modified`
+pub fn feed_response(request: &HTTPRequest, feed: &Feed) -> http_server::Result<HTTPResponse> {
+    let format = FeedFormat::from_request(request)?;
+    let content = format.render(feed);
+    let response =
+        HTTPResponse::with_content(content.as_str()).set_content_type(format.content_type());
+    Ok(set_feed_last_modified(response, feed))
+}
+
+/// Set `Last-Modified` by newest feed entry
+pub fn set_feed_last_modified(response: HTTPResponse, feed: &Feed) -> HTTPResponse {
+    if let Some(updated) = feed.last_updated() {
+        response.set_last_modified(updated.into())
+    } else {
+        response
+    }
+}
+
+/// Valid feed with single entry about error, so readers show it
+/// instead of silently failed update
+/// ```
+/// use any2feed::feed_sources::utils::error_feed;
+/// use http_server::{HTTPError, HTTPRequest};
+/// let lines = vec![
+///     "GET /telegram/feed/foo/ HTTP/1.1".to_string(),
+///     "Host: localhost".to_string(),
+/// ];
+/// let r = HTTPRequest::parse(&lines).unwrap();
+/// let feed = error_feed(&r, &HTTPError::bad_gateway("Mewe session expired"));
+/// assert_eq!(feed.entries.len(), 1);
+/// let content = feed.to_string();
+/// assert!(content.contains("502 Bad Gateway: Mewe session expired"));
+/// assert!(content.contains(">any2feed</generator>"));
+/// ```
+pub fn error_feed(request: &HTTPRequest, error: &HTTPError) -> Feed {
+    let url = request.url().to_string();
+    let now = Local::now();
+    let mut feed = Feed {
+        id: url.clone(),
+        title: CDATAElement(format!("any2feed: {error}")),
+        author: vec![Person::new("any2feed".to_string(), None, None)],
+        generator: Some(generator()),
+        ..Feed::default()
+    };
+    feed.link.push(Link::with_rel(url.clone(), LinkRel::_Self));
+    // Одна запись на ошибку в сутки, чтобы не заваливать читалку дубликатами
+    let id = format!("{url}#error-{}-{}", error.status(), now.format("%Y-%m-%d"));
+    let mut entry = Entry::new(id, error.to_string(), now.into());
+    entry.content = Some(Content::Text(error.to_string()));
+    feed.entries.push(entry);
+    feed.set_updated_from_entries();
+    feed
+}
+
+/// Wrap feed route callback, if `enabled` errors are rendered as [`error_feed`]
+pub fn with_error_feed<F>(
+    enabled: bool,
+    view: F,
+) -> impl Fn(&HTTPRequest) -> http_server::Result<HTTPResponse> + Send + Sync + 'static
+where
+    F: Fn(&HTTPRequest) -> http_server::Result<HTTPResponse> + Send + Sync + 'static,
+{
+    move |r| error_feed_result(enabled, r, view(r))
+}
+
+/// If `enabled` replace error of feed route with [`error_feed`] response
+pub fn error_feed_result(
+    enabled: bool,
+    request: &HTTPRequest,
+    result: http_server::Result<HTTPResponse>,
+) -> http_server::Result<HTTPResponse> {
+    match result {
+        Err(e) if enabled => {
+            log::warn!(
+                "Feed {} error: {} cause={:?}",
+                request.log_path(),
+                e,
+                e.cause()
+            );
+            let format = FeedFormat::from_request(request).unwrap_or_default();
+            let content = format.render(&error_feed(request, &e));
+            // Ответ 200, иначе читалки не покажут содержимое. Не кешируем
+            Ok(HTTPResponse::with_content(content.as_str())
+                .set_content_type(format.content_type())
+                .set_header("Cache-Control", "no-store"))
+        }
+        result => result,
     }
 }
 
@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod cli;
 pub mod config;
 pub mod feed_sources;
@@ -1,5 +1,6 @@
 use any2feed::config::load_config;
-use http_server::{run, HTTPRequest, HTTPResponse, Route, ServerConfig};
+use http_server::{HTTPRequest, HTTPResponse, Route, ServerConfig};
+use std::sync::Arc;
 
 fn main_view(_request: &HTTPRequest) -> http_server::Result<HTTPResponse> {
     Ok(HTTPResponse::with_content(
@@ -19,24 +20,67 @@ fn main_view(_request: &HTTPRequest) -> http_server::Result<HTTPResponse> {
 }
 
 fn main() {
-    let config = load_config();
+    let config = load_config().unwrap_or_else(|e| {
+        eprintln!("Config error: {e}");
+        std::process::exit(2);
+    });
 
     let mut routes = vec![Route::new("/", main_view)];
 
+    let media_cache = config.cache.as_ref().and_then(|c| c.media_cache());
+    let feed_cache_storage = config.cache.as_ref().map(|c| c.feed_cache_storage());
+    let url_signer = config.server.url_signer();
+
     let mut feed_source_list = config.get_enabled_feed_sources();
     let config_str = config.config_text.as_ref().unwrap();
     for feed_source in feed_source_list.iter_mut() {
         // Initialize
         log::info!("Feed source '{}' initialize", feed_source.name());
         feed_source.with_config(config_str).unwrap();
-        routes.extend(feed_source.routes());
+        if let Some(cache) = media_cache.as_ref() {
+            feed_source.with_media_cache(Arc::clone(cache));
+        }
+        feed_source.with_url_signer(url_signer.clone());
+        feed_source.with_error_feed(config.feed_source_error_feed(feed_source.name().as_str()));
+        if let Some(storage) = feed_cache_storage.as_ref() {
+            if let Some(cache) = config.feed_cache(storage, feed_source.name().as_str()) {
+                feed_source.with_feed_cache(cache);
+            }
+        }
+        let auth = config.feed_source_auth(feed_source.name().as_str());
+        for route in feed_source.routes() {
+            routes.push(match auth.clone() {
+                Some(auth) => route.with_auth(auth),
+                None => route,
+            });
+        }
     }
 
     let run_args = ServerConfig {
+        bind: config.server.bind.clone().unwrap_or_default(),
         port: config.server.port,
+        public_url: config.server.public_url().unwrap_or_default(),
+        trust_forwarded: config.server.trust_forwarded.unwrap_or(false),
         threads: config.server.threads,
+        keep_alive: config.server.keep_alive,
+        shutdown_timeout: config.server.shutdown_timeout,
+        request_timeout: config.server.request_timeout,
+        tls: config.server.tls(),
+        auth: config.server.auth.auth(),
         routes,
     };
 
-    run(run_args).unwrap();
+    #[cfg(feature = "async")]
+    if run_args.tls.is_some() {
+        // HTTPS есть только у блокирующего сервера, async роуты он тоже умеет
+        log::warn!("HTTPS is served by blocking server");
+        http_server::run(run_args).unwrap();
+    } else {
+        http_server::run_async(run_args).unwrap();
+    }
+    #[cfg(not(feature = "async"))]
+    http_server::run(run_args).unwrap();
+    // Кеши пишутся на диск сразу, остается только лог
+    log::info!("Shutdown complete");
+    log::logger().flush();
 }
@@ -1,4 +1,5 @@
 use any2feed::feed_sources::mewe::feed::{mewe_feed_to_feed, mewe_post_to_entry};
+use feed::Feed;
 use rstest::rstest;
 
 use mewe_api::json::{MeweApiFeedList, MeweApiPost, MeweApiUserInfo};
@@ -41,4 +42,6 @@ fn test_allfeed() {
     dbg!(&entry);
     let xml = entry.to_string();
     println!("{}", xml);
+    let parsed: Feed = xml.parse().unwrap();
+    assert_eq!(parsed, entry);
 }
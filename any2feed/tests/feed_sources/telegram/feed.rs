@@ -1,4 +1,7 @@
 use any2feed::feed_sources::telegram::feed::channel_post_to_entry;
+use feed::rss::Rss;
+use feed::{Feed, LinkRel};
+use rstest::rstest;
 use telegram::data::ChannelPost;
 use telegram::parse::parse_message;
 use test_utils::fixture::load_fixture;
@@ -14,3 +17,37 @@ fn test_entry_text() {
     let e = channel_post_to_entry(p, None);
     dbg!(e);
 }
+
+#[test]
+fn test_entry_voice_enclosure() {
+    let p = load_channel_post_fixture("voice");
+    let e = channel_post_to_entry(p, None);
+    let enclosure = e
+        .link
+        .iter()
+        .find(|l| l.rel() == LinkRel::Enclosure)
+        .unwrap();
+    assert_eq!(enclosure.mime_type.as_ref().unwrap().0, "audio/ogg");
+    assert!(Rss(&e).to_string().contains(r#"type="audio/ogg"/>"#));
+}
+
+#[rstest]
+#[case("link_preview")]
+#[case("media_file")]
+#[case("media_forwarded_from")]
+#[case("media_photo_and_video")]
+#[case("media_round_video")]
+#[case("poll")]
+#[case("sticker")]
+#[case("text")]
+#[case("voice")]
+#[case("with_from_author")]
+fn test_feed_well_formed(#[case] name: &str) {
+    let entry = channel_post_to_entry(load_channel_post_fixture(name), None);
+    let feed = Feed {
+        entries: vec![entry],
+        ..Feed::default()
+    };
+    let parsed: Feed = feed.to_string().parse().unwrap();
+    assert_eq!(parsed, feed);
+}
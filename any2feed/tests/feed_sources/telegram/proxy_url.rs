@@ -1,7 +1,9 @@
+use any2feed::feed_sources::signed_url::UrlSigner;
 use any2feed::feed_sources::telegram::feed::{channel_post_to_entry, set_proxy_url, Context};
 use feed::Content;
 use reqwest::Url;
 use std::collections::HashMap;
+use std::time::Duration;
 use telegram::data::{ChannelPost, LinkPreview, Media};
 use telegram::parse::parse_message;
 use test_utils::fixture::load_fixture;
@@ -36,7 +38,8 @@ fn test_set_proxy_url() {
         vec!["https://url.com/link_preview/".to_string()]
     );
 
-    let post = set_proxy_url(post, &Url::parse("http://localhost.com/").unwrap());
+    let signer = UrlSigner::new(b"secret", Duration::from_secs(60));
+    let post = set_proxy_url(post, &Url::parse("http://localhost.com/").unwrap(), &signer);
 
     assert_eq!(post.get_media_list().len(), 4);
     let url = Url::parse(post.get_media_list()[3].get_urls()[0].as_str()).unwrap();
@@ -53,7 +56,19 @@ fn test_set_proxy_url() {
     assert_eq!(
         query_params.get("url"),
         Some(&"https://url.com/link_preview/".to_string())
-    )
+    );
+    assert!(query_params.contains_key("exp"));
+    // Signed for path of media
+    assert!(signer.verify(
+        "channel_name/123/3-url",
+        "https://url.com/link_preview/",
+        &query_params
+    ));
+    assert!(!signer.verify(
+        "channel_name/123/2-url",
+        "https://url.com/link_preview/",
+        &query_params
+    ));
 }
 
 fn load_channel_post_fixture(name: &str) -> ChannelPost {
@@ -75,6 +90,7 @@ fn test_proxy_replace() {
     let p = load_channel_post_fixture("media_photo_and_video");
     let context = Context {
         proxy_url: Url::parse("http://localhost:12345/telegram/media").unwrap(),
+        signer: UrlSigner::default(),
     };
     let e = channel_post_to_entry(p, Some(&context));
     dbg!(&e);
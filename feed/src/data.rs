@@ -1,42 +1,53 @@
 // https://validator.w3.org/feed/docs/atom.html
 
-use chrono::Local;
+use chrono::{DateTime, FixedOffset, Local};
 use std::fmt::{Display, Formatter};
+use std::str::FromStr;
 use unicode_segmentation::UnicodeSegmentation;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum Content {
     Text(String),
     Html(String),
     Xhtml(String),
 }
 
+impl Content {
+    /// Inner text regardless of type
+    pub fn text(&self) -> &str {
+        match self {
+            Content::Text(x) | Content::Html(x) | Content::Xhtml(x) => x.as_str(),
+        }
+    }
+}
+
 impl Default for Content {
     fn default() -> Self {
         Content::Text(String::default())
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Element<T>(pub T);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct CDATAElement<T>(pub T);
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Attribute<T>(pub T);
 
-#[derive(Debug, Default)]
+/// https://www.rfc-editor.org/rfc/rfc4287#section-4.1.1
+#[derive(Debug, Default, PartialEq)]
 pub struct Feed {
     pub id: String,
     pub title: CDATAElement<String>,
-    pub updated: String,
+    pub updated: DateTime<FixedOffset>,
 
-    pub author: Element<Person>,
+    pub author: Vec<Person>,
     pub link: Vec<Link>,
 
     pub categories: Option<Element<Vec<Category>>>,
-    pub contributor: Option<Element<Person>>,
+    pub contributor: Vec<Person>,
     pub generator: Option<Generator>,
 
     pub icon: Option<Element<String>>,
@@ -45,26 +56,60 @@ pub struct Feed {
     pub subtitle: Option<Content>,
 
     pub entries: Vec<Entry>,
+
+    /// `xml:base`, for relative urls
+    pub base: Option<Attribute<String>>,
+    /// `xml:lang`, e.g. `en`
+    pub lang: Option<Attribute<String>>,
 }
 
-#[derive(Debug, Default)]
+/// https://www.rfc-editor.org/rfc/rfc4287#section-4.1.2
+#[derive(Debug, Default, PartialEq)]
 pub struct Entry {
     pub id: String,
     pub title: CDATAElement<String>,
-    // TODO dt object
-    pub updated: String,
-    pub author: Element<Person>,
+    pub updated: DateTime<FixedOffset>,
+    pub author: Vec<Person>,
     pub content: Option<Content>,
-    pub link: Option<Link>,
+    pub link: Vec<Link>,
     pub summary: Option<Content>,
     pub categories: Option<Element<Vec<Category>>>,
-    pub contributor: Option<Element<Person>>,
-    pub published: Option<Element<String>>,
+    pub contributor: Vec<Person>,
+    pub published: Option<Element<DateTime<FixedOffset>>>,
     pub rights: Option<Content>,
+    /// Metadata of original feed for copied entry, `entries` of it are ignored
+    pub source: Option<Box<Feed>>,
+
+    pub base: Option<Attribute<String>>,
+    pub lang: Option<Attribute<String>>,
+}
+
+impl Feed {
+    /// Newest `updated` of entries
+    /// ```
+    /// use feed::{Entry, Feed};
+    /// let mut feed = Feed::default();
+    /// assert_eq!(feed.last_updated(), None);
+    /// feed.entries = ["2023-03-01T10:00:00+03:00", "2023-03-01T08:00:00Z", "2023-03-01T07:30:00Z"]
+    ///     .into_iter()
+    ///     .map(|d| Entry { updated: d.parse().unwrap(), ..Entry::default() })
+    ///     .collect();
+    /// assert_eq!(feed.last_updated().unwrap().to_rfc3339(), "2023-03-01T08:00:00+00:00");
+    /// feed.set_updated_from_entries();
+    /// assert_eq!(feed.updated.to_rfc3339(), "2023-03-01T08:00:00+00:00");
+    /// ```
+    pub fn last_updated(&self) -> Option<DateTime<FixedOffset>> {
+        self.entries.iter().map(|e| e.updated).max()
+    }
+
+    /// Set feed `updated` by newest entry, otherwise current time
+    pub fn set_updated_from_entries(&mut self) {
+        self.updated = self.last_updated().unwrap_or_else(|| Local::now().into());
+    }
 }
 
 impl Entry {
-    pub fn new(id: String, title: String, updated: String) -> Entry {
+    pub fn new(id: String, title: String, updated: DateTime<FixedOffset>) -> Entry {
         // TODO correct truncate unicode
         let title = {
             let title = if title.is_empty() {
@@ -101,7 +146,7 @@ impl Entry {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Category {
     pub term: String,
     pub scheme: Option<Attribute<String>>,
@@ -118,7 +163,7 @@ impl Category {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Person {
     pub name: String,
     pub url: Option<Element<String>>,
@@ -135,7 +180,7 @@ impl Person {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum LinkRel {
     #[default]
     Alternate,
@@ -150,6 +195,26 @@ pub enum LinkRel {
     Next,
 }
 
+impl FromStr for LinkRel {
+    type Err = String;
+
+    /// ```
+    /// use feed::LinkRel;
+    /// assert_eq!("self".parse::<LinkRel>().unwrap(), LinkRel::_Self);
+    /// assert_eq!("next".parse::<LinkRel>().unwrap(), LinkRel::Next);
+    /// assert!("foo".parse::<LinkRel>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        use LinkRel::*;
+        [
+            Alternate, Enclosure, Related, _Self, Via, First, Last, Previous, Next,
+        ]
+        .into_iter()
+        .find(|r| r.to_string() == s)
+        .ok_or_else(|| format!("Unknown link rel {s:?}"))
+    }
+}
+
 impl Display for LinkRel {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -162,7 +227,7 @@ impl Display for LinkRel {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Link {
     pub href: Attribute<String>,
     pub title: Option<Attribute<String>>,
@@ -186,9 +251,31 @@ impl Link {
             ..Link::default()
         }
     }
+    /// Media attachment, e.g. audio of podcast entry
+    pub fn enclosure(href: String, mime_type: &str, length: Option<usize>) -> Link {
+        Link {
+            href: Attribute(href),
+            rel: Some(Attribute(LinkRel::Enclosure)),
+            mime_type: Some(Attribute(mime_type.to_string())),
+            length: length.map(Attribute),
+            ..Link::default()
+        }
+    }
+    /// Link relation, `alternate` if not set
+    /// ```
+    /// use feed::{Link, LinkRel};
+    /// assert_eq!(Link::new("https://example.com".to_string()).rel(), LinkRel::Alternate);
+    /// assert_eq!(
+    ///     Link::with_rel("https://example.com".to_string(), LinkRel::Next).rel(),
+    ///     LinkRel::Next
+    /// );
+    /// ```
+    pub fn rel(&self) -> LinkRel {
+        self.rel.as_ref().map_or(LinkRel::default(), |r| r.0)
+    }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, PartialEq)]
 pub struct Generator {
     pub name: Attribute<String>,
     pub uri: Option<Attribute<String>>,
@@ -0,0 +1,207 @@
+// https://www.jsonfeed.org/version/1.1/
+
+use crate::data::{Category, Content, Entry, Feed, LinkRel, Person};
+use serde::Serialize;
+
+pub const JSON_FEED_VERSION: &str = "https://jsonfeed.org/version/1.1";
+
+/// JSON Feed 1.1 document, built from [`Feed`]
+/// ```
+/// use feed::json_feed::JsonFeed;
+/// use feed::{CDATAElement, Content, Entry, Feed, Link, LinkRel};
+/// let mut entry = Entry::new(
+///     "https://example.com/1".to_string(),
+///     "Bar".to_string(),
+///     "2023-03-01T10:00:00+03:00".parse().unwrap(),
+/// );
+/// entry.content = Some(Content::Html("<p>Bar</p>".to_string()));
+/// let feed = Feed {
+///     title: CDATAElement("Foo".to_string()),
+///     link: vec![
+///         Link::new("https://example.com/".to_string()),
+///         Link::with_rel("https://example.com/feed.json".to_string(), LinkRel::_Self),
+///     ],
+///     entries: vec![entry],
+///     ..Feed::default()
+/// };
+/// let json = JsonFeed::from(&feed);
+/// assert_eq!(json.version, "https://jsonfeed.org/version/1.1");
+/// assert_eq!(json.home_page_url.as_deref(), Some("https://example.com/"));
+/// assert_eq!(json.feed_url.as_deref(), Some("https://example.com/feed.json"));
+/// assert_eq!(json.items[0].content_html.as_deref(), Some("<p>Bar</p>"));
+/// assert_eq!(json.items[0].date_modified.as_deref(), Some("2023-03-01T10:00:00+03:00"));
+/// assert!(feed.to_json().starts_with(r#"{"version":"https://jsonfeed.org/version/1.1""#));
+/// ```
+#[derive(Debug, Default, Serialize)]
+pub struct JsonFeed {
+    pub version: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub home_page_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub feed_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub favicon: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<JsonFeedAuthor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub items: Vec<JsonFeedItem>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct JsonFeedItem {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_html: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_published: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date_modified: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<JsonFeedAuthor>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<JsonFeedAttachment>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct JsonFeedAuthor {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct JsonFeedAttachment {
+    pub url: String,
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size_in_bytes: Option<usize>,
+}
+
+impl Feed {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&JsonFeed::from(self)).unwrap()
+    }
+}
+
+fn non_empty(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+fn tags(categories: &[Category]) -> Vec<String> {
+    categories
+        .iter()
+        .map(|c| c.label.as_ref().map_or(&c.term, |l| &l.0).clone())
+        .collect()
+}
+
+impl From<&Person> for JsonFeedAuthor {
+    fn from(person: &Person) -> Self {
+        JsonFeedAuthor {
+            name: non_empty(&person.name),
+            // Отдельного поля для почты нет
+            url: person
+                .url
+                .as_ref()
+                .map(|u| u.0.clone())
+                .or_else(|| person.email.as_ref().map(|e| format!("mailto:{}", e.0))),
+        }
+    }
+}
+
+/// Author list without empty ones
+fn authors(persons: &[Person]) -> Vec<JsonFeedAuthor> {
+    persons
+        .iter()
+        .map(JsonFeedAuthor::from)
+        .filter(|a| a.name.is_some() || a.url.is_some())
+        .collect()
+}
+
+impl From<&Feed> for JsonFeed {
+    fn from(feed: &Feed) -> Self {
+        let link = |rel: LinkRel| {
+            feed.link
+                .iter()
+                .find(|l| l.rel() == rel)
+                .map(|l| l.href.0.clone())
+        };
+        JsonFeed {
+            version: JSON_FEED_VERSION.to_string(),
+            title: feed.title.0.clone(),
+            home_page_url: link(LinkRel::Alternate),
+            feed_url: link(LinkRel::_Self),
+            description: feed.subtitle.as_ref().map(|s| s.text().to_string()),
+            next_url: link(LinkRel::Next),
+            icon: feed.logo.as_ref().map(|l| l.0.clone()),
+            favicon: feed.icon.as_ref().map(|i| i.0.clone()),
+            authors: authors(&feed.author),
+            language: feed.lang.as_ref().map(|l| l.0.clone()),
+            items: feed.entries.iter().map(JsonFeedItem::from).collect(),
+        }
+    }
+}
+
+impl From<&Entry> for JsonFeedItem {
+    fn from(entry: &Entry) -> Self {
+        let (content_html, content_text) = match &entry.content {
+            Some(Content::Text(text)) => (None, Some(text.clone())),
+            Some(Content::Html(html) | Content::Xhtml(html)) => (Some(html.clone()), None),
+            // Без содержимого item невалиден
+            None => (None, Some(String::new())),
+        };
+        JsonFeedItem {
+            id: entry.id.clone(),
+            url: entry
+                .link
+                .iter()
+                .find(|l| l.rel() == LinkRel::Alternate)
+                .map(|l| l.href.0.clone()),
+            title: non_empty(&entry.title.0),
+            content_html,
+            content_text,
+            summary: entry.summary.as_ref().map(|s| s.text().to_string()),
+            date_published: entry.published.as_ref().map(|p| p.0.to_rfc3339()),
+            date_modified: Some(entry.updated.to_rfc3339()),
+            authors: authors(&entry.author),
+            language: entry.lang.as_ref().map(|l| l.0.clone()),
+            tags: entry.categories.as_ref().map_or(vec![], |c| tags(&c.0)),
+            attachments: entry
+                .link
+                .iter()
+                .filter(|l| l.rel() == LinkRel::Enclosure)
+                .map(|l| JsonFeedAttachment {
+                    url: l.href.0.clone(),
+                    mime_type: l
+                        .mime_type
+                        .as_ref()
+                        .map_or("application/octet-stream".to_string(), |t| t.0.clone()),
+                    title: l.title.as_ref().map(|t| t.0.clone()),
+                    size_in_bytes: l.length.as_ref().map(|l| l.0),
+                })
+                .collect(),
+        }
+    }
+}
@@ -1,6 +1,10 @@
 pub mod data;
+pub mod json_feed;
+pub mod parse;
 pub mod render;
+pub mod rss;
 pub mod traits;
+pub mod xml;
 
 pub mod opml;
 pub mod utils;
@@ -1,4 +1,5 @@
 use crate::traits::{FeedAttribute, FeedElement};
+use crate::xml::{self, XmlWriter};
 use crate::{Attribute, CDATAElement, Element};
 use chrono::{DateTime, Local, Utc};
 use std::fmt::{Display, Formatter};
@@ -18,7 +19,7 @@ use std::fmt::{Display, Formatter};
 //         </outline>
 //   </body>
 // </opml>
-#[derive(Default, Debug)]
+#[derive(Default, Debug, PartialEq)]
 pub struct OPML {
     // version: Option<String>, todo select version
     pub title: CDATAElement<String>,
@@ -37,6 +38,10 @@ impl OPML {
         self.outlines.push(outline);
         self
     }
+    /// Outlines with feed url from all levels
+    pub fn feeds(&self) -> Vec<&Outline> {
+        self.outlines.iter().flat_map(|o| o.feeds()).collect()
+    }
 }
 
 impl Display for OPML {
@@ -47,29 +52,34 @@ impl Display for OPML {
             outlines,
         } = self;
 
-        let created = Element(created.unwrap_or_else(|| DateTime::from(Local::now())))
-            .render_tag("dateCreated");
-        let title = title.render_tag("title");
-        let outlines = outlines.iter().map(|c| c.to_string()).collect::<String>();
-
-        write!(
-            f,
-            r#"<?xml version="1.0" encoding="utf-8"?>
-<opml version="1.0">
-  <head>
-    {title}
-    {created}
-  </head>
-  <body>
-        {outlines}
-  </body>
-</opml>
-"#
-        )
+        let created = Element(
+            created
+                .unwrap_or_else(|| DateTime::from(Local::now()))
+                .to_string(),
+        );
+        let document = xml::render_document(|w| {
+            w.create_element("opml")
+                .with_attribute(("version", "1.0"))
+                .write_inner_content(|w| {
+                    w.create_element("head").write_inner_content(|w| {
+                        title.write_tag(w, "title")?;
+                        created.write_tag(w, "dateCreated")
+                    })?;
+                    w.create_element("body").write_inner_content(|w| {
+                        for o in outlines {
+                            o.write_tag(w, "outline")?;
+                        }
+                        Ok(())
+                    })?;
+                    Ok(())
+                })?;
+            Ok(())
+        });
+        writeln!(f, "{document}")
     }
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, PartialEq)]
 pub struct Outline {
     pub title: Attribute<String>,
     pub description: Attribute<String>,
@@ -110,10 +120,19 @@ impl Outline {
         self.outlines.push(outline);
         self
     }
+    /// Self and nested outlines with feed url
+    pub fn feeds(&self) -> Vec<&Outline> {
+        let mut feeds: Vec<&Outline> = Vec::new();
+        if self.xml_url.is_some() {
+            feeds.push(self);
+        }
+        feeds.extend(self.outlines.iter().flat_map(|o| o.feeds()));
+        feeds
+    }
 }
 
-impl Display for Outline {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl FeedElement for Outline {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
         let Outline {
             title,
             description,
@@ -122,32 +141,45 @@ impl Display for Outline {
             r#type,
             outlines,
         } = self;
-        let parts = [
-            title.render_attr("title"),
-            description.render_attr("description"),
-            text.render_attr("text"),
-            xml_url.render_attr("xmlUrl"),
-            r#type.render_attr("type"),
-        ]
-        .join(" ");
-
-        let outlines = outlines.iter().map(|c| c.to_string()).collect::<String>();
-        let (start, end) = if !outlines.is_empty() {
-            (">\n", "\n</outline>")
+        let attrs = [
+            ("title", title.attr_value()),
+            ("description", description.attr_value()),
+            ("text", text.attr_value()),
+            ("xmlUrl", xml_url.attr_value()),
+            ("type", r#type.attr_value()),
+        ];
+        let element = w
+            .create_element(tag)
+            .with_attributes(xml::attributes(&attrs));
+        if outlines.is_empty() {
+            element.write_empty()?;
         } else {
-            ("", " />")
-        };
-        write!(f, r#"<outline {parts}{start}{outlines}{end}"#)
+            element.write_inner_content(|w| {
+                for o in outlines {
+                    o.write_tag(w, "outline")?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Outline {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render_tag("outline"))
     }
 }
 
 #[cfg(test)]
 mod test {
     use crate::opml::{Outline, OPML};
+    use chrono::{TimeZone, Utc};
 
     #[test]
     fn test_opml_render() {
         let mut opml = OPML::new("Foo bar");
+        opml.created = Some(Utc.with_ymd_and_hms(2023, 2, 22, 8, 19, 27).unwrap());
         let mut outline = Outline::new("Foo");
         outline.outlines = vec![
             Outline::with_url("bar & url", "http://ya.ru"),
@@ -155,8 +187,9 @@ mod test {
         ];
         opml.outlines.push(outline);
 
-        dbg!(&opml);
-        println!("{}", opml);
+        let s = opml.to_string();
+        assert!(s.contains(r#"<outline title="bar &amp; url""#));
+        assert_eq!(s.parse::<OPML>().unwrap(), opml);
     }
 
     #[test]
@@ -167,7 +200,15 @@ mod test {
                 .add_outline(outline3)
                 .add_child("diez", Some("https://du.hast.much")),
         );
-        dbg!(&opml);
-        println!("{}", opml);
+        let parsed: OPML = opml.to_string().parse().unwrap();
+        assert_eq!(parsed.outlines, opml.outlines);
+        assert_eq!(
+            parsed
+                .feeds()
+                .iter()
+                .map(|o| o.title.0.as_str())
+                .collect::<Vec<_>>(),
+            vec!["level 3", "diez"]
+        );
     }
 }
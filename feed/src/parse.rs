@@ -0,0 +1,289 @@
+// Atom and OPML documents back to Feed and OPML, e.g. for import of subscriptions
+
+use crate::data::{Category, Content, Entry, Feed, Generator, Link, LinkRel, Person};
+use crate::opml::{Outline, OPML};
+use crate::{Attribute, CDATAElement, Element};
+use chrono::{DateTime, FixedOffset, NaiveDateTime, TimeZone, Utc};
+use roxmltree::{Document, Node, NS_XML_URI};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum ParseError {
+    Xml(roxmltree::Error),
+    /// Well-formed xml, but other document type
+    UnexpectedRoot(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Xml(e) => write!(f, "Invalid xml: {e}"),
+            ParseError::UnexpectedRoot(tag) => write!(f, "Unexpected root element <{tag}>"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+impl From<roxmltree::Error> for ParseError {
+    fn from(value: roxmltree::Error) -> Self {
+        ParseError::Xml(value)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, ParseError>;
+
+fn root<'a, 'i>(doc: &'a Document<'i>, name: &str) -> Result<Node<'a, 'i>> {
+    let root = doc.root_element();
+    if root.has_tag_name(name) {
+        Ok(root)
+    } else {
+        Err(ParseError::UnexpectedRoot(
+            root.tag_name().name().to_string(),
+        ))
+    }
+}
+
+fn children<'a, 'i: 'a>(
+    node: Node<'a, 'i>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'i>> + 'a {
+    node.children().filter(move |n| n.has_tag_name(name))
+}
+
+fn child<'a, 'i: 'a>(node: Node<'a, 'i>, name: &'a str) -> Option<Node<'a, 'i>> {
+    children(node, name).next()
+}
+
+/// Text of node with nested elements, CDATA included
+fn text(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect()
+}
+
+fn child_text(node: Node, name: &str) -> Option<String> {
+    child(node, name).map(text)
+}
+
+fn attr(node: Node, name: &str) -> Option<String> {
+    node.attribute(name).map(String::from)
+}
+
+/// Missing or invalid date is not fatal for import
+fn child_date(node: Node, name: &str) -> Option<DateTime<FixedOffset>> {
+    child_text(node, name).and_then(|d| DateTime::parse_from_rfc3339(d.trim()).ok())
+}
+
+fn parse_content(node: Node) -> Content {
+    match node.attribute("type") {
+        Some("html") => Content::Html(text(node)),
+        Some("xhtml") => {
+            // Разметка как есть, обычно обернутая в div
+            let first = node.children().find(|n| n.is_element());
+            let last = node.children().rfind(|n| n.is_element());
+            match (first, last) {
+                (Some(first), Some(last)) => Content::Xhtml(
+                    node.document().input_text()[first.range().start..last.range().end].to_string(),
+                ),
+                _ => Content::Xhtml(text(node)),
+            }
+        }
+        _ => Content::Text(text(node)),
+    }
+}
+
+fn parse_person(node: Node) -> Person {
+    Person {
+        name: child_text(node, "name").unwrap_or_default(),
+        // Atom `uri`, раньше рендерили как `url`
+        url: child_text(node, "uri")
+            .or_else(|| child_text(node, "url"))
+            .map(Element),
+        email: child_text(node, "email").map(Element),
+    }
+}
+
+/// Link with unknown `rel` is skipped
+fn parse_link(node: Node) -> Option<Link> {
+    let rel = match node.attribute("rel") {
+        Some(rel) => Some(Attribute(rel.parse::<LinkRel>().ok()?)),
+        None => None,
+    };
+    Some(Link {
+        href: Attribute(attr(node, "href")?),
+        title: attr(node, "title").map(Attribute),
+        length: node
+            .attribute("length")
+            .and_then(|l| l.parse().ok())
+            .map(Attribute),
+        mime_type: attr(node, "type").map(Attribute),
+        rel,
+        hreflang: attr(node, "hreflang").map(Attribute),
+    })
+}
+
+fn parse_categories(node: Node) -> Option<Element<Vec<Category>>> {
+    let categories: Vec<Category> = children(node, "category")
+        .filter_map(|c| {
+            Some(Category {
+                term: attr(c, "term")?,
+                scheme: attr(c, "scheme").map(Attribute),
+                label: attr(c, "label").map(Attribute),
+            })
+        })
+        .collect();
+    (!categories.is_empty()).then_some(Element(categories))
+}
+
+fn persons(node: Node, name: &str) -> Vec<Person> {
+    children(node, name).map(parse_person).collect()
+}
+
+/// `xml:base` or `xml:lang`
+fn xml_attr(node: Node, name: &str) -> Option<Attribute<String>> {
+    node.attribute((NS_XML_URI, name))
+        .map(|v| Attribute(v.to_string()))
+}
+
+fn parse_entry(node: Node) -> Entry {
+    Entry {
+        id: child_text(node, "id").unwrap_or_default(),
+        title: CDATAElement(child_text(node, "title").unwrap_or_default()),
+        updated: child_date(node, "updated").unwrap_or_default(),
+        author: persons(node, "author"),
+        content: child(node, "content").map(parse_content),
+        link: children(node, "link").filter_map(parse_link).collect(),
+        summary: child(node, "summary").map(parse_content),
+        categories: parse_categories(node),
+        contributor: persons(node, "contributor"),
+        published: child_date(node, "published").map(Element),
+        rights: child(node, "rights").map(parse_content),
+        source: child(node, "source").map(|s| Box::new(parse_feed(s))),
+        base: xml_attr(node, "base"),
+        lang: xml_attr(node, "lang"),
+    }
+}
+
+/// `feed` element, or `source` of entry without entries
+fn parse_feed(node: Node) -> Feed {
+    Feed {
+        id: child_text(node, "id").unwrap_or_default(),
+        title: CDATAElement(child_text(node, "title").unwrap_or_default()),
+        updated: child_date(node, "updated").unwrap_or_default(),
+        author: persons(node, "author"),
+        link: children(node, "link").filter_map(parse_link).collect(),
+        categories: parse_categories(node),
+        contributor: persons(node, "contributor"),
+        generator: child(node, "generator").map(|g| Generator {
+            name: Attribute(text(g)),
+            uri: attr(g, "uri").map(Attribute),
+            version: attr(g, "version").map(Attribute),
+        }),
+        icon: child_text(node, "icon").map(Element),
+        logo: child_text(node, "logo").map(Element),
+        rights: child(node, "rights").map(parse_content),
+        subtitle: child(node, "subtitle").map(parse_content),
+        entries: children(node, "entry").map(parse_entry).collect(),
+        base: xml_attr(node, "base"),
+        lang: xml_attr(node, "lang"),
+    }
+}
+
+/// Parse Atom document
+/// ```
+/// use feed::Feed;
+/// let feed: Feed = r#"<?xml version="1.0" encoding="utf-8"?>
+/// <feed xmlns="http://www.w3.org/2005/Atom">
+///   <id>urn:foo</id>
+///   <title type="text">Foo &amp; bar</title>
+///   <updated>2023-03-01T10:00:00Z</updated>
+///   <entry><id>urn:foo:1</id><title><![CDATA[First]]></title></entry>
+/// </feed>"#
+///     .parse()
+///     .unwrap();
+/// assert_eq!(feed.title.0, "Foo & bar");
+/// assert_eq!(feed.updated.to_rfc3339(), "2023-03-01T10:00:00+00:00");
+/// assert_eq!(feed.entries[0].title.0, "First");
+/// assert!("<rss/>".parse::<Feed>().is_err());
+/// ```
+impl FromStr for Feed {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let doc = Document::parse(s)?;
+        Ok(parse_feed(root(&doc, "feed")?))
+    }
+}
+
+/// RFC 822 date of spec, or `Display` of `DateTime<Utc>` as rendered by [`OPML`]
+fn parse_opml_date(date: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(date)
+        .map(|d| d.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%d %H:%M:%S%.f UTC")
+                .map(|d| Utc.from_utc_datetime(&d))
+        })
+        .ok()
+}
+
+fn parse_outline(node: Node) -> Outline {
+    let text = attr(node, "text");
+    Outline {
+        // Обычно у импортированных есть только text
+        title: Attribute(attr(node, "title").or(text.clone()).unwrap_or_default()),
+        description: Attribute(attr(node, "description").unwrap_or_default()),
+        text: text.map(Attribute),
+        xml_url: attr(node, "xmlUrl").map(Attribute),
+        r#type: attr(node, "type").map(Attribute),
+        outlines: children(node, "outline").map(parse_outline).collect(),
+    }
+}
+
+/// Parse OPML subscription list, e.g. export of other reader
+/// ```
+/// use feed::opml::OPML;
+/// let opml: OPML = r#"<?xml version='1.0' encoding='UTF-8' ?>
+/// <opml version="1.0">
+///   <head>
+///     <title>Thunderbird OPML Export - Blogs &amp; News Feeds</title>
+///     <dateCreated>Wed, 22 Feb 2023 08:19:27 GMT</dateCreated>
+///   </head>
+///   <body>
+///     <outline text="Programming" title="Programming">
+///       <outline text="Code as Craft" xmlUrl="https://codeascraft.com/feed/atom/" type="rss" />
+///     </outline>
+///   </body>
+/// </opml>"#
+///     .parse()
+///     .unwrap();
+/// assert_eq!(opml.title.0, "Thunderbird OPML Export - Blogs & News Feeds");
+/// assert_eq!(opml.created.unwrap().to_rfc3339(), "2023-02-22T08:19:27+00:00");
+/// let feeds = opml.feeds();
+/// assert_eq!(feeds[0].title.0, "Code as Craft");
+/// assert_eq!(feeds[0].xml_url.as_ref().unwrap().0, "https://codeascraft.com/feed/atom/");
+/// ```
+impl FromStr for OPML {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let doc = Document::parse(s)?;
+        let node = root(&doc, "opml")?;
+        let head = child(node, "head");
+        Ok(OPML {
+            title: CDATAElement(
+                head.and_then(|h| child_text(h, "title"))
+                    .unwrap_or_default(),
+            ),
+            created: head
+                .and_then(|h| child_text(h, "dateCreated"))
+                .and_then(|d| parse_opml_date(d.trim())),
+            outlines: child(node, "body").map_or(vec![], |b| {
+                children(b, "outline").map(parse_outline).collect()
+            }),
+        })
+    }
+}
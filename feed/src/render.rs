@@ -1,60 +1,71 @@
-use crate::data::{Category, Content, Entry, Feed, Person};
-use crate::traits::{FeedAttribute, FeedElement};
-use crate::utils::escape;
+use crate::data::{Category, Content, Entry, Feed, Generator, Person};
+use crate::traits::{FeedAttribute, FeedContent, FeedElement};
+use crate::xml::{self, XmlWriter};
 use crate::Link;
+use quick_xml::events::{BytesText, Event};
 use std::fmt::{Display, Formatter};
 
-impl Display for Feed {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let Feed {
-            id,
-            title,
-            link,
-            updated,
-            author,
-            generator: _, // TODO
-            subtitle,
-            contributor,
-            categories,
-            icon,
-            logo,
-            rights,
-            entries,
-        } = self;
-        let parts = [
-            title.render_tag("title"),
-            author.render_tag("author"),
-            link.iter().map(|l| l.to_string()).collect::<String>(),
-            // generator.render_
-            subtitle.render_tag("subtitle"),
-            contributor.render_tag("contributor"),
-            icon.render_tag("icon"),
-            logo.render_tag("logo"),
-            rights.render_tag("rights"),
-            categories.as_ref().map_or(String::new(), |l| {
-                l.0.iter().map(|c| c.to_string()).collect()
-            }),
-            entries.iter().map(|e| format!("{e}")).collect::<String>(),
-        ]
-        .into_iter()
-        .filter(|s| !s.is_empty())
-        .collect::<Vec<String>>()
-        .join("\n");
+const XML_BASE: &str = "xml:base";
+const XML_LANG: &str = "xml:lang";
 
-        write!(
-            f,
-            r#"<?xml version="1.0" encoding="utf-8"?>
-<feed xmlns="http://www.w3.org/2005/Atom">
-  <id>{id}</id>
-  <updated>{updated}</updated>
-  {parts}
-</feed>"#
-        )
+/// Feed metadata, also used for `source` of entry
+fn write_feed_metadata(feed: &Feed, w: &mut XmlWriter) -> xml::Result {
+    let Feed {
+        id,
+        title,
+        updated,
+        author,
+        link,
+        categories,
+        contributor,
+        generator,
+        icon,
+        logo,
+        rights,
+        subtitle,
+        entries: _,
+        base: _,
+        lang: _,
+    } = feed;
+    xml::text_element(w, "id", id)?;
+    xml::text_element(w, "updated", &updated.to_rfc3339())?;
+    title.write_tag(w, "title")?;
+    author.write_tag(w, "author")?;
+    link.write_tag(w, "link")?;
+    subtitle.write_tag(w, "subtitle")?;
+    contributor.write_tag(w, "contributor")?;
+    generator.write_tag(w, "generator")?;
+    icon.write_tag(w, "icon")?;
+    logo.write_tag(w, "logo")?;
+    rights.write_tag(w, "rights")?;
+    for c in categories.iter().flat_map(|c| &c.0) {
+        c.write_tag(w, "category")?;
     }
+    Ok(())
 }
 
-impl Display for Entry {
+impl Display for Feed {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let attrs = [
+            (XML_BASE, self.base.attr_value()),
+            (XML_LANG, self.lang.attr_value()),
+        ];
+        let document = xml::render_document(|w| {
+            w.create_element("feed")
+                .with_attribute(("xmlns", "http://www.w3.org/2005/Atom"))
+                .with_attributes(xml::attributes(&attrs))
+                .write_inner_content(|w| {
+                    write_feed_metadata(self, w)?;
+                    self.entries.write_tag(w, "entry")
+                })?;
+            Ok(())
+        });
+        f.write_str(&document)
+    }
+}
+
+impl FeedElement for Entry {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
         let Entry {
             id,
             title,
@@ -67,62 +78,98 @@ impl Display for Entry {
             contributor,
             published,
             rights,
+            source,
+            base,
+            lang,
         } = self;
-
-        let parts = [
-            title.render_tag("title"),
-            author.render_tag("author"),
-            content.render_tag("content"),
-            link.as_ref().map_or(String::new(), |l| l.to_string()),
-            summary.render_tag("summary"),
-            categories.as_ref().map_or(String::new(), |l| {
-                l.0.iter().map(|c| c.to_string()).collect()
-            }),
-            contributor.render_tag("contributor"),
-            published.render_tag("published"),
-            rights.render_tag("rights"),
-        ]
-        .join("\n");
-        write!(
-            f,
-            r#"
-    <entry>
-        <id>{id}</id>
-        <updated>{updated}</updated>
-        {parts}
-    </entry>
-        "#
-        )
+        let attrs = [(XML_BASE, base.attr_value()), (XML_LANG, lang.attr_value())];
+        w.create_element(tag)
+            .with_attributes(xml::attributes(&attrs))
+            .write_inner_content(|w| {
+                xml::text_element(w, "id", id)?;
+                xml::text_element(w, "updated", &updated.to_rfc3339())?;
+                title.write_tag(w, "title")?;
+                author.write_tag(w, "author")?;
+                content.write_tag(w, "content")?;
+                link.write_tag(w, "link")?;
+                summary.write_tag(w, "summary")?;
+                for c in categories.iter().flat_map(|c| &c.0) {
+                    c.write_tag(w, "category")?;
+                }
+                contributor.write_tag(w, "contributor")?;
+                published.write_tag(w, "published")?;
+                rights.write_tag(w, "rights")?;
+                if let Some(source) = source {
+                    w.create_element("source")
+                        .write_inner_content(|w| write_feed_metadata(source, w))?;
+                }
+                Ok(())
+            })?;
+        Ok(())
     }
 }
 
-impl Display for Category {
+impl Display for Entry {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render_tag("entry"))
+    }
+}
+
+impl FeedElement for Category {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
         let Category {
             term,
             scheme,
             label,
         } = self;
-        let term = escape(term);
-        let scheme = scheme.render_attr("scheme");
-        let label = label.render_attr("label");
-        write!(f, r#"<category term="{term}" {scheme} {label} />"#)
+        let attrs = [
+            ("term", Some(xml::clean(term).into_owned())),
+            ("scheme", scheme.attr_value()),
+            ("label", label.attr_value()),
+        ];
+        w.create_element(tag)
+            .with_attributes(xml::attributes(&attrs))
+            .write_empty()?;
+        Ok(())
+    }
+}
+
+impl Display for Category {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render_tag("category"))
     }
 }
 
 impl FeedElement for Content {
-    fn render_tag(&self, tag: &str) -> String {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
         let (t, c) = match self {
             Content::Text(x) => ("text", x),
             Content::Html(x) => ("html", x),
-            Content::Xhtml(x) => ("xhtml", x),
+            // Разметка вставляется как есть, только если она сама по себе валидна
+            Content::Xhtml(x) if is_well_formed(x) => {
+                w.create_element(tag)
+                    .with_attribute(("type", "xhtml"))
+                    .write_inner_content(|w| {
+                        w.write_event(Event::Text(BytesText::from_escaped(xml::clean(x))))
+                    })?;
+                return Ok(());
+            }
+            Content::Xhtml(x) => ("html", x),
         };
-        format!(r#"<{tag} type="{t}"><![CDATA[{c}]]></{tag}>"#)
+        w.create_element(tag)
+            .with_attribute(("type", t))
+            .write_inner_content(|w| xml::cdata(w, c))?;
+        Ok(())
     }
 }
 
-impl Display for Link {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+/// Fragment of markup, e.g. `<div>...</div>`, can be embedded into document
+fn is_well_formed(markup: &str) -> bool {
+    roxmltree::Document::parse(&format!("<x>{}</x>", xml::clean(markup))).is_ok()
+}
+
+impl FeedElement for Link {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
         let Link {
             href,
             title,
@@ -131,24 +178,57 @@ impl Display for Link {
             rel,
             hreflang,
         } = self;
-        let parts = [
-            href.render_attr("href"),
-            title.render_attr("title"),
-            length.render_attr("length"),
-            mime_type.render_attr("type"),
-            rel.render_attr("rel"),
-            hreflang.render_attr("hreflang"),
-        ]
-        .join(" ");
-        write!(f, r#"<link {parts}/>"#)
+        let attrs = [
+            ("href", href.attr_value()),
+            ("title", title.attr_value()),
+            ("length", length.attr_value()),
+            ("type", mime_type.attr_value()),
+            ("rel", rel.attr_value()),
+            ("hreflang", hreflang.attr_value()),
+        ];
+        w.create_element(tag)
+            .with_attributes(xml::attributes(&attrs))
+            .write_empty()?;
+        Ok(())
     }
 }
 
-impl Display for Person {
+impl Display for Link {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render_tag("link"))
+    }
+}
+
+impl FeedContent for Person {
+    fn write_content(&self, w: &mut XmlWriter) -> xml::Result {
         let Person { name, url, email } = self;
-        let url = url.render_tag("url");
-        let email = email.render_tag("email");
-        write!(f, r#"<name>{name}</name>{url}{email}"#)
+        xml::text_element(w, "name", name)?;
+        url.write_tag(w, "uri")?;
+        email.write_tag(w, "email")
+    }
+}
+
+impl FeedElement for Person {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
+        w.create_element(tag)
+            .write_inner_content(|w| self.write_content(w))?;
+        Ok(())
+    }
+}
+
+impl Display for Person {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&xml::render(|w| self.write_content(w)))
+    }
+}
+
+impl FeedElement for Generator {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
+        let Generator { name, uri, version } = self;
+        let attrs = [("uri", uri.attr_value()), ("version", version.attr_value())];
+        w.create_element(tag)
+            .with_attributes(xml::attributes(&attrs))
+            .write_inner_content(|w| xml::text(w, &name.0))?;
+        Ok(())
     }
 }
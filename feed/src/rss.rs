@@ -0,0 +1,232 @@
+// https://www.rssboard.org/rss-specification
+
+use crate::data::{Category, Content, Entry, Feed, LinkRel};
+use crate::traits::{FeedAttribute, FeedElement};
+use crate::xml::{self, XmlWriter};
+use chrono::{DateTime, FixedOffset};
+use std::fmt::{Display, Formatter};
+
+/// RSS 2.0 rendering of [`Feed`] or [`Entry`], Atom is default `Display` of them
+/// ```
+/// use feed::rss::Rss;
+/// use feed::{CDATAElement, Entry, Feed, Link};
+/// let feed = Feed {
+///     title: CDATAElement("Foo".to_string()),
+///     link: vec![Link::new("https://example.com/".to_string())],
+///     entries: vec![Entry::new(
+///         "https://example.com/1".to_string(),
+///         "Bar".to_string(),
+///         "2023-03-01T10:00:00+03:00".parse().unwrap(),
+///     )],
+///     ..Feed::default()
+/// };
+/// let rss = Rss(&feed).to_string();
+/// assert!(rss.contains(r#"<rss version="2.0""#));
+/// assert!(rss.contains("<link>https://example.com/</link>"));
+/// assert!(rss.contains("<pubDate>Wed, 01 Mar 2023 10:00:00 +0300</pubDate>"));
+/// assert_eq!(feed.to_rss(), rss);
+/// ```
+pub struct Rss<'a, T>(pub &'a T);
+
+impl Feed {
+    pub fn to_rss(&self) -> String {
+        Rss(self).to_string()
+    }
+}
+
+/// Date as RFC 2822 of RSS
+/// ```
+/// use feed::rss::rfc2822;
+/// assert_eq!(
+///     rfc2822(&"2023-03-01T10:00:00+03:00".parse().unwrap()),
+///     "Wed, 01 Mar 2023 10:00:00 +0300"
+/// );
+/// ```
+pub fn rfc2822(date: &DateTime<FixedOffset>) -> String {
+    // Двузначный день, как в примерах спецификации
+    date.format("%a, %d %b %Y %H:%M:%S %z").to_string()
+}
+
+fn write_categories(w: &mut XmlWriter, categories: &[Category]) -> xml::Result {
+    for c in categories {
+        let attrs = [("domain", c.scheme.attr_value())];
+        w.create_element("category")
+            .with_attributes(xml::attributes(&attrs))
+            .write_inner_content(|w| xml::cdata(w, &c.term))?;
+    }
+    Ok(())
+}
+
+impl Display for Rss<'_, Feed> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let Feed {
+            id,
+            title,
+            updated,
+            author,
+            link,
+            categories,
+            contributor: _,
+            generator,
+            icon,
+            logo,
+            rights,
+            subtitle,
+            entries,
+            base: _,
+            lang,
+        } = self.0;
+        // Ссылка на сайт обязательна, в крайнем случае id
+        let site_url = link
+            .iter()
+            .find(|l| l.rel() == LinkRel::Alternate)
+            .map_or(id.as_str(), |l| l.href.0.as_str());
+        let description = subtitle.as_ref().map_or(title.0.as_str(), Content::text);
+        let document = xml::render_document(|w| {
+            w.create_element("rss")
+                .with_attributes([
+                    ("version", "2.0"),
+                    ("xmlns:atom", "http://www.w3.org/2005/Atom"),
+                    ("xmlns:dc", "http://purl.org/dc/elements/1.1/"),
+                ])
+                .write_inner_content(|w| {
+                    w.create_element("channel").write_inner_content(|w| {
+                        title.write_tag(w, "title")?;
+                        xml::text_element(w, "link", site_url)?;
+                        xml::cdata_element(w, "description", description)?;
+                        // self, next и прочие через atom:link
+                        for l in link.iter().filter(|l| l.rel() != LinkRel::Alternate) {
+                            let attrs = [
+                                ("href", l.href.attr_value()),
+                                ("rel", Some(l.rel().to_string())),
+                            ];
+                            w.create_element("atom:link")
+                                .with_attributes(xml::attributes(&attrs))
+                                .write_empty()?;
+                        }
+                        xml::text_element(w, "lastBuildDate", &rfc2822(updated))?;
+                        if let Some(g) = generator {
+                            let version = g
+                                .version
+                                .as_ref()
+                                .map_or(String::new(), |v| format!(" {}", v.0));
+                            xml::text_element(w, "generator", &format!("{}{version}", g.name.0))?;
+                        }
+                        if let Some(l) = lang {
+                            xml::text_element(w, "language", &l.0)?;
+                        }
+                        // Только один редактор
+                        if let Some((email, author)) =
+                            author.iter().find_map(|a| a.email.as_ref().map(|e| (e, a)))
+                        {
+                            let editor = format!("{} ({})", email.0, author.name);
+                            xml::text_element(w, "managingEditor", &editor)?;
+                        }
+                        if let Some(url) = logo.as_ref().or(icon.as_ref()) {
+                            w.create_element("image").write_inner_content(|w| {
+                                xml::text_element(w, "url", &url.0)?;
+                                title.write_tag(w, "title")?;
+                                xml::text_element(w, "link", site_url)
+                            })?;
+                        }
+                        if let Some(r) = rights {
+                            xml::cdata_element(w, "copyright", r.text())?;
+                        }
+                        if let Some(c) = categories {
+                            write_categories(w, &c.0)?;
+                        }
+                        for e in entries {
+                            Rss(e).write_tag(w, "item")?;
+                        }
+                        Ok(())
+                    })?;
+                    Ok(())
+                })?;
+            Ok(())
+        });
+        f.write_str(&document)
+    }
+}
+
+impl FeedElement for Rss<'_, Entry> {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
+        let Entry {
+            id,
+            title,
+            updated,
+            author,
+            content,
+            link,
+            summary,
+            categories,
+            contributor: _,
+            published,
+            rights: _,
+            source,
+            base: _,
+            lang: _,
+        } = self.0;
+        let is_permalink = id.starts_with("http://") || id.starts_with("https://");
+        let pub_date = published.as_ref().map_or(updated, |p| &p.0);
+        let description = content.as_ref().or(summary.as_ref()).map(Content::text);
+        w.create_element(tag).write_inner_content(|w| {
+            title.write_tag(w, "title")?;
+            if let Some(l) = link.iter().find(|l| l.rel() == LinkRel::Alternate) {
+                xml::text_element(w, "link", &l.href.0)?;
+            }
+            w.create_element("guid")
+                .with_attribute(("isPermaLink", is_permalink.to_string().as_str()))
+                .write_inner_content(|w| xml::text(w, id))?;
+            xml::text_element(w, "pubDate", &rfc2822(pub_date))?;
+            for a in author.iter().filter(|a| !a.name.is_empty()) {
+                xml::cdata_element(w, "dc:creator", &a.name)?;
+            }
+            if let Some(d) = description {
+                xml::cdata_element(w, "description", d)?;
+            }
+            if let Some(c) = categories {
+                write_categories(w, &c.0)?;
+            }
+            for l in link.iter().filter(|l| l.rel() == LinkRel::Enclosure) {
+                // length обязателен, 0 если неизвестен
+                let attrs = [
+                    ("url", l.href.attr_value()),
+                    (
+                        "length",
+                        Some(l.length.as_ref().map_or(0, |l| l.0).to_string()),
+                    ),
+                    (
+                        "type",
+                        Some(
+                            l.mime_type
+                                .attr_value()
+                                .unwrap_or_else(|| "application/octet-stream".to_string()),
+                        ),
+                    ),
+                ];
+                w.create_element("enclosure")
+                    .with_attributes(xml::attributes(&attrs))
+                    .write_empty()?;
+            }
+            // В RSS у source обязателен url самого канала
+            if let Some((source, url)) = source.as_ref().and_then(|s| {
+                s.link
+                    .iter()
+                    .find(|l| l.rel() == LinkRel::_Self)
+                    .map(|l| (s, l))
+            }) {
+                w.create_element("source")
+                    .with_attributes(xml::attributes(&[("url", url.href.attr_value())]))
+                    .write_inner_content(|w| xml::text(w, &source.title.0))?;
+            }
+            Ok(())
+        })?;
+        Ok(())
+    }
+}
+
+impl Display for Rss<'_, Entry> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.render_tag("item"))
+    }
+}
@@ -1,27 +1,59 @@
 use crate::utils::escape;
+use crate::xml::{self, XmlWriter};
 use crate::{Attribute, CDATAElement, Element};
+use chrono::{DateTime, FixedOffset};
 use std::fmt::{Display, Formatter};
-use std::format;
 
 pub trait FeedElement {
-    fn render_tag(&self, tag: &str) -> String;
-}
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result;
 
-impl<T: Display> FeedElement for Element<T> {
     fn render_tag(&self, tag: &str) -> String {
-        format!("<{tag}>{}</{tag}>", self.0)
+        xml::render(|w| self.write_tag(w, tag))
+    }
+}
+
+/// Inner content of [`Element`], escaped text for plain values
+pub trait FeedContent {
+    fn write_content(&self, w: &mut XmlWriter) -> xml::Result;
+}
+
+impl FeedContent for String {
+    fn write_content(&self, w: &mut XmlWriter) -> xml::Result {
+        xml::text(w, self)
+    }
+}
+
+/// RFC 3339, the only format allowed by Atom
+impl FeedContent for DateTime<FixedOffset> {
+    fn write_content(&self, w: &mut XmlWriter) -> xml::Result {
+        xml::text(w, &self.to_rfc3339())
+    }
+}
+
+impl<T: FeedContent> FeedElement for Element<T> {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
+        w.create_element(tag)
+            .write_inner_content(|w| self.0.write_content(w))?;
+        Ok(())
     }
 }
 
 impl<T: Display> FeedElement for CDATAElement<T> {
-    fn render_tag(&self, tag: &str) -> String {
-        format!("<{tag}><![CDATA[{}]]></{tag}>", self.0)
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
+        xml::cdata_element(w, tag, &self.0.to_string())
     }
 }
 
 impl<T: FeedElement> FeedElement for Option<T> {
-    fn render_tag(&self, tag: &str) -> String {
-        self.as_ref().map_or(String::new(), |e| e.render_tag(tag))
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
+        self.as_ref().map_or(Ok(()), |e| e.write_tag(w, tag))
+    }
+}
+
+/// Repeated element, e.g. `link` or `author`
+impl<T: FeedElement> FeedElement for Vec<T> {
+    fn write_tag(&self, w: &mut XmlWriter, tag: &str) -> xml::Result {
+        self.iter().try_for_each(|e| e.write_tag(w, tag))
     }
 }
 
@@ -32,18 +64,23 @@ impl<T: Display> Display for Element<T> {
 }
 
 pub trait FeedAttribute {
-    fn render_attr(&self, name: &str) -> String;
+    /// Value without chars not allowed in XML, not escaped yet
+    fn attr_value(&self) -> Option<String>;
+
+    fn render_attr(&self, name: &str) -> String {
+        self.attr_value()
+            .map_or(String::new(), |v| format!(r#"{name}="{}""#, escape(&v)))
+    }
 }
 
 impl<T: FeedAttribute> FeedAttribute for Option<T> {
-    fn render_attr(&self, name: &str) -> String {
-        self.as_ref().map_or(String::new(), |e| e.render_attr(name))
+    fn attr_value(&self) -> Option<String> {
+        self.as_ref().and_then(|a| a.attr_value())
     }
 }
 
 impl<T: Display> FeedAttribute for Attribute<T> {
-    fn render_attr(&self, name: &str) -> String {
-        let s = format!(r#"{name}="{}""#, self.0);
-        escape(s.as_str())
+    fn attr_value(&self) -> Option<String> {
+        Some(xml::clean(&self.0.to_string()).into_owned())
     }
 }
@@ -1,10 +1,12 @@
+use crate::xml::clean;
+
 ///
 /// ```
 /// use feed::utils::escape;
 /// let s = escape("foo&bar=1");
 /// assert_eq!(s.as_str(), "foo&amp;bar=1");
+/// assert_eq!(escape(r#""<a>""#), "&quot;&lt;a&gt;&quot;");
 /// ```
 pub fn escape(s: &str) -> String {
-    // TODO check escapes
-    s.replace('&', "&amp;")
+    quick_xml::escape::escape(&clean(s)).into_owned()
 }
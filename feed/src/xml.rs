@@ -0,0 +1,99 @@
+// All feed documents are written with quick-xml, never with format!,
+// so text, attributes and CDATA are always escaped
+
+use quick_xml::events::{BytesCData, BytesDecl, BytesText, Event};
+use quick_xml::Writer;
+use std::borrow::Cow;
+
+pub type XmlWriter = Writer<Vec<u8>>;
+pub type Result = quick_xml::Result<()>;
+
+/// Chars allowed by XML 1.0, others make document invalid even if escaped
+fn is_xml_char(c: char) -> bool {
+    matches!(c,
+        '\t' | '\n' | '\r'
+        | '\u{20}'..='\u{D7FF}'
+        | '\u{E000}'..='\u{FFFD}'
+        | '\u{10000}'..='\u{10FFFF}')
+}
+
+/// Strip chars not allowed in XML, e.g. control chars from Telegram posts
+/// ```
+/// use feed::xml::clean;
+/// assert_eq!(clean("a\u{0}b\u{1b}c\td"), "abc\td");
+/// assert_eq!(clean("foo & bar"), "foo & bar");
+/// ```
+pub fn clean(s: &str) -> Cow<'_, str> {
+    if s.chars().all(is_xml_char) {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(s.chars().filter(|c| is_xml_char(*c)).collect())
+    }
+}
+
+/// Escaped text node
+pub fn text(w: &mut XmlWriter, s: &str) -> Result {
+    w.write_event(Event::Text(BytesText::new(&clean(s))))
+}
+
+/// CDATA section, `]]>` inside splits it in two
+/// ```
+/// use feed::xml::{cdata, render};
+/// assert_eq!(render(|w| cdata(w, "a]]>b")), "<![CDATA[a]]]]><![CDATA[>b]]>");
+/// ```
+pub fn cdata(w: &mut XmlWriter, s: &str) -> Result {
+    let s = clean(s);
+    let mut rest: &str = &s;
+    while let Some(i) = rest.find("]]>") {
+        w.write_event(Event::CData(BytesCData::new(&rest[..i + 2])))?;
+        rest = &rest[i + 2..];
+    }
+    w.write_event(Event::CData(BytesCData::new(rest)))
+}
+
+/// `<tag>text</tag>`
+pub fn text_element(w: &mut XmlWriter, tag: &str, s: &str) -> Result {
+    w.create_element(tag).write_inner_content(|w| text(w, s))?;
+    Ok(())
+}
+
+/// `<tag><![CDATA[text]]></tag>`
+pub fn cdata_element(w: &mut XmlWriter, tag: &str, s: &str) -> Result {
+    w.create_element(tag).write_inner_content(|w| cdata(w, s))?;
+    Ok(())
+}
+
+/// Attribute pairs without missing ones, for `ElementWriter::with_attributes`
+pub fn attributes<'a>(
+    attrs: &'a [(&'a str, Option<String>)],
+) -> impl Iterator<Item = (&'a str, &'a str)> {
+    attrs
+        .iter()
+        .filter_map(|(name, value)| value.as_deref().map(|v| (*name, v)))
+}
+
+fn into_string(w: XmlWriter) -> String {
+    // quick-xml пишет только валидный utf-8
+    String::from_utf8(w.into_inner()).unwrap()
+}
+
+/// Fragment, e.g. single element, without indentation
+/// ```
+/// use feed::xml::{render, text_element};
+/// assert_eq!(render(|w| text_element(w, "a", "<b>")), "<a>&lt;b&gt;</a>");
+/// ```
+pub fn render(f: impl FnOnce(&mut XmlWriter) -> Result) -> String {
+    let mut w = Writer::new(Vec::new());
+    // Запись в Vec не падает
+    f(&mut w).unwrap();
+    into_string(w)
+}
+
+/// Indented document with xml declaration
+pub fn render_document(f: impl FnOnce(&mut XmlWriter) -> Result) -> String {
+    let mut w = Writer::new_with_indent(Vec::new(), b' ', 2);
+    w.write_event(Event::Decl(BytesDecl::new("1.0", Some("utf-8"), None)))
+        .and_then(|_| f(&mut w))
+        .unwrap();
+    into_string(w)
+}
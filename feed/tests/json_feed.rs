@@ -0,0 +1,93 @@
+use feed::{CDATAElement, Category, Content, Element, Entry, Feed, Link, LinkRel, Person};
+use serde_json::{json, Value};
+
+#[test]
+fn json_feed() {
+    let mut entry = Entry::new(
+        "channel/1".to_string(),
+        "Episode 1".to_string(),
+        "2023-03-01T10:00:00+03:00".parse().unwrap(),
+    );
+    entry.id = "channel/1".to_string();
+    entry.author = vec![Person::new(
+        "Foo".to_string(),
+        Some("https://example.com/foo".to_string()),
+        None,
+    )];
+    entry.content = Some(Content::Html("<p>Hello</p>".to_string()));
+    entry.link = vec![
+        Link::new("https://example.com/posts/1".to_string()),
+        Link::enclosure(
+            "https://example.com/1.ogg".to_string(),
+            "audio/ogg",
+            Some(1024),
+        ),
+    ];
+    entry.categories = Some(Element(vec![
+        Category::new("news".to_string(), None, None),
+        Category::new("t".to_string(), None, Some("Tech".to_string())),
+    ]));
+    let feed = Feed {
+        id: "https://example.com/".to_string(),
+        title: CDATAElement("Foo".to_string()),
+        subtitle: Some(Content::Text("Foo podcast".to_string())),
+        link: vec![
+            Link::new("https://example.com/".to_string()),
+            Link::with_rel("https://example.com/feed.json".to_string(), LinkRel::_Self),
+            Link::with_rel(
+                "https://example.com/feed.json?before=1".to_string(),
+                LinkRel::Next,
+            ),
+        ],
+        entries: vec![entry],
+        ..Feed::default()
+    };
+    let value: Value = serde_json::from_str(&feed.to_json()).unwrap();
+    assert_eq!(
+        value,
+        json!({
+            "version": "https://jsonfeed.org/version/1.1",
+            "title": "Foo",
+            "home_page_url": "https://example.com/",
+            "feed_url": "https://example.com/feed.json",
+            "description": "Foo podcast",
+            "next_url": "https://example.com/feed.json?before=1",
+            "items": [{
+                "id": "channel/1",
+                "url": "https://example.com/posts/1",
+                "title": "Episode 1",
+                "content_html": "<p>Hello</p>",
+                "date_modified": "2023-03-01T10:00:00+03:00",
+                "authors": [{"name": "Foo", "url": "https://example.com/foo"}],
+                "tags": ["news", "Tech"],
+                "attachments": [{
+                    "url": "https://example.com/1.ogg",
+                    "mime_type": "audio/ogg",
+                    "size_in_bytes": 1024
+                }]
+            }]
+        })
+    );
+}
+
+#[test]
+fn json_feed_empty_item() {
+    let mut entry = Entry::new("1".to_string(), String::new(), Default::default());
+    entry.id = "1".to_string();
+    let feed = Feed {
+        entries: vec![entry],
+        ..Feed::default()
+    };
+    let value: Value = serde_json::from_str(&feed.to_json()).unwrap();
+    // Обязательные поля есть, пустые пропущены
+    assert_eq!(
+        value["items"][0],
+        json!({
+            "id": "1",
+            "title": "no title",
+            "content_text": "",
+            "date_modified": "1970-01-01T00:00:00+00:00"
+        })
+    );
+    assert!(value.get("authors").is_none());
+}
@@ -1 +1,5 @@
+mod json_feed;
+mod parse;
 mod render;
+mod rss;
+mod validate;
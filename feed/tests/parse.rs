@@ -0,0 +1,140 @@
+use feed::opml::OPML;
+use feed::parse::ParseError;
+use feed::{
+    Attribute, CDATAElement, Category, Content, Element, Entry, Feed, Generator, Link, LinkRel,
+    Person,
+};
+
+fn full_feed() -> Feed {
+    let entry = Entry {
+        id: "https://example.com/posts/1".to_string(),
+        title: CDATAElement("Post <1>".to_string()),
+        updated: "2023-03-01T10:00:00+03:00".parse().unwrap(),
+        author: vec![Person::new(
+            "Foo".to_string(),
+            Some("https://example.com/foo".to_string()),
+            Some("foo@example.com".to_string()),
+        )],
+        content: Some(Content::Html("<p>Hello &amp; bye</p>".to_string())),
+        link: vec![
+            Link::new("https://example.com/posts/1?a=1&b=2".to_string()),
+            Link {
+                title: Some(Attribute("Audio".to_string())),
+                ..Link::enclosure(
+                    "https://example.com/1.ogg".to_string(),
+                    "audio/ogg",
+                    Some(1024),
+                )
+            },
+        ],
+        summary: Some(Content::Text("Hello".to_string())),
+        categories: Some(Element(vec![Category::new(
+            "news".to_string(),
+            Some("https://example.com/tags".to_string()),
+            Some("News".to_string()),
+        )])),
+        contributor: vec![Person::new("Bar".to_string(), None, None)],
+        published: Some(Element("2023-03-01T09:00:00+03:00".parse().unwrap())),
+        rights: Some(Content::Text("CC BY".to_string())),
+        source: Some(Box::new(Feed {
+            id: "https://other.example.com/".to_string(),
+            title: CDATAElement("Other".to_string()),
+            updated: "2023-02-28T10:00:00Z".parse().unwrap(),
+            link: vec![Link::with_rel(
+                "https://other.example.com/feed/".to_string(),
+                LinkRel::_Self,
+            )],
+            generator: Some(Generator::new("Other".to_string(), None, None)),
+            ..Feed::default()
+        })),
+        base: Some(Attribute("https://example.com/posts/".to_string())),
+        lang: Some(Attribute("ru".to_string())),
+    };
+    Feed {
+        id: "https://example.com/".to_string(),
+        title: CDATAElement("Foo & bar".to_string()),
+        updated: "2023-03-01T10:00:00+03:00".parse().unwrap(),
+        author: vec![
+            Person::new("Foo".to_string(), None, None),
+            Person::new("Baz".to_string(), None, Some("baz@example.com".to_string())),
+        ],
+        link: vec![
+            Link::new("https://example.com/".to_string()),
+            Link::with_rel("https://example.com/feed/".to_string(), LinkRel::_Self),
+            Link::with_rel(
+                "https://example.com/feed/?before=1".to_string(),
+                LinkRel::Next,
+            ),
+        ],
+        categories: Some(Element(vec![Category::new("blog".to_string(), None, None)])),
+        contributor: vec![Person::new("Bar".to_string(), None, None)],
+        generator: Some(Generator::new(
+            "any2feed".to_string(),
+            Some("https://github.com/Apkawa/rust-any2feed".to_string()),
+            Some("0.1.0".to_string()),
+        )),
+        icon: Some(Element("https://example.com/favicon.ico".to_string())),
+        logo: Some(Element("https://example.com/logo.png".to_string())),
+        rights: Some(Content::Text("CC BY".to_string())),
+        subtitle: Some(Content::Html("<b>Foo</b> blog".to_string())),
+        entries: vec![entry],
+        base: None,
+        lang: Some(Attribute("en".to_string())),
+    }
+}
+
+#[test]
+fn atom_round_trip() {
+    let feed = full_feed();
+    let parsed: Feed = feed.to_string().parse().unwrap();
+    assert_eq!(parsed, feed);
+}
+
+#[test]
+fn atom_external() {
+    let feed: Feed = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:media="http://search.yahoo.com/mrss/">
+  <title type="text">Example</title>
+  <id>urn:uuid:60a76c80-d399-11d9-b91C-0003939e0af6</id>
+  <updated>2003-12-13T18:30:02Z</updated>
+  <generator uri="https://example.com/gen" version="1.0">Gen</generator>
+  <link rel="hub" href="https://hub.example.com/"/>
+  <entry>
+    <id>urn:uuid:1225c695-cfb8-4ebb-aaaa-80da344efa6a</id>
+    <title>Atom-Powered Robots Run Amok</title>
+    <updated>2003-12-13T18:30:02Z</updated>
+    <author><name>John Doe</name><uri>https://example.com/john</uri></author>
+    <content type="xhtml"><div xmlns="http://www.w3.org/1999/xhtml"><p>Robots</p></div></content>
+    <media:thumbnail url="https://example.com/t.jpg"/>
+  </entry>
+</feed>"#
+        .parse()
+        .unwrap();
+    assert_eq!(feed.generator.as_ref().unwrap().name.0, "Gen");
+    // Unknown rel skipped
+    assert!(feed.link.is_empty());
+    let entry = &feed.entries[0];
+    assert_eq!(
+        entry.author[0].url.as_ref().unwrap().0,
+        "https://example.com/john"
+    );
+    assert_eq!(
+        entry.content,
+        Some(Content::Xhtml(
+            r#"<div xmlns="http://www.w3.org/1999/xhtml"><p>Robots</p></div>"#.to_string()
+        ))
+    );
+}
+
+#[test]
+fn parse_errors() {
+    assert!(matches!(
+        "<feed><id></feed>".parse::<Feed>(),
+        Err(ParseError::Xml(_))
+    ));
+    assert!(matches!(
+        "<opml/>".parse::<Feed>(),
+        Err(ParseError::UnexpectedRoot(tag)) if tag == "opml"
+    ));
+    assert!("<feed/>".parse::<OPML>().is_err());
+}
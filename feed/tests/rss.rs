@@ -0,0 +1,86 @@
+use feed::rss::Rss;
+use feed::{CDATAElement, Category, Content, Element, Entry, Feed, Link, LinkRel, Person};
+
+fn example_entry() -> Entry {
+    let mut entry = Entry::new(
+        "https://example.com/posts/1".to_string(),
+        "Episode 1".to_string(),
+        "2023-03-01T10:00:00+03:00".parse().unwrap(),
+    );
+    entry.id = "https://example.com/posts/1".to_string();
+    entry.author = vec![Person::new("Foo".to_string(), None, None)];
+    entry.content = Some(Content::Html("<p>Hello</p>".to_string()));
+    entry.link = vec![
+        Link::new("https://example.com/posts/1".to_string()),
+        Link::enclosure(
+            "https://example.com/1.ogg?a=1&b=2".to_string(),
+            "audio/ogg",
+            Some(1024),
+        ),
+    ];
+    entry.categories = Some(Element(vec![Category::new(
+        "news".to_string(),
+        Some("https://example.com/tags".to_string()),
+        None,
+    )]));
+    entry
+}
+
+#[test]
+fn rss_item() {
+    let item = Rss(&example_entry()).to_string();
+    assert!(item.contains("<title><![CDATA[Episode 1]]></title>"));
+    assert!(item.contains("<link>https://example.com/posts/1</link>"));
+    assert!(item.contains(r#"<guid isPermaLink="true">https://example.com/posts/1</guid>"#));
+    assert!(item.contains("<pubDate>Wed, 01 Mar 2023 10:00:00 +0300</pubDate>"));
+    assert!(item.contains("<dc:creator><![CDATA[Foo]]></dc:creator>"));
+    assert!(item.contains("<description><![CDATA[<p>Hello</p>]]></description>"));
+    assert!(
+        item.contains(r#"<category domain="https://example.com/tags"><![CDATA[news]]></category>"#)
+    );
+    assert!(item.contains(
+        r#"<enclosure url="https://example.com/1.ogg?a=1&amp;b=2" length="1024" type="audio/ogg"/>"#
+    ));
+}
+
+#[test]
+fn rss_item_guid_not_permalink() {
+    let mut entry = example_entry();
+    entry.id = "channel/1".to_string();
+    entry.published = Some(Element("2023-02-28T10:00:00Z".parse().unwrap()));
+    let item = Rss(&entry).to_string();
+    assert!(item.contains(r#"<guid isPermaLink="false">channel/1</guid>"#));
+    // Дата публикации важнее даты обновления
+    assert!(item.contains("<pubDate>Tue, 28 Feb 2023 10:00:00 +0000</pubDate>"));
+}
+
+#[test]
+fn rss_channel() {
+    let feed = Feed {
+        id: "https://example.com/".to_string(),
+        title: CDATAElement("Foo".to_string()),
+        updated: "2023-03-01T10:00:00Z".parse().unwrap(),
+        subtitle: Some(Content::Text("Foo podcast".to_string())),
+        logo: Some(Element("https://example.com/logo.png".to_string())),
+        link: vec![
+            Link::new("https://example.com/".to_string()),
+            Link::with_rel("https://example.com/feed.rss".to_string(), LinkRel::_Self),
+            Link::with_rel(
+                "https://example.com/feed.rss?before=1".to_string(),
+                LinkRel::Next,
+            ),
+        ],
+        entries: vec![example_entry()],
+        ..Feed::default()
+    };
+    let rss = feed.to_rss();
+    assert!(rss.starts_with(r#"<?xml version="1.0" encoding="utf-8"?>"#));
+    assert!(rss.contains("<link>https://example.com/</link>"));
+    assert!(rss.contains("<description><![CDATA[Foo podcast]]></description>"));
+    assert!(rss.contains(r#"<atom:link href="https://example.com/feed.rss" rel="self"/>"#));
+    assert!(rss.contains(r#"<atom:link href="https://example.com/feed.rss?before=1" rel="next"/>"#));
+    assert!(rss.contains("<lastBuildDate>Wed, 01 Mar 2023 10:00:00 +0000</lastBuildDate>"));
+    assert!(rss.contains("<url>https://example.com/logo.png</url>"));
+    assert_eq!(rss.matches("<item>").count(), 1);
+    assert!(rss.ends_with("</channel>\n</rss>"));
+}
@@ -0,0 +1,152 @@
+// Every generated document must stay well-formed whatever comes from sources
+use feed::opml::{Outline, OPML};
+use feed::{
+    Attribute, CDATAElement, Category, Content, Element, Entry, Feed, Link, LinkRel, Person,
+};
+
+/// Everything readers choked on: markup, entities, quotes, CDATA end and control chars
+const NASTY: &str = "Tom & Jerry <b>\"quoted\"</b> 'single' ]]> &amp; \u{0}\u{1b}end";
+/// Same string as it must be read back
+const CLEANED: &str = "Tom & Jerry <b>\"quoted\"</b> 'single' ]]> &amp; end";
+
+fn person(s: &str) -> Person {
+    Person::new(
+        s.to_string(),
+        Some(format!("https://example.com/?q={s}")),
+        Some(s.to_string()),
+    )
+}
+
+fn example_feed(s: &str) -> Feed {
+    let entry = Entry {
+        id: format!("https://example.com/1?a=1&b={s}"),
+        title: CDATAElement(s.to_string()),
+        updated: "2023-03-01T10:00:00+03:00".parse().unwrap(),
+        author: vec![person(s)],
+        content: Some(Content::Html(format!("<p>{s}</p>"))),
+        link: vec![
+            Link {
+                title: Some(Attribute(s.to_string())),
+                ..Link::new(format!("https://example.com/1?a=1&b={s}"))
+            },
+            Link::enclosure(
+                format!("https://example.com/1.ogg?a=1&b={s}"),
+                "audio/ogg",
+                None,
+            ),
+        ],
+        summary: Some(Content::Text(s.to_string())),
+        categories: Some(Element(vec![Category::new(
+            s.to_string(),
+            Some(format!("https://example.com/tags?{s}")),
+            Some(s.to_string()),
+        )])),
+        contributor: vec![person(s)],
+        published: Some(Element("2023-03-01T09:00:00+03:00".parse().unwrap())),
+        rights: Some(Content::Text(s.to_string())),
+        source: Some(Box::new(Feed {
+            id: format!("https://other.example.com/?{s}"),
+            title: CDATAElement(s.to_string()),
+            link: vec![Link::with_rel(
+                format!("https://other.example.com/feed/?{s}"),
+                LinkRel::_Self,
+            )],
+            ..Feed::default()
+        })),
+        base: Some(Attribute(format!("https://example.com/?{s}"))),
+        lang: Some(Attribute(s.to_string())),
+    };
+    Feed {
+        id: format!("https://example.com/?{s}"),
+        title: CDATAElement(s.to_string()),
+        updated: "2023-03-01T10:00:00+03:00".parse().unwrap(),
+        author: vec![person(s)],
+        link: vec![
+            Link::new(format!("https://example.com/?{s}")),
+            Link::with_rel(format!("https://example.com/feed/?{s}"), LinkRel::_Self),
+        ],
+        categories: Some(Element(vec![Category::new(s.to_string(), None, None)])),
+        icon: Some(Element(format!("https://example.com/favicon.ico?{s}"))),
+        logo: Some(Element(format!("https://example.com/logo.png?{s}"))),
+        rights: Some(Content::Html(s.to_string())),
+        subtitle: Some(Content::Text(s.to_string())),
+        entries: vec![entry],
+        ..Feed::default()
+    }
+}
+
+#[test]
+fn atom_well_formed() {
+    let xml = example_feed(NASTY).to_string();
+    roxmltree::Document::parse(&xml).unwrap();
+    assert!(!xml.contains('\u{0}'));
+    assert_eq!(xml.parse::<Feed>().unwrap(), example_feed(CLEANED));
+}
+
+#[test]
+fn atom_xhtml_content() {
+    let mut feed = example_feed(CLEANED);
+    let xhtml = r#"<div xmlns="http://www.w3.org/1999/xhtml"><p>Tom &amp; Jerry</p></div>"#;
+    feed.entries[0].content = Some(Content::Xhtml(xhtml.to_string()));
+    let parsed: Feed = feed.to_string().parse().unwrap();
+    assert_eq!(parsed, feed);
+
+    // Невалидная разметка уходит как html, а не ломает документ
+    feed.entries[0].content = Some(Content::Xhtml(format!("<p>{NASTY}")));
+    let parsed: Feed = feed.to_string().parse().unwrap();
+    assert_eq!(
+        parsed.entries[0].content,
+        Some(Content::Html(format!("<p>{CLEANED}")))
+    );
+}
+
+#[test]
+fn rss_well_formed() {
+    let xml = example_feed(NASTY).to_rss();
+    let doc = roxmltree::Document::parse(&xml).unwrap();
+    let text = |name: &str| {
+        doc.descendants()
+            .find(|n| n.has_tag_name(name))
+            .map(|n| {
+                n.descendants()
+                    .filter(|t| t.is_text())
+                    .filter_map(|t| t.text())
+                    .collect::<String>()
+            })
+            .unwrap()
+    };
+    assert_eq!(text("title"), CLEANED);
+    assert_eq!(text("description"), CLEANED);
+    assert_eq!(
+        text("guid"),
+        format!("https://example.com/1?a=1&b={CLEANED}")
+    );
+    let enclosure = doc
+        .descendants()
+        .find(|n| n.has_tag_name("enclosure"))
+        .unwrap();
+    assert_eq!(
+        enclosure.attribute("url").unwrap(),
+        format!("https://example.com/1.ogg?a=1&b={CLEANED}")
+    );
+}
+
+#[test]
+fn json_feed_valid() {
+    let json: serde_json::Value = serde_json::from_str(&example_feed(NASTY).to_json()).unwrap();
+    assert_eq!(json["items"][0]["title"], NASTY);
+}
+
+#[test]
+fn opml_well_formed() {
+    let opml = |s: &str| {
+        let mut opml = OPML::new(s).add_outline(
+            Outline::new(s).add_child(s, Some(&format!("https://example.com/feed?{s}"))),
+        );
+        opml.created = Some(chrono::DateTime::default());
+        opml
+    };
+    let xml = opml(NASTY).to_string();
+    roxmltree::Document::parse(&xml).unwrap();
+    assert_eq!(xml.parse::<OPML>().unwrap(), opml(CLEANED));
+}
@@ -0,0 +1,480 @@
+//! Async server on tokio runtime, serves same [`Route`](crate::Route)s as blocking [`crate::Server`].
+//!
+//! Connection waits don't occupy threads, blocking callbacks run on blocking threads of runtime
+use std::future::Future;
+use std::io;
+use std::io::{Cursor, Read};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+
+use crate::server::auth::redact_token;
+use crate::server::config::ServerConfig;
+use crate::server::error;
+use crate::server::http_server::{
+    dispatch, finish_response, read_timeout, shutdown_on_signals, Dispatch, Head, HeadTooLarge,
+    ShutdownHandle, MAX_KEEP_ALIVE_REQUESTS,
+};
+use crate::server::request::{HTTPMethod, HTTPRequest, MAX_BODY_SIZE};
+use crate::server::response::{ContentStream, HTTPResponse};
+use crate::server::HTTPError;
+
+/// Size of chunk of streamed body
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Io `future` with optional timeout
+async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = io::Result<T>>,
+) -> io::Result<T> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future)
+            .await
+            .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into())),
+        None => future.await,
+    }
+}
+
+/// Read request line and headers, empty on closed connection
+async fn read_head<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Vec<String>> {
+    let mut head = Head::default();
+    loop {
+        let mut line = Vec::new();
+        let mut limited = (&mut *reader).take(head.remaining());
+        if limited.read_until(b'\n', &mut line).await? == 0 {
+            // Соединение закрыто
+            return Ok(head.lines);
+        }
+        if head.push(&line)? {
+            return Ok(head.lines);
+        }
+    }
+}
+
+/// Raw body with `Transfer-Encoding` framing, parsed by [`HTTPRequest::read_body`]
+async fn read_raw_body<R: AsyncBufRead + Unpin>(
+    request: &HTTPRequest<'_>,
+    reader: &mut R,
+) -> io::Result<Vec<u8>> {
+    let mut raw: Vec<u8> = Vec::new();
+    if request
+        .header("Transfer-Encoding")
+        .is_some_and(|t| t.to_ascii_lowercase().contains("chunked"))
+    {
+        loop {
+            let start = raw.len();
+            if reader.read_until(b'\n', &mut raw).await? == 0 {
+                break;
+            }
+            let line = String::from_utf8_lossy(&raw[start..]);
+            let size = line.split(';').next().unwrap_or_default().trim();
+            // Некорректный размер отклонит read_body
+            let Ok(size) = usize::from_str_radix(size, 16) else {
+                break;
+            };
+            if raw.len() + size > MAX_BODY_SIZE * 2 {
+                break;
+            }
+            if size == 0 {
+                // Trailer headers
+                loop {
+                    let start = raw.len();
+                    if reader.read_until(b'\n', &mut raw).await? == 0
+                        || raw[start..].iter().all(|b| b.is_ascii_whitespace())
+                    {
+                        break;
+                    }
+                }
+                break;
+            }
+            // Данные и CRLF
+            (&mut *reader)
+                .take(size as u64 + 2)
+                .read_to_end(&mut raw)
+                .await?;
+        }
+    } else if let Some(length) = request
+        .header("Content-Length")
+        .and_then(|l| l.trim().parse::<usize>().ok())
+        .filter(|l| *l <= MAX_BODY_SIZE)
+    {
+        (&mut *reader)
+            .take(length as u64)
+            .read_to_end(&mut raw)
+            .await?;
+    }
+    Ok(raw)
+}
+
+/// Copy blocking [`ContentStream`] to client, reading on blocking thread.
+/// Same format as [`ContentStream::write_to`]
+async fn write_stream<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    mut stream: ContentStream,
+    write_timeout: Option<Duration>,
+) -> io::Result<u64> {
+    let mut written: u64 = 0;
+    loop {
+        let size = match stream.length {
+            Some(length) if written >= length => return Ok(written),
+            Some(length) => (length - written).min(STREAM_CHUNK_SIZE as u64) as usize,
+            None => STREAM_CHUNK_SIZE,
+        };
+        let (returned, chunk) = tokio::task::spawn_blocking(move || {
+            let mut buf = vec![0; size];
+            let result = loop {
+                match stream.reader.read(&mut buf) {
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    result => break result,
+                }
+            };
+            (stream, result.map(|n| buf[..n].to_vec()))
+        })
+        .await
+        .map_err(io::Error::other)?;
+        stream = returned;
+        let chunk = chunk?;
+        if chunk.is_empty() {
+            if let Some(length) = stream.length {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("Stream ended at {written} of {length} bytes"),
+                ));
+            }
+            if stream.until_close {
+                return Ok(written);
+            }
+            with_timeout(write_timeout, writer.write_all(b"0\r\n\r\n")).await?;
+            return Ok(written);
+        }
+        if stream.length.is_some() || stream.until_close {
+            with_timeout(write_timeout, writer.write_all(&chunk)).await?;
+        } else {
+            let mut framed = format!("{:x}\r\n", chunk.len()).into_bytes();
+            framed.extend_from_slice(&chunk);
+            framed.extend_from_slice(b"\r\n");
+            with_timeout(write_timeout, writer.write_all(&framed)).await?;
+        }
+        written += chunk.len() as u64;
+    }
+}
+
+async fn write_response<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    response: &mut HTTPResponse,
+    with_body: bool,
+    write_timeout: Option<Duration>,
+) -> io::Result<()> {
+    let head = response.to_string();
+    with_timeout(write_timeout, writer.write_all(head.as_bytes())).await?;
+    if with_body {
+        if let Some(stream) = response.content_stream.take() {
+            write_stream(writer, stream, write_timeout).await?;
+        } else if let Some(content) = response.content.as_ref() {
+            with_timeout(write_timeout, writer.write_all(content)).await?;
+        }
+    }
+    with_timeout(write_timeout, writer.flush()).await
+}
+
+/// Run callback of route with deadline [`ServerConfig::request_timeout`]
+async fn run_route(
+    config: &ServerConfig,
+    route_index: usize,
+    request: &HTTPRequest<'static>,
+) -> error::Result<HTTPResponse> {
+    let future = config.routes[route_index].run_cb_async(request.detach());
+    let Some(timeout) = config.request_timeout() else {
+        return future.await;
+    };
+    // Задача колбэка продолжит работу в фоне
+    tokio::time::timeout(timeout, future)
+        .await
+        .unwrap_or_else(|_| {
+            log::warn!(
+                "Request {} timed out after {:?}",
+                request.log_path(),
+                timeout
+            );
+            Err(HTTPError::gateway_timeout("Request timed out"))
+        })
+}
+
+/// Handle one request, returns `true` if connection should be kept
+async fn handle_request<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    lines: &[String],
+    local_addr: Option<SocketAddr>,
+    config: &Arc<ServerConfig>,
+    can_keep_alive: bool,
+    shutdown: &ShutdownHandle,
+) -> bool
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let write_timeout = config.request_timeout();
+    let mut request: HTTPRequest<'static> = match HTTPRequest::parse(lines) {
+        Ok(r) => r,
+        Err(e) => {
+            log::warn!(
+                "Invalid request {:?}: {:?}",
+                lines.first().map(|l| redact_token(l)),
+                e
+            );
+            let mut response = HTTPResponse::new(400).set_header("Connection", "close");
+            let _ = write_response(writer, &mut response, false, write_timeout).await;
+            return false;
+        }
+    };
+    if request
+        .header("Expect")
+        .is_some_and(|e| e.eq_ignore_ascii_case("100-continue"))
+        && with_timeout(write_timeout, async {
+            writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+            writer.flush().await
+        })
+        .await
+        .is_err()
+    {
+        return false;
+    }
+    let body = with_timeout(read_timeout(config, false), read_raw_body(&request, reader)).await;
+    if let Err(e) = body
+        .map_err(|e| {
+            log::error!("Read body fail: {:?}", e);
+            HTTPError::InvalidRequest
+        })
+        .and_then(|body| request.read_body(&mut Cursor::new(body)))
+    {
+        log::warn!("Invalid request body {:?}: {:?}", request.log_path(), e);
+        let mut response = HTTPResponse::new(400).set_header("Connection", "close");
+        let _ = write_response(writer, &mut response, false, write_timeout).await;
+        return false;
+    }
+    let keep_alive = can_keep_alive && request.is_keep_alive();
+    request.config = Some(Arc::clone(config));
+    if let Some(addr) = local_addr {
+        request.set_local_addr(addr);
+    }
+
+    let response = match dispatch(config, &mut request) {
+        Dispatch::Route(i) => {
+            log::trace!("run callback request={:?}", request);
+            let response = run_route(config, i, &request).await;
+            log::trace!("response={:?}", response);
+            response
+        }
+        Dispatch::Response(response) => response,
+    };
+    // Сервер останавливается, пока выполнялся запрос
+    let keep_alive = keep_alive && !shutdown.is_shutdown();
+    let with_body = request.method != HTTPMethod::HEAD;
+    let log_path = request.log_path();
+    // Сжатие и буферизация потока блокируют
+    let response_config = Arc::clone(config);
+    let response = tokio::task::spawn_blocking(move || {
+        finish_response(&response_config, &request, response, keep_alive)
+    })
+    .await;
+    let mut response = response.unwrap_or_else(|e| {
+        log::error!("Finish response {} fail: {:?}", log_path, e);
+        HTTPError::new(500, "Internal server error")
+            .to_response()
+            .set_header("Connection", "close")
+    });
+    let keep_alive = response.header("Connection") == Some("keep-alive");
+    log::info!("{code} {path}", code = response.status, path = log_path);
+    let result = write_response(writer, &mut response, with_body, write_timeout).await;
+    if let Err(e) = &result {
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            log::warn!("Write response {} fail: {:?}", log_path, e);
+        }
+    }
+    result.is_ok() && keep_alive
+}
+
+/// Serve requests of connection until close
+async fn handle_connection(stream: TcpStream, config: Arc<ServerConfig>, shutdown: ShutdownHandle) {
+    let addr = stream.peer_addr().ok();
+    let local_addr = stream.local_addr().ok();
+    log::debug!("client {:?} connected", addr);
+    let keep_alive = config.keep_alive_timeout();
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    for i in 1..=MAX_KEEP_ALIVE_REQUESTS {
+        if i > 1 && shutdown.is_shutdown() {
+            break;
+        }
+        let lines = match with_timeout(read_timeout(&config, i > 1), read_head(&mut reader)).await {
+            Ok(lines) => lines,
+            Err(e) if HeadTooLarge::is(&e) => {
+                log::warn!("client {:?} {}", addr, e);
+                let mut response = HeadTooLarge::to_response();
+                let write_timeout = config.request_timeout();
+                let _ = write_response(&mut writer, &mut response, true, write_timeout).await;
+                break;
+            }
+            Err(e) => {
+                // Таймаут простоя или обрыв соединения
+                log::debug!("client {:?} read fail: {:?}", addr, e);
+                break;
+            }
+        };
+        if lines.is_empty() {
+            // Close
+            break;
+        }
+        let can_keep_alive = keep_alive.is_some() && i < MAX_KEEP_ALIVE_REQUESTS;
+        if !handle_request(
+            &mut reader,
+            &mut writer,
+            &lines,
+            local_addr,
+            &config,
+            can_keep_alive,
+            &shutdown,
+        )
+        .await
+        {
+            break;
+        }
+    }
+    log::debug!("client {:?} disconnected", addr);
+}
+
+pub struct AsyncServer {
+    listeners: Vec<TcpListener>,
+    config: Arc<ServerConfig>,
+    shutdown: ShutdownHandle,
+}
+
+impl AsyncServer {
+    /// Bind all [`ServerConfig::addrs`], HTTPS is served only by blocking [`crate::Server`]
+    pub async fn bind(config: ServerConfig) -> io::Result<AsyncServer> {
+        log::debug!("Server config: {:?}", config);
+        if config.tls.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "HTTPS is not supported by async server",
+            ));
+        }
+        let mut listeners = Vec::new();
+        for addr in config.addrs() {
+            let listener = TcpListener::bind(addr.as_str())
+                .await
+                .inspect_err(|e| log::error!("Bind {} fail: {:?}", addr, e))?;
+            listeners.push(listener);
+        }
+        let shutdown = ShutdownHandle::new(
+            listeners
+                .iter()
+                .map(|l| l.local_addr())
+                .collect::<io::Result<_>>()?,
+        );
+        Ok(AsyncServer {
+            listeners,
+            config: Arc::new(config),
+            shutdown,
+        })
+    }
+
+    /// First bound address
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listeners[0].local_addr()
+    }
+
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.shutdown.addrs.clone()
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    async fn accept(
+        listener: TcpListener,
+        config: Arc<ServerConfig>,
+        shutdown: ShutdownHandle,
+        connections: Arc<()>,
+    ) {
+        loop {
+            let accepted = listener.accept().await;
+            if shutdown.is_shutdown() {
+                break;
+            }
+            let stream = match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    log::warn!("Accept fail: {:?}", e);
+                    continue;
+                }
+            };
+            let config = Arc::clone(&config);
+            let shutdown = shutdown.clone();
+            let connection = Arc::clone(&connections);
+            tokio::spawn(async move {
+                handle_connection(stream, config, shutdown).await;
+                drop(connection);
+            });
+        }
+    }
+
+    /// Accept connections until shutdown, then wait in-flight requests
+    /// up to [`ServerConfig::shutdown_timeout`]
+    pub async fn serve(self) -> io::Result<()> {
+        for addr in self.local_addrs() {
+            println!("Run server: http://{}", addr);
+        }
+        // Ссылка на каждое открытое соединение
+        let connections = Arc::new(());
+        let accepts: Vec<_> = self
+            .listeners
+            .into_iter()
+            .map(|listener| {
+                tokio::spawn(Self::accept(
+                    listener,
+                    Arc::clone(&self.config),
+                    self.shutdown.clone(),
+                    Arc::clone(&connections),
+                ))
+            })
+            .collect();
+        for accept in accepts {
+            accept.await.map_err(io::Error::other)?;
+        }
+        let timeout = self.config.shutdown_timeout();
+        log::info!("Server stopped, waiting in-flight requests {:?}", timeout);
+        let waiting = async {
+            while Arc::strong_count(&connections) > 1 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        if tokio::time::timeout(timeout, waiting).await.is_err() {
+            log::warn!("In-flight requests not finished in {:?}", timeout);
+        }
+        Ok(())
+    }
+}
+
+/// Run async server until SIGINT/SIGTERM, [`ServerConfig::threads`] is number of runtime workers
+pub fn run_async(config: ServerConfig) -> io::Result<()> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(config.threads.unwrap_or(4).max(1) as usize)
+        .enable_all()
+        .build()?;
+    let result = runtime.block_on(async {
+        let server = AsyncServer::bind(config).await?;
+        shutdown_on_signals(server.shutdown_handle());
+        server.serve().await
+    });
+    // Не ждем зависшие колбэки
+    runtime.shutdown_timeout(Duration::from_secs(1));
+    result
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+use std::fmt::Formatter;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::server::request::HTTPRequest;
+use crate::server::response::HTTPResponse;
+
+/// Query param with secret token, kept in generated links
+pub const AUTH_TOKEN_PARAM: &str = "token";
+
+/// Hide value of [`AUTH_TOKEN_PARAM`] in path or request line for logs
+/// ```
+/// use http_server::auth::redact_token;
+/// assert_eq!(redact_token("/feed/?a=1&token=secret"), "/feed/?a=1&token=***");
+/// assert_eq!(
+///     redact_token("GET /feed/?token=secret HTTP/1.1"),
+///     "GET /feed/?token=*** HTTP/1.1"
+/// );
+/// assert_eq!(redact_token("/feed/?tokens=1"), "/feed/?tokens=1");
+/// ```
+pub fn redact_token(path: &str) -> String {
+    let Some((path, rest)) = path.split_once('?') else {
+        return path.to_string();
+    };
+    let (query, tail) = rest.split_once(' ').unwrap_or((rest, ""));
+    let query = query
+        .split('&')
+        .map(|p| match p.split_once('=') {
+            Some((AUTH_TOKEN_PARAM, _)) => format!("{AUTH_TOKEN_PARAM}=***"),
+            _ => p.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    if tail.is_empty() {
+        format!("{path}?{query}")
+    } else {
+        format!("{path}?{query} {tail}")
+    }
+}
+
+/// Access control of routes: HTTP Basic credentials and/or secret token.
+///
+/// Token accepted as `?token=` query param or `Authorization: Bearer` header.
+/// Without credentials everything is allowed
+#[derive(Clone, Default)]
+pub struct Auth {
+    /// User and password for Basic auth
+    pub basic: Option<(String, String)>,
+    pub token: Option<String>,
+}
+
+impl fmt::Debug for Auth {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        // Секреты в лог не пишем
+        f.debug_struct("Auth")
+            .field("basic", &self.basic.as_ref().map(|(user, _)| user))
+            .field("token", &self.token.as_ref().map(|_| "***"))
+            .finish()
+    }
+}
+
+/// Compare secrets in constant time
+fn secure_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+impl Auth {
+    pub fn is_public(&self) -> bool {
+        self.basic.is_none() && self.token.is_none()
+    }
+
+    ///
+    /// ```
+    /// use http_server::{Auth, HTTPRequest};
+    /// let auth = Auth {
+    ///     basic: Some(("user".to_string(), "pass".to_string())),
+    ///     token: Some("secret".to_string()),
+    /// };
+    /// let mut r = HTTPRequest::default();
+    /// assert!(!auth.is_allowed(&r));
+    /// // user:pass
+    /// r.headers.insert("Authorization".to_string(), "Basic dXNlcjpwYXNz".to_string());
+    /// assert!(auth.is_allowed(&r));
+    /// r.headers.insert("Authorization".to_string(), "Bearer secret".to_string());
+    /// assert!(auth.is_allowed(&r));
+    /// r.headers.clear();
+    /// r.query_params.insert("token".to_string(), "wrong".to_string());
+    /// assert!(!auth.is_allowed(&r));
+    /// r.query_params.insert("token".to_string(), "secret".to_string());
+    /// assert!(auth.is_allowed(&r));
+    /// ```
+    pub fn is_allowed(&self, request: &HTTPRequest) -> bool {
+        if self.is_public() {
+            return true;
+        }
+        if let Some(token) = self.token.as_ref() {
+            let query_token = request.query_params.get(AUTH_TOKEN_PARAM);
+            if query_token.is_some_and(|t| secure_eq(t.as_bytes(), token.as_bytes())) {
+                return true;
+            }
+        }
+        let Some((scheme, credentials)) = request
+            .header("Authorization")
+            .and_then(|a| a.trim().split_once(' '))
+        else {
+            return false;
+        };
+        let credentials = credentials.trim();
+        match (scheme.to_lowercase().as_str(), &self.basic, &self.token) {
+            ("basic", Some((user, password)), _) => {
+                let expected = STANDARD.encode(format!("{user}:{password}"));
+                secure_eq(credentials.as_bytes(), expected.as_bytes())
+            }
+            ("bearer", _, Some(token)) => secure_eq(credentials.as_bytes(), token.as_bytes()),
+            _ => false,
+        }
+    }
+
+    /// 401 response, with Basic challenge if enabled
+    pub fn unauthorized(&self) -> HTTPResponse {
+        let response = HTTPResponse::new(401);
+        if self.basic.is_some() {
+            response.set_header(
+                "WWW-Authenticate",
+                r#"Basic realm="Restricted", charset="UTF-8""#,
+            )
+        } else {
+            response
+        }
+    }
+}
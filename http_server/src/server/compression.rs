@@ -0,0 +1,171 @@
+use std::io;
+use std::io::Write;
+
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use crate::server::request::HTTPRequest;
+use crate::server::response::HTTPResponse;
+
+/// Smaller bodies are not worth compressing
+const MIN_COMPRESS_SIZE: usize = 256;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Encoding {
+    Gzip,
+    #[cfg(feature = "brotli")]
+    Brotli,
+}
+
+impl Encoding {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => "br",
+        }
+    }
+
+    /// Supported encodings, by preference
+    fn supported() -> &'static [Encoding] {
+        &[
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli,
+            Encoding::Gzip,
+        ]
+    }
+
+    /// Choose encoding by `Accept-Encoding` header
+    /// ```
+    /// use http_server::compression::Encoding;
+    /// assert_eq!(Encoding::negotiate("gzip, deflate"), Some(Encoding::Gzip));
+    /// assert_eq!(Encoding::negotiate("deflate;q=1, gzip;q=0.5"), Some(Encoding::Gzip));
+    /// assert_eq!(Encoding::negotiate("*"), Encoding::negotiate("gzip, br"));
+    /// assert_eq!(Encoding::negotiate("gzip;q=0"), None);
+    /// assert_eq!(Encoding::negotiate("identity"), None);
+    /// ```
+    pub fn negotiate(accept_encoding: &str) -> Option<Encoding> {
+        let accepted: Vec<(String, f32)> = accept_encoding
+            .split(',')
+            .filter_map(|part| {
+                let mut params = part.split(';');
+                let name = params.next()?.trim().to_ascii_lowercase();
+                if name.is_empty() {
+                    return None;
+                }
+                let q = params
+                    .filter_map(|p| p.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((name, q))
+            })
+            .collect();
+        let quality = |encoding: &Encoding| {
+            accepted
+                .iter()
+                .find(|(name, _)| name == encoding.name())
+                .or_else(|| accepted.iter().find(|(name, _)| name == "*"))
+                .map(|(_, q)| *q)
+                .unwrap_or(0.0)
+        };
+        let mut best: Option<(Encoding, f32)> = None;
+        for encoding in Encoding::supported() {
+            let q = quality(encoding);
+            // При равном весе берем по порядку предпочтения
+            if q > 0.0 && !matches!(best, Some((_, best_q)) if q <= best_q) {
+                best = Some((*encoding, q));
+            }
+        }
+        best.map(|(encoding, _)| encoding)
+    }
+
+    pub fn compress(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            #[cfg(feature = "brotli")]
+            Encoding::Brotli => {
+                let mut out = Vec::new();
+                {
+                    let mut writer = brotli::CompressorWriter::new(&mut out, 4096, 5, 22);
+                    writer.write_all(data)?;
+                }
+                Ok(out)
+            }
+        }
+    }
+}
+
+/// Text content types, media is already compressed
+/// ```
+/// use http_server::compression::is_compressible;
+/// assert!(is_compressible("text/xml; charset=utf-8"));
+/// assert!(is_compressible("application/atom+xml"));
+/// assert!(!is_compressible("image/jpeg"));
+/// ```
+pub fn is_compressible(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    mime.starts_with("text/")
+        || mime.ends_with("+xml")
+        || mime.ends_with("+json")
+        || matches!(
+            mime.as_str(),
+            "application/xml" | "application/json" | "application/javascript"
+        )
+}
+
+impl HTTPResponse {
+    /// Compress body by client `Accept-Encoding`
+    pub fn compress(self, request: &HTTPRequest) -> Self {
+        let Some(content) = self.content.as_ref() else {
+            return self;
+        };
+        let content_type = self.content_type.as_deref().unwrap_or("text/plain");
+        if self.status != 200
+            || content.len() < MIN_COMPRESS_SIZE
+            || !is_compressible(content_type)
+            || self.header("Content-Encoding").is_some()
+        {
+            return self;
+        }
+        // Ответ зависит от заголовка, даже если клиент сжатие не поддерживает
+        let vary = match self.header("Vary") {
+            Some(vary) => format!("{vary}, Accept-Encoding"),
+            None => "Accept-Encoding".to_string(),
+        };
+        let response = self.set_header("Vary", vary.as_str());
+        let Some(encoding) = request
+            .header("Accept-Encoding")
+            .and_then(Encoding::negotiate)
+        else {
+            return response;
+        };
+        // ETag должен отличаться для каждого представления
+        let response = response.with_etag();
+        let etag = response
+            .header("ETag")
+            .map(|etag| format!("{}-{}\"", etag.trim_end_matches('"'), encoding.name()));
+        let compressed = match encoding.compress(response.content.as_ref().unwrap()) {
+            Ok(compressed) => compressed,
+            Err(e) => {
+                log::warn!("Compress {:?} fail: {:?}", encoding, e);
+                return response;
+            }
+        };
+        let mut response = response.set_header("Content-Encoding", encoding.name());
+        if let Some(etag) = etag {
+            response = response.set_header("ETag", etag.as_str());
+        }
+        response.content = Some(Bytes::from(compressed));
+        response
+    }
+}
@@ -1,39 +1,141 @@
+use crate::server::auth::Auth;
 use crate::server::error;
-use crate::server::request::HTTPRequest;
+#[cfg(feature = "tokio")]
+use crate::server::error::HTTPError;
+use crate::server::request::{HTTPMethod, HTTPRequest};
 use crate::server::response::HTTPResponse;
-use crate::utils::parse_match_captures;
+use crate::utils::{parse_match_captures, pattern_to_regex};
 use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
-use std::sync::{Arc, Mutex};
+#[cfg(feature = "tokio")]
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+#[cfg(feature = "tokio")]
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
 
-pub type ViewCallback = dyn Fn(&HTTPRequest) -> error::Result<HTTPResponse> + Send;
+pub type ViewCallback = dyn Fn(&HTTPRequest) -> error::Result<HTTPResponse> + Send + Sync;
+
+#[cfg(feature = "tokio")]
+pub type ViewFuture = Pin<Box<dyn Future<Output = error::Result<HTTPResponse>> + Send>>;
+
+/// Async callback gets owned request, see [`Route::new_async`]
+#[cfg(feature = "tokio")]
+pub type AsyncViewCallback = dyn Fn(HTTPRequest<'static>) -> ViewFuture + Send + Sync;
+
+enum Callback {
+    /// Blocking callback, calls run concurrently in request threads
+    Blocking(Arc<ViewCallback>),
+    #[cfg(feature = "tokio")]
+    Async(Arc<AsyncViewCallback>),
+}
 
 pub struct Route {
     pattern: String,
     re: regex::Regex,
-    callback: Arc<Mutex<ViewCallback>>,
+    /// `GET` also allows `HEAD`
+    methods: Vec<HTTPMethod>,
+    callback: Callback,
+    /// Override of [`ServerConfig::auth`]
+    pub auth: Option<Auth>,
 }
 
 impl Debug for Route {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Route")
             .field("pattern", &self.pattern)
-            .field("callback", &"Arc<Mutex<ViewCallback>")
+            .field("methods", &self.methods)
+            .field("auth", &self.auth)
+            .field(
+                "callback",
+                &match self.callback {
+                    Callback::Blocking(_) => "Arc<ViewCallback>",
+                    #[cfg(feature = "tokio")]
+                    Callback::Async(_) => "Arc<AsyncViewCallback>",
+                },
+            )
             .finish()
     }
 }
 
 impl Route {
+    /// `GET` route, `pattern` is regex with placeholders `{name}` or `{name:regex}`,
+    /// see [`pattern_to_regex`]
     pub fn new(
         pattern: &str,
-        callback: impl Fn(&HTTPRequest) -> error::Result<HTTPResponse> + Send + 'static,
+        callback: impl Fn(&HTTPRequest) -> error::Result<HTTPResponse> + Send + Sync + 'static,
     ) -> Self {
+        Self::with_callback(pattern, Callback::Blocking(Arc::new(callback)))
+    }
+
+    /// `GET` route with async callback, calls run concurrently.
+    /// In blocking [`crate::Server`] it runs on own runtime per request
+    /// ```
+    /// use http_server::{HTTPResponse, Route};
+    /// let route = Route::new_async("/feed/{slug}/", |r| async move {
+    ///     let slug: String = r.path_param("slug")?;
+    ///     Ok(HTTPResponse::with_content(slug.as_str()))
+    /// });
+    /// assert!(route.match_path("/feed/foo/"));
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub fn new_async<F, Fut>(pattern: &str, callback: F) -> Self
+    where
+        F: Fn(HTTPRequest<'static>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = error::Result<HTTPResponse>> + Send + 'static,
+    {
+        let callback: Arc<AsyncViewCallback> = Arc::new(move |r| Box::pin(callback(r)));
+        Self::with_callback(pattern, Callback::Async(callback))
+    }
+
+    fn with_callback(pattern: &str, callback: Callback) -> Self {
+        let re = pattern_to_regex(pattern);
         Self {
             pattern: pattern.to_string(),
-            re: regex::Regex::new(format!(r#"^{pattern}$"#).as_str()).unwrap(),
-            callback: Arc::new(Mutex::new(callback)),
+            re: regex::Regex::new(format!(r#"^{re}$"#).as_str()).unwrap(),
+            methods: vec![HTTPMethod::GET],
+            callback,
+            auth: None,
         }
     }
+
+    pub fn with_auth(mut self, auth: Auth) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Replace allowed methods, default `GET`
+    pub fn with_methods(mut self, methods: &[HTTPMethod]) -> Self {
+        self.methods = methods.to_vec();
+        self
+    }
+
+    /// Allowed methods for `Allow` header
+    /// ```
+    /// use http_server::{HTTPMethod, HTTPResponse, Route};
+    /// let route = Route::new("/", |_| Ok(HTTPResponse::default()));
+    /// assert_eq!(route.allowed_methods(), vec![HTTPMethod::HEAD, HTTPMethod::GET]);
+    /// assert!(route.allows(&HTTPMethod::HEAD));
+    /// assert!(!route.allows(&HTTPMethod::POST));
+    /// let route = route.with_methods(&[HTTPMethod::POST]);
+    /// assert_eq!(route.allowed_methods(), vec![HTTPMethod::POST]);
+    /// ```
+    pub fn allowed_methods(&self) -> Vec<HTTPMethod> {
+        let mut methods = self.methods.clone();
+        if methods.contains(&HTTPMethod::GET) {
+            methods.push(HTTPMethod::HEAD);
+        }
+        methods.sort();
+        methods.dedup();
+        methods
+    }
+
+    pub fn allows(&self, method: &HTTPMethod) -> bool {
+        self.allowed_methods().contains(method)
+    }
+
     pub fn match_path(&self, path: &str) -> bool {
         self.re.is_match(path)
     }
@@ -43,22 +145,132 @@ impl Route {
     }
 
     pub fn run_cb(&self, request: &HTTPRequest) -> error::Result<HTTPResponse> {
-        self.callback
-            .lock()
-            // Ignore poison error
-            .unwrap_or_else(|e| e.into_inner())(request)
+        match &self.callback {
+            Callback::Blocking(callback) => callback(request),
+            #[cfg(feature = "tokio")]
+            Callback::Async(callback) => tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| HTTPError::new(500, "Internal server error").with_cause(e))?
+                .block_on(callback(request.detach())),
+        }
+    }
+
+    /// Run callback in async server, blocking callback runs on blocking thread.
+    /// Panic of callback is internal error
+    #[cfg(feature = "tokio")]
+    pub async fn run_cb_async(&self, request: HTTPRequest<'static>) -> error::Result<HTTPResponse> {
+        let result = match &self.callback {
+            Callback::Blocking(callback) => {
+                let callback = Arc::clone(callback);
+                tokio::task::spawn_blocking(move || callback(&request)).await
+            }
+            // Отдельной задачей, чтобы поймать панику
+            Callback::Async(callback) => tokio::spawn(callback(request)).await,
+        };
+        result.unwrap_or_else(|e| Err(HTTPError::new(500, "Internal server error").with_cause(e)))
     }
 }
 
+/// PEM files of certificate chain and private key for HTTPS.
+/// Files are reread on change, without restart
+#[derive(Default, Debug, Clone)]
+pub struct TlsConfig {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+}
+
 #[derive(Default, Debug)]
 pub struct ServerConfig {
+    /// Listen addresses: `host`, `ip` or with port `host:port`, `[::1]:8080`.
+    /// Default `127.0.0.1`
+    pub bind: Vec<String>,
+    /// Port for addresses without port, default 12345
     pub port: Option<u16>,
+    /// Externally visible base url for generated links, e.g. `https://example.com/feeds/`.
+    /// By default built from `Host` and, with `trust_forwarded`, `X-Forwarded-*` headers
+    pub public_url: Option<url::Url>,
+    /// Use `X-Forwarded-*` headers of reverse proxy. Off by default, any client can send them
+    pub trust_forwarded: bool,
     pub threads: Option<u8>,
+    /// Idle timeout of persistent connection in seconds, default 5, 0 - disable keep-alive
+    pub keep_alive: Option<u64>,
+    /// Time in seconds to finish in-flight requests on shutdown, default 10
+    pub shutdown_timeout: Option<u64>,
+    /// Deadline of route callback in seconds, default 60, 0 - no limit.
+    /// Timed out request gets 504, callback is left to finish in background.
+    /// While `threads` of such callbacks of a route are running, new requests to it get 503
+    pub request_timeout: Option<u64>,
+    /// Serve HTTPS on all addresses, requires `tls` feature
+    pub tls: Option<TlsConfig>,
+    /// Access control of all routes, see [`Route::with_auth`]
+    pub auth: Option<Auth>,
     pub routes: Vec<Route>,
 }
 
 impl ServerConfig {
+    /// First listen address
     pub fn addr(&self) -> String {
-        format!("127.0.0.1:{}", self.port.unwrap_or(12345))
+        self.addrs().remove(0)
+    }
+
+    /// All listen addresses with port
+    /// ```
+    /// use http_server::ServerConfig;
+    /// let config = ServerConfig {
+    ///     bind: ["0.0.0.0", "::", "[::1]", "[::1]:8080", "localhost", "localhost:8080"]
+    ///         .map(String::from)
+    ///         .to_vec(),
+    ///     port: Some(80),
+    ///     ..ServerConfig::default()
+    /// };
+    /// assert_eq!(
+    ///     config.addrs(),
+    ///     vec!["0.0.0.0:80", "[::]:80", "[::1]:80", "[::1]:8080", "localhost:80", "localhost:8080"]
+    /// );
+    /// assert_eq!(ServerConfig::default().addrs(), vec!["127.0.0.1:12345"]);
+    /// ```
+    pub fn addrs(&self) -> Vec<String> {
+        let port = self.port.unwrap_or(12345);
+        if self.bind.is_empty() {
+            return vec![format!("127.0.0.1:{port}")];
+        }
+        self.bind
+            .iter()
+            .map(|host| {
+                if host.parse::<SocketAddr>().is_ok() {
+                    return host.clone();
+                }
+                let ip = host.trim_start_matches('[').trim_end_matches(']');
+                if let Ok(ip) = ip.parse::<IpAddr>() {
+                    return SocketAddr::new(ip, port).to_string();
+                }
+                // Имя хоста, возможно с портом
+                match host.rsplit_once(':') {
+                    Some((_, p)) if p.parse::<u16>().is_ok() => host.clone(),
+                    _ => format!("{host}:{port}"),
+                }
+            })
+            .collect()
+    }
+
+    /// Idle timeout of persistent connection, `None` if keep-alive disabled
+    pub fn keep_alive_timeout(&self) -> Option<Duration> {
+        match self.keep_alive.unwrap_or(5) {
+            0 => None,
+            timeout => Some(Duration::from_secs(timeout)),
+        }
+    }
+
+    pub fn shutdown_timeout(&self) -> Duration {
+        Duration::from_secs(self.shutdown_timeout.unwrap_or(10))
+    }
+
+    /// Deadline of route callback, `None` if unlimited
+    pub fn request_timeout(&self) -> Option<Duration> {
+        match self.request_timeout.unwrap_or(60) {
+            0 => None,
+            timeout => Some(Duration::from_secs(timeout)),
+        }
     }
 }
@@ -1,4 +1,7 @@
-use std::result;
+use std::fmt::{Debug, Display, Formatter};
+use std::{fmt, result};
+
+use crate::server::response::HTTPResponse;
 
 pub type Result<T> = result::Result<T, HTTPError>;
 
@@ -7,4 +10,127 @@ pub enum HTTPError {
     InvalidMethod,
     InvalidRequest,
     NotFound,
+    /// Any error status with message for client, cause is only logged
+    Status {
+        status: u16,
+        message: String,
+        cause: Option<String>,
+    },
+}
+
+/// Reason phrase of error status
+fn reason(status: u16) -> &'static str {
+    match status {
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        s if s < 500 => "Client Error",
+        _ => "Server Error",
+    }
+}
+
+impl HTTPError {
+    pub fn new(status: u16, message: impl Into<String>) -> HTTPError {
+        HTTPError::Status {
+            status,
+            message: message.into(),
+            cause: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> HTTPError {
+        HTTPError::new(404, message)
+    }
+
+    /// Upstream failed or returned unexpected response
+    pub fn bad_gateway(message: impl Into<String>) -> HTTPError {
+        HTTPError::new(502, message)
+    }
+
+    pub fn gateway_timeout(message: impl Into<String>) -> HTTPError {
+        HTTPError::new(504, message)
+    }
+
+    /// Attach cause for log
+    /// ```
+    /// use http_server::HTTPError;
+    /// let e = HTTPError::bad_gateway("Telegram unavailable").with_cause("connection refused");
+    /// assert_eq!(e.status(), 502);
+    /// assert_eq!(e.cause(), Some("\"connection refused\""));
+    /// assert_eq!(e.to_string(), "502 Bad Gateway: Telegram unavailable");
+    /// let e = HTTPError::NotFound.with_cause(42);
+    /// assert_eq!(e.to_string(), "404 Not Found");
+    /// ```
+    pub fn with_cause(self, cause: impl Debug) -> HTTPError {
+        HTTPError::Status {
+            status: self.status(),
+            message: self.message().to_string(),
+            cause: Some(format!("{cause:?}")),
+        }
+    }
+
+    pub fn status(&self) -> u16 {
+        match self {
+            HTTPError::InvalidMethod => 405,
+            HTTPError::InvalidRequest => 400,
+            HTTPError::NotFound => 404,
+            HTTPError::Status { status, .. } => *status,
+        }
+    }
+
+    /// Message for client, empty for plain variants
+    pub fn message(&self) -> &str {
+        match self {
+            HTTPError::Status { message, .. } => message.as_str(),
+            _ => "",
+        }
+    }
+
+    pub fn cause(&self) -> Option<&str> {
+        match self {
+            HTTPError::Status { cause, .. } => cause.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Plain text error page
+    /// ```
+    /// use http_server::HTTPError;
+    /// let r = HTTPError::not_found("Channel foo not found").to_response();
+    /// assert_eq!(r.status, 404);
+    /// assert_eq!(
+    ///     r.content.unwrap().as_ref(),
+    ///     b"404 Not Found: Channel foo not found\n"
+    /// );
+    /// ```
+    pub fn to_response(&self) -> HTTPResponse {
+        let response = HTTPResponse::with_content(format!("{self}\n").as_str())
+            .set_content_type("text/plain; charset=utf-8");
+        HTTPResponse {
+            status: self.status(),
+            ..response
+        }
+    }
+}
+
+impl Display for HTTPError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let status = self.status();
+        write!(f, "{status} {}", reason(status))?;
+        match self.message() {
+            "" => Ok(()),
+            message => write!(f, ": {message}"),
+        }
+    }
 }
+
+impl std::error::Error for HTTPError {}
@@ -1,108 +1,699 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::io;
-use std::io::{BufRead, BufReader, Write};
-use std::net::{TcpListener, TcpStream};
-
-use std::sync::Arc;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::{mpsc, Arc, Mutex, Once};
+use std::thread;
+use std::time::Duration;
 
+use crate::server::auth::redact_token;
 use crate::server::config::ServerConfig;
 
-use crate::server::request::HTTPRequest;
+use crate::server::error;
+use crate::server::request::{HTTPMethod, HTTPRequest};
 use crate::server::response::HTTPResponse;
-use crate::server::thread_pool::ThreadPool;
+use crate::server::thread_pool::{catch_panic, ThreadPool};
+use crate::server::HTTPError;
 use crate::server::HTTPError::*;
 
-pub(crate) fn handle_client(mut stream: TcpStream, config: Arc<ServerConfig>) {
-    let addr = stream.peer_addr().unwrap();
-    log::debug!("client {:?} connected", addr);
-    let reader = BufReader::new(&stream);
-    // TODO check body
-    let request: Vec<String> = reader
-        .lines()
-        .map(|r| r.unwrap())
-        .take_while(|l| !l.is_empty())
-        .collect();
-
-    if request.is_empty() {
-        // Close
-        return;
-    }
-    let mut request = HTTPRequest::parse(&request).unwrap();
-    let req_headers = request.headers.clone();
-    request.config = Some(Arc::clone(&config));
-    request.stream = Some(Box::new(&stream));
-
-    let routes = &config.routes;
-    let mut response = Err(NotFound);
-    for r in routes {
-        log::trace!("try match route: {:?}", r);
-        if let Some(path_params) = r.parse_path(&request.path) {
-            request.path_params = Some(path_params);
-            log::trace!("run callback request={:?}", request);
-            response = r.run_cb(&request);
-            log::trace!("response={:?}", response);
-            break;
+/// Limit of requests per persistent connection
+pub(crate) const MAX_KEEP_ALIVE_REQUESTS: usize = 100;
+
+/// Deadline of reading first request and body, idle wait between requests is keep-alive timeout
+pub(crate) const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Timeout of next read, `idle` is wait for next request of persistent connection
+pub(crate) fn read_timeout(config: &ServerConfig, idle: bool) -> Option<Duration> {
+    if idle {
+        config.keep_alive_timeout()
+    } else {
+        Some(READ_TIMEOUT)
+    }
+}
+
+fn set_read_timeout(socket: &TcpStream, timeout: Option<Duration>) {
+    if let Err(e) = socket.set_read_timeout(timeout) {
+        log::warn!("Set read timeout fail: {:?}", e);
+    }
+}
+
+/// Limit of request line with headers
+pub(crate) const MAX_HEAD_SIZE: usize = 64 * 1024;
+
+/// Limit of header lines
+pub(crate) const MAX_HEADERS: usize = 100;
+
+/// Request line and headers over [`MAX_HEAD_SIZE`] or [`MAX_HEADERS`]
+#[derive(Debug)]
+pub(crate) struct HeadTooLarge;
+
+impl fmt::Display for HeadTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Request header fields too large")
+    }
+}
+
+impl std::error::Error for HeadTooLarge {}
+
+impl HeadTooLarge {
+    pub(crate) fn is(e: &io::Error) -> bool {
+        e.get_ref().is_some_and(|e| e.is::<HeadTooLarge>())
+    }
+
+    /// Error page, connection is closed after it
+    pub(crate) fn to_response() -> HTTPResponse {
+        HTTPError::new(431, HeadTooLarge.to_string())
+            .to_response()
+            .set_header("Connection", "close")
+    }
+}
+
+/// Lines of request head being read
+#[derive(Debug, Default)]
+pub(crate) struct Head {
+    pub(crate) lines: Vec<String>,
+    size: usize,
+}
+
+impl Head {
+    /// Limit of next line read, one byte over to detect too large head
+    pub(crate) fn remaining(&self) -> u64 {
+        (MAX_HEAD_SIZE + 1 - self.size) as u64
+    }
+
+    /// Add read line, returns `true` on end of head
+    pub(crate) fn push(&mut self, line: &[u8]) -> io::Result<bool> {
+        self.size += line.len();
+        if self.size > MAX_HEAD_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, HeadTooLarge));
+        }
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            // Пустые строки перед запросом игнорируем
+            return Ok(!self.lines.is_empty());
+        }
+        if self.lines.len() > MAX_HEADERS {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, HeadTooLarge));
         }
+        self.lines.push(line.to_string());
+        Ok(false)
     }
-    // dbg!(&response);
-    let response = match response {
-        Ok(r) => r,
-        Err(e) => match e {
-            NotFound => HTTPResponse::new(404),
-            _ => HTTPResponse::new(500),
-        },
-    };
-    log::info!(
-        "{code} {path}",
-        code = response.status,
-        path = request.full_path
-    );
-    let header_write_state = stream
+}
+
+/// Read request line and headers, empty on closed connection
+fn read_head<R: BufRead>(reader: &mut R) -> io::Result<Vec<String>> {
+    let mut head = Head::default();
+    loop {
+        let mut line = Vec::new();
+        let mut limited = reader.by_ref().take(head.remaining());
+        if limited.read_until(b'\n', &mut line)? == 0 {
+            // Соединение закрыто
+            return Ok(head.lines);
+        }
+        if head.push(&line)? {
+            return Ok(head.lines);
+        }
+    }
+}
+
+fn write_response<W: Write>(
+    stream: &mut W,
+    response: &mut HTTPResponse,
+    with_body: bool,
+    req_headers: &HashMap<String, String>,
+) -> io::Result<()> {
+    stream
         .write_all(response.to_string().as_bytes())
         .map_err(|e| {
             log::warn!(
                 "Write headers fail: e={:?} request={:?} response={:?}",
                 &e,
-                &req_headers,
-                &response
+                req_headers,
+                response
             );
             e
-        });
-    if header_write_state.is_ok() {
+        })?;
+    if !with_body {
+        return stream.flush();
+    }
+    let result = if let Some(mut content_stream) = response.content_stream.take() {
+        let mut writer = BufWriter::new(stream);
+        content_stream
+            .write_to(&mut writer)
+            .and_then(|_| writer.flush())
+    } else {
         let empty_bytes = bytes::Bytes::new();
-        let _ = stream
+        stream
             .write_all(response.content.as_ref().unwrap_or(&empty_bytes))
-            .map_err(|e| {
-                match e.kind() {
-                    io::ErrorKind::BrokenPipe => (), // Разрыв соединения от клиента, пока глушим их
-                    _ => {
-                        log::warn!(
-                            "Write headers fail: e={:?} request={:?} response={:?}",
-                            &e,
-                            &req_headers,
-                            &response
-                        );
-                    } // Другие ошибки
-                }
+            .and_then(|_| stream.flush())
+    };
+    result.map_err(|e| {
+        match e.kind() {
+            io::ErrorKind::BrokenPipe => (), // Разрыв соединения от клиента, пока глушим их
+            _ => {
+                log::warn!(
+                    "Write body fail: e={:?} request={:?} response={:?}",
+                    &e,
+                    req_headers,
+                    response
+                );
+            } // Другие ошибки
+        }
+        e
+    })
+}
+
+/// Callbacks running in request threads of server by route, including timed out.
+///
+/// Timed out callback can't be stopped, so number of them is limited per route:
+/// hung upstream of one route doesn't take down others
+#[derive(Debug, Clone)]
+pub(crate) struct RunningCallbacks {
+    counts: Arc<Vec<AtomicUsize>>,
+    max: usize,
+}
+
+impl RunningCallbacks {
+    /// Workers plus as many timed out callbacks for each of `routes`
+    pub(crate) fn new(threads: usize, routes: usize) -> RunningCallbacks {
+        RunningCallbacks {
+            counts: Arc::new((0..routes).map(|_| AtomicUsize::new(0)).collect()),
+            max: threads.max(1) * 2,
+        }
+    }
+
+    /// Slot for callback of route until drop, `None` if limit reached
+    fn acquire(&self, route_index: usize) -> Option<CallbackSlot> {
+        let count = &self.counts[route_index];
+        if count.fetch_add(1, Ordering::SeqCst) >= self.max {
+            count.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(CallbackSlot {
+            counts: Arc::clone(&self.counts),
+            route_index,
+        })
+    }
+
+    fn count(&self, route_index: usize) -> usize {
+        self.counts[route_index].load(Ordering::SeqCst)
+    }
+}
+
+struct CallbackSlot {
+    counts: Arc<Vec<AtomicUsize>>,
+    route_index: usize,
+}
+
+impl Drop for CallbackSlot {
+    fn drop(&mut self) {
+        self.counts[self.route_index].fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Panic of callback is internal error
+fn run_cb_catch_panic(
+    config: &ServerConfig,
+    route_index: usize,
+    request: &HTTPRequest,
+) -> error::Result<HTTPResponse> {
+    catch_panic(|| config.routes[route_index].run_cb(request)).unwrap_or_else(|message| {
+        Err(HTTPError::new(500, "Internal server error").with_cause(message))
+    })
+}
+
+/// Run callback of route with deadline [`ServerConfig::request_timeout`].
+///
+/// Callback can't be interrupted, so it runs in own thread and worker only waits result.
+/// Hung upstream call blocks that thread, not the worker.
+/// Over limit of [`RunningCallbacks`] of route request gets 503
+fn run_route(
+    config: &Arc<ServerConfig>,
+    route_index: usize,
+    request: &HTTPRequest,
+    callbacks: &RunningCallbacks,
+) -> error::Result<HTTPResponse> {
+    let Some(timeout) = config.request_timeout() else {
+        return run_cb_catch_panic(config, route_index, request);
+    };
+    let Some(slot) = callbacks.acquire(route_index) else {
+        log::warn!(
+            "Request {} rejected, {} callbacks of route running",
+            request.log_path(),
+            callbacks.count(route_index)
+        );
+        return Err(HTTPError::new(503, "Server overloaded"));
+    };
+    let (sender, receiver) = mpsc::channel();
+    let detached = request.detach();
+    let cb_config = Arc::clone(config);
+    let spawned = thread::Builder::new()
+        .name("request".to_string())
+        .spawn(move || {
+            let result = run_cb_catch_panic(&cb_config, route_index, &detached);
+            drop(slot);
+            // Ошибка если ответ уже не ждут
+            let _ = sender.send(result);
+        });
+    if let Err(e) = spawned {
+        log::error!("Spawn request thread fail: {:?}", e);
+        return Err(HTTPError::new(503, "Server overloaded").with_cause(e));
+    }
+    match receiver.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(RecvTimeoutError::Timeout) => {
+            log::warn!(
+                "Request {} timed out after {:?}, {} callbacks of route running",
+                request.log_path(),
+                timeout,
+                callbacks.count(route_index)
+            );
+            Err(HTTPError::gateway_timeout("Request timed out"))
+        }
+        Err(RecvTimeoutError::Disconnected) => {
+            Err(HTTPError::new(500, "Internal server error").with_cause("Request thread died"))
+        }
+    }
+}
+
+/// Result of route matching
+pub(crate) enum Dispatch {
+    /// Index of matched route, path params are set to request
+    Route(usize),
+    /// Not found, not allowed method or unauthorized
+    Response(error::Result<HTTPResponse>),
+}
+
+/// Find route of request
+pub(crate) fn dispatch(config: &ServerConfig, request: &mut HTTPRequest) -> Dispatch {
+    // Методы роутов, совпавших только по пути
+    let mut allowed: Vec<HTTPMethod> = Vec::new();
+    for (i, r) in config.routes.iter().enumerate() {
+        log::trace!("try match route: {:?}", r);
+        let Some(path_params) = r.parse_path(&request.path) else {
+            continue;
+        };
+        if !r.allows(&request.method) {
+            allowed.extend(r.allowed_methods());
+            continue;
+        }
+        if let Some(auth) = r.auth.as_ref().or(config.auth.as_ref()) {
+            if !auth.is_allowed(request) {
+                log::debug!("Unauthorized request {:?}", request.log_path());
+                return Dispatch::Response(Ok(auth.unauthorized()));
+            }
+        }
+        request.path_params = Some(path_params);
+        return Dispatch::Route(i);
+    }
+    if allowed.is_empty() {
+        return Dispatch::Response(Err(NotFound));
+    }
+    allowed.sort();
+    allowed.dedup();
+    let allow = allowed
+        .iter()
+        .map(|m| m.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Dispatch::Response(Ok(InvalidMethod
+        .to_response()
+        .set_header("Allow", allow.as_str())))
+}
+
+/// Error page or compressed, conditional and range response with connection headers.
+///
+/// Streamed body of unknown length is sent to HTTP/1.0 client until close,
+/// so connection is kept only if response has `Connection: keep-alive`
+pub(crate) fn finish_response(
+    config: &ServerConfig,
+    request: &HTTPRequest,
+    response: error::Result<HTTPResponse>,
+    keep_alive: bool,
+) -> HTTPResponse {
+    let response = match response {
+        Ok(r) => r.compress(request).conditional(request).range(request),
+        Err(e) => {
+            if e.status() >= 500 {
+                log::error!("{} {} cause={:?}", e, request.log_path(), e.cause());
+            } else {
+                log::debug!("{} {} cause={:?}", e, request.log_path(), e.cause());
+            }
+            e.to_response()
+        }
+    };
+    let mut response = response.remove_header("Keep-Alive");
+    let mut keep_alive = keep_alive;
+    if request.version == "HTTP/1.0" {
+        if let Some(stream) = response.content_stream.as_mut() {
+            // Chunked не поддерживается в HTTP/1.0, конец тела - закрытие соединения
+            if stream.length.is_none() {
+                stream.until_close = true;
+                keep_alive = false;
+            }
+        }
+    }
+    if keep_alive {
+        let timeout = config.keep_alive_timeout().unwrap_or_default().as_secs();
+        response
+            .set_header("Connection", "keep-alive")
+            .set_header("Keep-Alive", format!("timeout={timeout}").as_str())
+    } else {
+        response.set_header("Connection", "close")
+    }
+}
+
+/// Handle one request, returns `true` if connection should be kept
+fn handle_request<S: Read + Write>(
+    socket: &TcpStream,
+    reader: &mut BufReader<S>,
+    lines: &[String],
+    config: &Arc<ServerConfig>,
+    can_keep_alive: bool,
+    shutdown: &ShutdownHandle,
+    callbacks: &RunningCallbacks,
+) -> bool {
+    let mut request = match HTTPRequest::parse(lines) {
+        Ok(r) => r,
+        Err(e) => {
+            log::warn!(
+                "Invalid request {:?}: {:?}",
+                lines.first().map(|l| redact_token(l)),
                 e
-            });
+            );
+            let mut response = HTTPResponse::new(400).set_header("Connection", "close");
+            let _ = write_response(reader.get_mut(), &mut response, false, &HashMap::new());
+            return false;
+        }
+    };
+    if request
+        .header("Expect")
+        .is_some_and(|e| e.eq_ignore_ascii_case("100-continue"))
+    {
+        let stream = reader.get_mut();
+        if stream
+            .write_all(b"HTTP/1.1 100 Continue\r\n\r\n")
+            .and_then(|_| stream.flush())
+            .is_err()
+        {
+            return false;
+        }
+    }
+    if let Err(e) = request.read_body(reader) {
+        log::warn!("Invalid request body {:?}: {:?}", request.log_path(), e);
+        let mut response = HTTPResponse::new(400).set_header("Connection", "close");
+        let _ = write_response(reader.get_mut(), &mut response, false, &request.headers);
+        return false;
     }
+    let keep_alive = can_keep_alive && request.is_keep_alive();
+    let mut req_headers = request.headers.clone();
+    // Секреты в лог не пишем
+    req_headers.retain(|k, _| !k.eq_ignore_ascii_case("Authorization"));
+    request.config = Some(Arc::clone(config));
+    request.stream = Some(Box::new(socket));
+
+    let response = match dispatch(config, &mut request) {
+        Dispatch::Route(i) => {
+            log::trace!("run callback request={:?}", request);
+            let response = run_route(config, i, &request, callbacks);
+            log::trace!("response={:?}", response);
+            response
+        }
+        Dispatch::Response(response) => response,
+    };
+    // Сервер останавливается, пока выполнялся запрос
+    let keep_alive = keep_alive && !shutdown.is_shutdown();
+    let mut response = finish_response(config, &request, response, keep_alive);
+    let keep_alive = response.header("Connection") == Some("keep-alive");
+    log::info!(
+        "{code} {path}",
+        code = response.status,
+        path = request.log_path()
+    );
+    let with_body = request.method != HTTPMethod::HEAD;
+    write_response(reader.get_mut(), &mut response, with_body, &req_headers).is_ok() && keep_alive
 }
 
-pub fn run(config: ServerConfig) -> io::Result<()> {
-    log::debug!("Server config: {:?}", config);
-    let config = Arc::new(config);
-    let addr = config.addr();
-    println!("Run server: http://{}", addr);
-    let listener = TcpListener::bind(addr)?;
-
-    let pool = ThreadPool::new(config.threads.unwrap_or(4) as usize);
-    // accept connections and process them serially
-    for stream in listener.incoming() {
-        let stream = stream.unwrap();
-        let _config = Arc::clone(&config);
-        pool.execute(move || {
-            handle_client(stream, _config);
+pub(crate) fn handle_client(
+    stream: TcpStream,
+    config: Arc<ServerConfig>,
+    shutdown: ShutdownHandle,
+    callbacks: RunningCallbacks,
+) {
+    handle_connection(&stream, &stream, &config, &shutdown, &callbacks);
+}
+
+/// Serve requests of connection until close, `socket` is underlying tcp stream of `stream`
+pub(crate) fn handle_connection<S: Read + Write>(
+    socket: &TcpStream,
+    stream: S,
+    config: &Arc<ServerConfig>,
+    shutdown: &ShutdownHandle,
+    callbacks: &RunningCallbacks,
+) -> S {
+    let addr = socket.peer_addr().ok();
+    log::debug!("client {:?} connected", addr);
+    let keep_alive = config.keep_alive_timeout();
+    // Медленный клиент не должен занимать воркер бесконечно
+    if let Err(e) = socket.set_write_timeout(config.request_timeout()) {
+        log::warn!("Set write timeout fail: {:?}", e);
+    }
+    let mut reader = BufReader::new(stream);
+    for i in 1..=MAX_KEEP_ALIVE_REQUESTS {
+        if i > 1 && shutdown.is_shutdown() {
+            break;
+        }
+        set_read_timeout(socket, read_timeout(config, i > 1));
+        let lines = match read_head(&mut reader) {
+            Ok(lines) => lines,
+            Err(e) if HeadTooLarge::is(&e) => {
+                log::warn!("client {:?} {}", addr, e);
+                let mut response = HeadTooLarge::to_response();
+                let _ = write_response(reader.get_mut(), &mut response, true, &HashMap::new());
+                break;
+            }
+            Err(e) => {
+                // Таймаут простоя или обрыв соединения
+                log::debug!("client {:?} read fail: {:?}", addr, e);
+                break;
+            }
+        };
+        if lines.is_empty() {
+            // Close
+            break;
+        }
+        // Тело читается с тем же дедлайном, что и первый запрос
+        set_read_timeout(socket, read_timeout(config, false));
+        let can_keep_alive = keep_alive.is_some() && i < MAX_KEEP_ALIVE_REQUESTS;
+        if !handle_request(
+            socket,
+            &mut reader,
+            &lines,
+            config,
+            can_keep_alive,
+            shutdown,
+            callbacks,
+        ) {
+            break;
+        }
+    }
+    log::debug!("client {:?} disconnected", addr);
+    reader.into_inner()
+}
+
+/// Stops [`Server`] from other thread or signal handler
+#[derive(Debug, Clone)]
+pub struct ShutdownHandle {
+    flag: Arc<AtomicBool>,
+    pub(crate) addrs: Vec<SocketAddr>,
+}
+
+impl ShutdownHandle {
+    pub(crate) fn new(addrs: Vec<SocketAddr>) -> ShutdownHandle {
+        ShutdownHandle {
+            flag: Arc::new(AtomicBool::new(false)),
+            addrs,
+        }
+    }
+
+    /// Stop accepting connections, returns `false` if already stopping
+    pub fn shutdown(&self) -> bool {
+        if self.flag.swap(true, Ordering::SeqCst) {
+            return false;
+        }
+        // Будим заблокированные accept
+        for addr in &self.addrs {
+            let mut addr = *addr;
+            if addr.ip().is_unspecified() {
+                addr.set_ip(match addr {
+                    SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                    SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+                });
+            }
+            let _ = TcpStream::connect_timeout(&addr, Duration::from_secs(1));
+        }
+        true
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+}
+
+/// Handles of servers stopped by SIGINT/SIGTERM
+static SIGNAL_HANDLES: Mutex<Vec<ShutdownHandle>> = Mutex::new(Vec::new());
+static SIGNAL_INIT: Once = Once::new();
+
+/// Shutdown server on SIGINT/SIGTERM, second signal terminates process immediately
+pub fn shutdown_on_signals(handle: ShutdownHandle) {
+    SIGNAL_INIT.call_once(|| {
+        let result = ctrlc::set_handler(|| {
+            let handles = SIGNAL_HANDLES.lock().unwrap_or_else(|e| e.into_inner());
+            let stopped = handles.iter().filter(|h| h.shutdown()).count();
+            if stopped == 0 {
+                log::warn!("Forced exit");
+                std::process::exit(1);
+            }
+            log::info!("Got stop signal, shutting down");
+        });
+        if let Err(e) = result {
+            log::warn!("Set signal handler fail: {:?}", e);
+        }
+    });
+    SIGNAL_HANDLES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(handle);
+}
+
+pub struct Server {
+    listeners: Vec<TcpListener>,
+    config: Arc<ServerConfig>,
+    shutdown: ShutdownHandle,
+    callbacks: RunningCallbacks,
+    #[cfg(feature = "tls")]
+    tls: Option<Arc<rustls::ServerConfig>>,
+}
+
+impl Server {
+    /// Bind all [`ServerConfig::addrs`]
+    pub fn bind(config: ServerConfig) -> io::Result<Server> {
+        log::debug!("Server config: {:?}", config);
+        #[cfg(feature = "tls")]
+        let tls = config
+            .tls
+            .as_ref()
+            .map(crate::server::tls::server_config)
+            .transpose()?;
+        #[cfg(not(feature = "tls"))]
+        if config.tls.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "HTTPS requires http_server `tls` feature",
+            ));
+        }
+        let listeners = config
+            .addrs()
+            .iter()
+            .map(|addr| {
+                TcpListener::bind(addr).inspect_err(|e| log::error!("Bind {} fail: {:?}", addr, e))
+            })
+            .collect::<io::Result<Vec<TcpListener>>>()?;
+        let shutdown = ShutdownHandle::new(
+            listeners
+                .iter()
+                .map(|l| l.local_addr())
+                .collect::<io::Result<_>>()?,
+        );
+        Ok(Server {
+            listeners,
+            callbacks: RunningCallbacks::new(
+                config.threads.unwrap_or(4) as usize,
+                config.routes.len(),
+            ),
+            config: Arc::new(config),
+            shutdown,
+            #[cfg(feature = "tls")]
+            tls,
         })
     }
-    Ok(())
+
+    /// First bound address
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listeners[0].local_addr()
+    }
+
+    pub fn local_addrs(&self) -> Vec<SocketAddr> {
+        self.shutdown.addrs.clone()
+    }
+
+    pub fn shutdown_handle(&self) -> ShutdownHandle {
+        self.shutdown.clone()
+    }
+
+    fn accept(&self, listener: &TcpListener, pool: &ThreadPool) {
+        for stream in listener.incoming() {
+            if self.shutdown.is_shutdown() {
+                break;
+            }
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    log::warn!("Accept fail: {:?}", e);
+                    continue;
+                }
+            };
+            let config = Arc::clone(&self.config);
+            let shutdown = self.shutdown.clone();
+            let callbacks = self.callbacks.clone();
+            #[cfg(feature = "tls")]
+            if let Some(tls) = self.tls.as_ref().map(Arc::clone) {
+                pool.execute(move || {
+                    crate::server::tls::handle_client(stream, tls, config, shutdown, callbacks);
+                });
+                continue;
+            }
+            pool.execute(move || {
+                handle_client(stream, config, shutdown, callbacks);
+            })
+        }
+    }
+
+    /// Accept connections until shutdown, then wait in-flight requests
+    /// up to [`ServerConfig::shutdown_timeout`]
+    pub fn serve(self) -> io::Result<()> {
+        let scheme = if self.config.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        for addr in self.local_addrs() {
+            println!("Run server: {}://{}", scheme, addr);
+        }
+        let pool = ThreadPool::new(self.config.threads.unwrap_or(4) as usize);
+        // Каждый адрес слушаем в своем потоке
+        thread::scope(|s| {
+            for listener in &self.listeners {
+                s.spawn(|| self.accept(listener, &pool));
+            }
+        });
+        drop(self.listeners);
+        let timeout = self.config.shutdown_timeout();
+        log::info!("Server stopped, waiting in-flight requests {:?}", timeout);
+        if !pool.shutdown(timeout) {
+            log::warn!("In-flight requests not finished in {:?}", timeout);
+        }
+        Ok(())
+    }
+}
+
+/// Run server until SIGINT/SIGTERM
+pub fn run(config: ServerConfig) -> io::Result<()> {
+    let server = Server::bind(config)?;
+    shutdown_on_signals(server.shutdown_handle());
+    server.serve()
 }
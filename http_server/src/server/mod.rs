@@ -1,17 +1,27 @@
+#[cfg(feature = "tokio")]
+pub mod async_server;
+pub mod auth;
+pub mod compression;
 pub mod config;
 /// Handmade http server
 pub mod data;
 pub mod error;
 pub mod http_server;
+pub mod range;
 pub mod response;
 
+pub use auth::Auth;
 pub use error::*;
 
 pub mod request;
 pub mod thread_pool;
+#[cfg(feature = "tls")]
+mod tls;
 pub mod utils;
 
-pub use self::http_server::run;
-pub use config::{Route, ServerConfig};
+#[cfg(feature = "tokio")]
+pub use self::async_server::{run_async, AsyncServer};
+pub use self::http_server::{run, shutdown_on_signals, Server, ShutdownHandle};
+pub use config::{Route, ServerConfig, TlsConfig};
 pub use request::{HTTPMethod, HTTPRequest};
-pub use response::HTTPResponse;
+pub use response::{ContentStream, HTTPResponse};
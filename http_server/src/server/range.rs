@@ -0,0 +1,86 @@
+use crate::server::request::HTTPRequest;
+use crate::server::response::HTTPResponse;
+
+/// Parse single `Range: bytes=...` for content of `len` bytes.
+/// `None` - ignore header and serve full content, `Some(Err(()))` - range not satisfiable
+/// ```
+/// use http_server::range::parse_range;
+/// assert_eq!(parse_range("bytes=0-499", 1000), Some(Ok((0, 499))));
+/// assert_eq!(parse_range("bytes=500-", 1000), Some(Ok((500, 999))));
+/// assert_eq!(parse_range("bytes=-100", 1000), Some(Ok((900, 999))));
+/// assert_eq!(parse_range("bytes=900-2000", 1000), Some(Ok((900, 999))));
+/// assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+/// assert_eq!(parse_range("bytes=0-1,5-6", 1000), None);
+/// assert_eq!(parse_range("items=0-1", 1000), None);
+/// ```
+pub fn parse_range(range: &str, len: usize) -> Option<Result<(usize, usize), ()>> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        // Несколько диапазонов не поддерживаем, отдаем целиком
+        return None;
+    }
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+    let range = if start.is_empty() {
+        let suffix: usize = end.parse().ok()?;
+        if suffix == 0 || len == 0 {
+            return Some(Err(()));
+        }
+        (len.saturating_sub(suffix), len - 1)
+    } else {
+        let start: usize = start.parse().ok()?;
+        let end: usize = if end.is_empty() {
+            usize::MAX
+        } else {
+            end.parse().ok()?
+        };
+        if end < start {
+            return None;
+        }
+        if start >= len {
+            return Some(Err(()));
+        }
+        (start, end.min(len - 1))
+    };
+    Some(Ok(range))
+}
+
+impl HTTPResponse {
+    /// Serve `Range` request from full response with `Accept-Ranges: bytes`
+    pub fn range(self, request: &HTTPRequest) -> Self {
+        if self.status != 200 || self.header("Accept-Ranges") != Some("bytes") {
+            return self;
+        }
+        let Some(range) = request.header("Range") else {
+            return self;
+        };
+        let response = self.with_etag();
+        if let Some(if_range) = request.header("If-Range") {
+            // Представление изменилось, отдаем целиком
+            if Some(if_range) != response.header("ETag")
+                && Some(if_range) != response.header("Last-Modified")
+            {
+                return response;
+            }
+        }
+        let Some(content) = response.content.as_ref() else {
+            return response;
+        };
+        let len = content.len();
+        match parse_range(range, len) {
+            None => response,
+            Some(Err(_)) => HTTPResponse::new(416)
+                .set_header("Content-Range", format!("bytes */{len}").as_str()),
+            Some(Ok((start, end))) => {
+                let content = content.slice(start..=end);
+                let mut response = response.set_header(
+                    "Content-Range",
+                    format!("bytes {start}-{end}/{len}").as_str(),
+                );
+                response.status = 206;
+                response.content = Some(content);
+                response
+            }
+        }
+    }
+}
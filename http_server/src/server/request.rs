@@ -1,16 +1,21 @@
 use std::collections::HashMap;
-use std::net::TcpStream;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
+use std::net::{SocketAddr, TcpStream};
+use std::str::FromStr;
 use std::sync::Arc;
 
 use url::Url;
 
 use HTTPError::*;
 
+use crate::server::auth::{redact_token, AUTH_TOKEN_PARAM};
 use crate::server::config::ServerConfig;
 use crate::server::error;
 use crate::server::error::HTTPError;
 
-#[derive(Default, Debug, Eq, PartialEq)]
+#[derive(Default, Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum HTTPMethod {
     HEAD,
     OPTIONS,
@@ -33,11 +38,41 @@ impl HTTPMethod {
             _ => Err(InvalidMethod),
         }
     }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HTTPMethod::HEAD => "HEAD",
+            HTTPMethod::OPTIONS => "OPTIONS",
+            HTTPMethod::GET => "GET",
+            HTTPMethod::POST => "POST",
+            HTTPMethod::PUT => "PUT",
+            HTTPMethod::DELETE => "DELETE",
+        }
+    }
+}
+
+impl Display for HTTPMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
+/// Parse param value, invalid value is bad request
+fn parse_param<T: FromStr>(name: &str, value: &str) -> error::Result<T> {
+    value.parse().map_err(|_| {
+        log::debug!("Invalid param {}={:?}", name, value);
+        InvalidRequest
+    })
+}
+
+/// Limit of request body size
+pub const MAX_BODY_SIZE: usize = 10 * 1024 * 1024;
+
 #[derive(Debug, Default)]
 pub struct HTTPRequest<'a> {
     pub method: HTTPMethod,
+    /// `HTTP/1.1`
+    pub version: String,
     pub path: String,
     pub path_params: Option<HashMap<String, Option<String>>>,
     pub full_path: String,
@@ -48,7 +83,7 @@ pub struct HTTPRequest<'a> {
     pub config: Option<Arc<ServerConfig>>,
 }
 
-impl HTTPRequest<'_> {
+impl<'a> HTTPRequest<'a> {
     ///
     /// ```
     /// use http_server::HTTPRequest;
@@ -62,7 +97,7 @@ impl HTTPRequest<'_> {
     /// assert_eq!(r.query_params.len(), 2);
     /// assert_eq!(r.query_params.get("a").unwrap(), "1");
     /// ```
-    pub fn parse(lines: &[String]) -> error::Result<HTTPRequest> {
+    pub fn parse(lines: &[String]) -> error::Result<HTTPRequest<'a>> {
         log::trace!("parse: lines={:?}", lines);
         let req_head = lines[0]
             .split_whitespace()
@@ -70,7 +105,7 @@ impl HTTPRequest<'_> {
             .collect::<Vec<&str>>();
 
         let mut request = match req_head[..] {
-            [method, path, _http_version] => {
+            [method, path, version] => {
                 let url = Url::parse(format!("http://example.com{path}").as_str()).unwrap();
                 let query_params: HashMap<String, String> = url
                     .query_pairs()
@@ -79,6 +114,7 @@ impl HTTPRequest<'_> {
                     .collect();
                 HTTPRequest {
                     method: HTTPMethod::from_str(method)?,
+                    version: version.to_string(),
                     path: url.path().to_string(),
                     full_path: path.to_string(),
                     query_params,
@@ -107,12 +143,331 @@ impl HTTPRequest<'_> {
         Ok(request)
     }
 
+    /// Owned copy for other thread, without connection.
+    /// Address of connection is kept as `Host` header for [`HTTPRequest::host`]
+    pub(crate) fn detach(&self) -> HTTPRequest<'static> {
+        let mut request = HTTPRequest {
+            method: self.method,
+            version: self.version.clone(),
+            path: self.path.clone(),
+            path_params: self.path_params.clone(),
+            full_path: self.full_path.clone(),
+            query_params: self.query_params.clone(),
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+            stream: None,
+            config: self.config.clone(),
+        };
+        if let Some(addr) = self.stream.as_ref().and_then(|s| s.local_addr().ok()) {
+            request.set_local_addr(addr);
+        }
+        request
+    }
+
+    /// Address of connection for request without stream, kept as `Host` header
+    /// if request has no valid one
+    pub(crate) fn set_local_addr(&mut self, addr: SocketAddr) {
+        if self.header_host().is_none() {
+            self.headers.retain(|k, _| !k.eq_ignore_ascii_case("Host"));
+            self.headers.insert("Host".to_string(), addr.to_string());
+        }
+    }
+
+    /// Header value, name is case-insensitive
+    /// ```
+    /// use http_server::HTTPRequest;
+    /// let v = vec!["GET / HTTP/1.1".to_string(), "If-None-Match: \"foo\"".to_string()];
+    /// let r = HTTPRequest::parse(&v).unwrap();
+    /// assert_eq!(r.header("if-none-match"), Some("\"foo\""));
+    /// ```
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Client wants persistent connection
+    /// ```
+    /// use http_server::HTTPRequest;
+    /// let parse = |lines: &[&str]| {
+    ///     let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+    ///     HTTPRequest::parse(&lines).unwrap().is_keep_alive()
+    /// };
+    /// assert!(parse(&["GET / HTTP/1.1"]));
+    /// assert!(!parse(&["GET / HTTP/1.1", "Connection: close"]));
+    /// assert!(!parse(&["GET / HTTP/1.0"]));
+    /// assert!(parse(&["GET / HTTP/1.0", "Connection: Keep-Alive"]));
+    /// ```
+    pub fn is_keep_alive(&self) -> bool {
+        let has_token = |token: &str| {
+            self.header("Connection")
+                .is_some_and(|c| c.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+        };
+        if self.version == "HTTP/1.0" {
+            has_token("keep-alive")
+        } else {
+            !has_token("close")
+        }
+    }
+
+    /// Read body by `Content-Length` or `Transfer-Encoding: chunked`
+    pub fn read_body<R: BufRead>(&mut self, reader: &mut R) -> error::Result<()> {
+        let body = if self
+            .header("Transfer-Encoding")
+            .is_some_and(|t| t.to_ascii_lowercase().contains("chunked"))
+        {
+            read_chunked_body(reader)?
+        } else if let Some(length) = self.header("Content-Length") {
+            let length: usize = length.trim().parse().map_err(|_| InvalidRequest)?;
+            if length > MAX_BODY_SIZE {
+                log::error!("Body too large length={}", length);
+                return Err(InvalidRequest);
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).map_err(|e| {
+                log::error!("Read body fail: {:?}", e);
+                InvalidRequest
+            })?;
+            body
+        } else {
+            return Ok(());
+        };
+        self.body = Some(String::from_utf8_lossy(&body).to_string());
+        Ok(())
+    }
+
+    /// Path param by placeholder name or capture group index,
+    /// `None` if route has no such param or optional group not matched
+    /// ```
+    /// use std::collections::HashMap;
+    /// use http_server::HTTPRequest;
+    /// let r = HTTPRequest {
+    ///     path_params: Some(HashMap::from([
+    ///         ("id".to_string(), Some("123".to_string())),
+    ///         ("page".to_string(), None),
+    ///     ])),
+    ///     ..HTTPRequest::default()
+    /// };
+    /// assert_eq!(r.path_param_opt::<usize>("id").unwrap(), Some(123));
+    /// assert_eq!(r.path_param_opt::<usize>("page").unwrap(), None);
+    /// assert!(r.path_param_opt::<bool>("id").is_err());
+    /// ```
+    pub fn path_param_opt<T: FromStr>(&self, name: &str) -> error::Result<Option<T>> {
+        self.path_params
+            .as_ref()
+            .and_then(|p| p.get(name))
+            .and_then(|v| v.as_deref())
+            .map(|v| parse_param(name, v))
+            .transpose()
+    }
+
+    /// Required path param, see [`HTTPRequest::path_param_opt`]
+    /// ```
+    /// use std::collections::HashMap;
+    /// use http_server::HTTPRequest;
+    /// let r = HTTPRequest {
+    ///     path_params: Some(HashMap::from([("slug".to_string(), Some("foo".to_string()))])),
+    ///     ..HTTPRequest::default()
+    /// };
+    /// assert_eq!(r.path_param::<String>("slug").unwrap(), "foo");
+    /// assert!(r.path_param::<String>("other").is_err());
+    /// ```
+    pub fn path_param<T: FromStr>(&self, name: &str) -> error::Result<T> {
+        self.path_param_opt(name)?.ok_or_else(|| {
+            log::debug!("Missing path param {:?}", name);
+            InvalidRequest
+        })
+    }
+
+    /// Query param, `None` if absent
+    /// ```
+    /// use http_server::HTTPRequest;
+    /// let v = vec!["GET /?before=10&limit=foo HTTP/1.1".to_string()];
+    /// let r = HTTPRequest::parse(&v).unwrap();
+    /// assert_eq!(r.query_param::<usize>("before").unwrap(), Some(10));
+    /// assert_eq!(r.query_param::<usize>("after").unwrap(), None);
+    /// assert!(r.query_param::<usize>("limit").is_err());
+    /// ```
+    pub fn query_param<T: FromStr>(&self, name: &str) -> error::Result<Option<T>> {
+        self.query_params
+            .get(name)
+            .map(|v| parse_param(name, v))
+            .transpose()
+    }
+
+    /// First value of `X-Forwarded-*` header, if [`ServerConfig::trust_forwarded`]
+    fn forwarded(&self, name: &str) -> Option<&str> {
+        if !self.config.as_ref().is_some_and(|c| c.trust_forwarded) {
+            return None;
+        }
+        self.header(name)
+            .and_then(|v| v.split(',').next())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+    }
+
+    /// Valid host from `X-Forwarded-Host` or `Host` header
+    fn header_host(&self) -> Option<&str> {
+        let headers = [self.forwarded("X-Forwarded-Host"), self.header("Host")];
+        headers.into_iter().flatten().find(|h| is_valid_host(h))
+    }
+
+    /// Host from `X-Forwarded-Host` or `Host` header, else address of connection or config
+    /// ```
+    /// use std::sync::Arc;
+    /// use http_server::{HTTPRequest, ServerConfig};
+    /// let mut r = HTTPRequest::default();
+    /// r.headers.insert("host".to_string(), "[::1]:8080".to_string());
+    /// assert_eq!(r.host(), Some("[::1]:8080".to_string()));
+    /// r.headers.insert("X-Forwarded-Host".to_string(), "feeds.example.com, proxy".to_string());
+    /// assert_eq!(r.host(), Some("[::1]:8080".to_string()));
+    /// r.config = Some(Arc::new(ServerConfig {
+    ///     trust_forwarded: true,
+    ///     ..ServerConfig::default()
+    /// }));
+    /// assert_eq!(r.host(), Some("feeds.example.com".to_string()));
+    /// r.headers.insert("X-Forwarded-Host".to_string(), "evil.com/path?".to_string());
+    /// assert_eq!(r.host(), Some("[::1]:8080".to_string()));
+    /// ```
+    pub fn host(&self) -> Option<String> {
+        if let Some(host) = self.header_host() {
+            return Some(host.to_string());
+        }
+        if let Some(addr) = self.stream.as_ref().and_then(|s| s.local_addr().ok()) {
+            return Some(addr.to_string());
+        }
+        self.config.as_ref().map(|c| c.addr())
+    }
+
+    /// Externally visible base url: [`ServerConfig::public_url`] or built from
+    /// `X-Forwarded-Proto`, `X-Forwarded-Prefix` and [`HTTPRequest::host`]
+    /// ```
+    /// use std::sync::Arc;
+    /// use http_server::{HTTPRequest, ServerConfig};
+    /// let mut r = HTTPRequest::default();
+    /// r.headers.insert("Host".to_string(), "127.0.0.1:12345".to_string());
+    /// assert_eq!(r.base_url().as_str(), "http://127.0.0.1:12345/");
+    /// r.config = Some(Arc::new(ServerConfig {
+    ///     trust_forwarded: true,
+    ///     ..ServerConfig::default()
+    /// }));
+    /// r.headers.insert("X-Forwarded-Proto".to_string(), "https".to_string());
+    /// r.headers.insert("X-Forwarded-Host".to_string(), "example.com".to_string());
+    /// r.headers.insert("X-Forwarded-Prefix".to_string(), "/feeds/".to_string());
+    /// assert_eq!(r.base_url().as_str(), "https://example.com/feeds/");
+    /// ```
+    pub fn base_url(&self) -> Url {
+        if let Some(url) = self.config.as_ref().and_then(|c| c.public_url.clone()) {
+            return url;
+        }
+        let is_tls = self.config.as_ref().is_some_and(|c| c.tls.is_some());
+        let proto = self
+            .forwarded("X-Forwarded-Proto")
+            .filter(|p| matches!(*p, "http" | "https"))
+            .unwrap_or(if is_tls { "https" } else { "http" });
+        let prefix = self
+            .forwarded("X-Forwarded-Prefix")
+            .filter(|p| p.starts_with('/') && !p.contains(['?', '#', '\\']))
+            .unwrap_or("/");
+        let host = self.host().unwrap_or_else(|| "localhost".to_string());
+        Url::parse(format!("{proto}://{host}{prefix}").as_str()).unwrap()
+    }
+
+    /// Absolute url of server `path` against [`HTTPRequest::base_url`].
+    /// Auth token of request is kept, so generated links stay authorized
+    /// ```
+    /// use std::sync::Arc;
+    /// use http_server::{HTTPRequest, ServerConfig};
+    /// let mut r = HTTPRequest::default();
+    /// r.config = Some(Arc::new(ServerConfig {
+    ///     trust_forwarded: true,
+    ///     ..ServerConfig::default()
+    /// }));
+    /// r.headers.insert("Host".to_string(), "example.com".to_string());
+    /// r.headers.insert("X-Forwarded-Prefix".to_string(), "/feeds".to_string());
+    /// assert_eq!(
+    ///     r.absolute_url("/telegram/media").as_str(),
+    ///     "http://example.com/feeds/telegram/media"
+    /// );
+    /// r.query_params.insert("token".to_string(), "secret".to_string());
+    /// assert_eq!(
+    ///     r.absolute_url("/telegram/media").as_str(),
+    ///     "http://example.com/feeds/telegram/media?token=secret"
+    /// );
+    /// ```
+    pub fn absolute_url(&self, path: &str) -> Url {
+        let mut url = self.base_url();
+        let prefix = url.path().trim_end_matches('/').to_string();
+        url.set_path(format!("{prefix}{path}").as_str());
+        if let Some(token) = self.query_params.get(AUTH_TOKEN_PARAM) {
+            url.query_pairs_mut().append_pair(AUTH_TOKEN_PARAM, token);
+        }
+        url
+    }
+
+    /// `full_path` for logs, without value of auth token
+    /// ```
+    /// use http_server::HTTPRequest;
+    /// let r = HTTPRequest::parse(&["GET /feed/?token=secret HTTP/1.1".to_string()]).unwrap();
+    /// assert_eq!(r.log_path(), "/feed/?token=***");
+    /// ```
+    pub fn log_path(&self) -> String {
+        redact_token(&self.full_path)
+    }
+
+    /// Externally visible url of this request
     pub fn url(&self) -> Url {
-        let s = format!(
-            "http://{}{}",
-            self.config.as_ref().unwrap().addr(),
-            self.full_path
-        );
-        Url::parse(s.as_str()).unwrap()
+        let mut url = self.absolute_url(&self.path);
+        // Query целиком из запроса, вместе с токеном
+        url.set_query(self.full_path.split_once('?').map(|(_, q)| q));
+        url
+    }
+}
+
+fn is_valid_host(host: &str) -> bool {
+    !host.is_empty()
+        && !host.contains(['/', '\\', '?', '#', '@', ' '])
+        && Url::parse(format!("http://{host}/").as_str()).is_ok()
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> error::Result<String> {
+    let mut line = String::new();
+    match reader.read_line(&mut line) {
+        Ok(0) => Err(InvalidRequest),
+        Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+        Err(e) => {
+            log::error!("Read line fail: {:?}", e);
+            Err(InvalidRequest)
+        }
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc9112#section-7.1
+fn read_chunked_body<R: BufRead>(reader: &mut R) -> error::Result<Vec<u8>> {
+    let mut body: Vec<u8> = Vec::new();
+    loop {
+        let line = read_line(reader)?;
+        // Расширения чанка после `;` игнорируем
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| InvalidRequest)?;
+        if size == 0 {
+            break;
+        }
+        if body.len() + size > MAX_BODY_SIZE {
+            log::error!("Body too large");
+            return Err(InvalidRequest);
+        }
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader
+            .read_exact(&mut body[start..])
+            .map_err(|_| InvalidRequest)?;
+        if !read_line(reader)?.is_empty() {
+            return Err(InvalidRequest);
+        }
     }
+    // Trailer headers
+    while !read_line(reader)?.is_empty() {}
+    Ok(body)
 }
@@ -2,6 +2,64 @@ use bytes::Bytes;
 use std::collections::HashMap;
 use std::fmt;
 use std::fmt::{Display, Formatter};
+use std::io::{self, Read, Write};
+use std::time::SystemTime;
+
+use crate::server::request::{HTTPMethod, HTTPRequest};
+use crate::server::utils::fnv1a;
+
+/// Streamed body, written without buffering in memory
+pub struct ContentStream {
+    pub reader: Box<dyn Read + Send>,
+    /// Known length, otherwise sent with chunked transfer encoding
+    pub length: Option<u64>,
+    /// Body without length ends by closing connection, for HTTP/1.0 clients
+    pub until_close: bool,
+}
+
+impl ContentStream {
+    pub fn new(reader: impl Read + Send + 'static, length: Option<u64>) -> Self {
+        ContentStream {
+            reader: Box::new(reader),
+            length,
+            until_close: false,
+        }
+    }
+
+    /// Write body to client, in chunked encoding if length unknown
+    pub fn write_to<W: Write>(&mut self, writer: &mut W) -> io::Result<u64> {
+        if self.length.is_none() && self.until_close {
+            return io::copy(&mut self.reader, writer);
+        }
+        if let Some(length) = self.length {
+            let written = io::copy(&mut self.reader.by_ref().take(length), writer)?;
+            if written < length {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("Stream ended at {written} of {length} bytes"),
+                ));
+            }
+            return Ok(written);
+        }
+        let mut buf = vec![0; 64 * 1024];
+        let mut written: u64 = 0;
+        loop {
+            let n = match self.reader.read(&mut buf) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if n == 0 {
+                writer.write_all(b"0\r\n\r\n")?;
+                return Ok(written);
+            }
+            writer.write_all(format!("{n:x}\r\n").as_bytes())?;
+            writer.write_all(&buf[..n])?;
+            writer.write_all(b"\r\n")?;
+            written += n as u64;
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct HTTPResponse {
@@ -9,6 +67,8 @@ pub struct HTTPResponse {
     pub content: Option<Bytes>,
     pub content_type: Option<String>,
     pub headers: HashMap<String, String>,
+    /// Used instead of `content` for large bodies
+    pub content_stream: Option<ContentStream>,
 }
 
 impl HTTPResponse {
@@ -25,15 +85,130 @@ impl HTTPResponse {
             ..HTTPResponse::default()
         }
     }
+    /// ```
+    /// use std::io::Cursor;
+    /// use http_server::HTTPResponse;
+    /// let r = HTTPResponse::with_stream(Cursor::new("foo"), Some(3));
+    /// assert_eq!(r.status, 200);
+    /// assert!(r.to_string().contains("Content-Length: 3"));
+    /// let r = HTTPResponse::with_stream(Cursor::new("foo"), None);
+    /// assert!(r.to_string().contains("Transfer-Encoding: chunked"));
+    /// ```
+    pub fn with_stream(reader: impl Read + Send + 'static, length: Option<u64>) -> Self {
+        HTTPResponse {
+            status: 200,
+            content_stream: Some(ContentStream::new(reader, length)),
+            ..HTTPResponse::default()
+        }
+    }
+
+    /// Read streamed body into `content`
+    pub fn buffer_stream(mut self) -> io::Result<Self> {
+        if let Some(mut stream) = self.content_stream.take() {
+            let mut content = Vec::new();
+            stream.reader.read_to_end(&mut content)?;
+            self.content = Some(Bytes::from(content));
+        }
+        Ok(self)
+    }
+
     pub fn set_content_type(mut self, content_type: &str) -> Self {
         self.content_type = Some(content_type.to_string());
         self
     }
 
+    /// Set header, replacing existing with any case
     pub fn set_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.retain(|k, _| !k.eq_ignore_ascii_case(name));
         self.headers.insert(name.to_string(), value.to_string());
         self
     }
+
+    /// Remove header, name is case-insensitive
+    pub fn remove_header(mut self, name: &str) -> Self {
+        self.headers.retain(|k, _| !k.eq_ignore_ascii_case(name));
+        self
+    }
+
+    /// Header value, name is case-insensitive
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn set_last_modified(self, time: SystemTime) -> Self {
+        let value = httpdate::fmt_http_date(time);
+        self.set_header("Last-Modified", value.as_str())
+    }
+
+    /// Set ETag from content hash, if not set yet
+    /// ```
+    /// use http_server::HTTPResponse;
+    /// let r = HTTPResponse::with_content("foo").with_etag();
+    /// assert_eq!(r.header("etag"), Some("\"dcb27518fed9d577\""));
+    /// ```
+    pub fn with_etag(self) -> Self {
+        if self.header("ETag").is_some() {
+            return self;
+        }
+        let Some(content) = self.content.as_ref() else {
+            return self;
+        };
+        let etag = format!("\"{:016x}\"", fnv1a(content));
+        self.set_header("ETag", etag.as_str())
+    }
+
+    /// Check client validators `If-None-Match` and `If-Modified-Since`
+    pub fn is_not_modified(&self, request: &HTTPRequest) -> bool {
+        if self.status != 200 || !matches!(request.method, HTTPMethod::GET | HTTPMethod::HEAD) {
+            return false;
+        }
+        if let Some(if_none_match) = request.header("If-None-Match") {
+            // If-None-Match has precedence over If-Modified-Since
+            let Some(etag) = self.header("ETag") else {
+                return false;
+            };
+            let etag = etag.trim_start_matches("W/");
+            return if_none_match
+                .split(',')
+                .map(|t| t.trim())
+                .any(|t| t == "*" || t.trim_start_matches("W/") == etag);
+        }
+        let since = request
+            .header("If-Modified-Since")
+            .and_then(|s| httpdate::parse_http_date(s).ok());
+        let last_modified = self
+            .header("Last-Modified")
+            .and_then(|s| httpdate::parse_http_date(s).ok());
+        match (since, last_modified) {
+            (Some(since), Some(last_modified)) => last_modified <= since,
+            _ => false,
+        }
+    }
+
+    /// Compute validators and replace response to `304 Not Modified` if client has actual copy
+    pub fn conditional(self, request: &HTTPRequest) -> Self {
+        let response = self.with_etag();
+        if !response.is_not_modified(request) {
+            return response;
+        }
+        let headers = response
+            .headers
+            .into_iter()
+            .filter(|(k, _)| {
+                ["ETag", "Last-Modified", "Cache-Control", "Expires", "Vary"]
+                    .iter()
+                    .any(|h| k.eq_ignore_ascii_case(h))
+            })
+            .collect();
+        HTTPResponse {
+            status: 304,
+            headers,
+            ..HTTPResponse::default()
+        }
+    }
 }
 
 impl Display for HTTPResponse {
@@ -42,18 +217,35 @@ impl Display for HTTPResponse {
         let content = self.content.as_ref().unwrap_or(content);
 
         let mut extra_headers: Vec<String> = Vec::with_capacity(2);
-        if !content.is_empty() {
+        if !content.is_empty() || self.content_stream.is_some() {
             let content_type = &"text/plain".to_string();
             let content_type = self.content_type.as_ref().unwrap_or(content_type);
             extra_headers.push(format!("Content-Type: {}", content_type));
-            extra_headers.push(format!("Content-Length: {}", content.len()));
+        }
+        // Для keep-alive длина нужна всегда, кроме ответов без тела
+        if !matches!(self.status, 100..=199 | 204 | 304) {
+            match self.content_stream.as_ref() {
+                Some(ContentStream {
+                    length: Some(length),
+                    ..
+                }) => extra_headers.push(format!("Content-Length: {length}")),
+                Some(ContentStream {
+                    until_close: true, ..
+                }) => (),
+                Some(_) => extra_headers.push("Transfer-Encoding: chunked".to_string()),
+                None => extra_headers.push(format!("Content-Length: {}", content.len())),
+            }
         }
 
-        // TODO корректная обработка HEAD
-
+        // На HEAD заголовки те же, что и на GET, тело не пишется при отправке
         let headers = self
             .headers
             .iter()
+            // Длину тела считаем сами, заголовки проксируемого ответа тут не нужны
+            .filter(|(k, _)| {
+                !k.eq_ignore_ascii_case("Content-Length")
+                    && !k.eq_ignore_ascii_case("Transfer-Encoding")
+            })
             .map(|(k, v)| format!("{k}: {v}"))
             .chain(extra_headers)
             .collect::<Vec<String>>()
@@ -81,6 +273,10 @@ impl fmt::Debug for HTTPResponse {
                     .as_ref()
                     .map(|b| Bytes::from(format!("{} bytes content", b.len()))),
             )
+            .field(
+                "content_stream",
+                &self.content_stream.as_ref().map(|s| s.length),
+            )
             .finish()
     }
 }
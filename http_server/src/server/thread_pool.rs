@@ -1,17 +1,78 @@
 /// https://doc.rust-lang.org/book/ch20-02-multithreaded.html
 /// https://doc.rust-lang.org/book/ch20-03-graceful-shutdown-and-cleanup.html
+use std::any::Any;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
 pub struct ThreadPool {
     /// Кладем воркеров, они нужны больше для аккуратной остановки
     workers: Vec<Worker>,
     sender: Option<mpsc::Sender<Job>>,
     receiver: Option<Arc<Mutex<mpsc::Receiver<Job>>>>,
+    stats: Arc<PoolStats>,
 }
 
 type Job = Box<dyn FnOnce() + Send + 'static>;
 
+/// Load of pool, all workers busy and jobs queued - saturated
+#[derive(Debug, Default)]
+struct PoolStats {
+    size: usize,
+    busy: AtomicUsize,
+    queued: AtomicUsize,
+    saturated: AtomicBool,
+}
+
+impl PoolStats {
+    fn queue(&self) {
+        let queued = self.queued.fetch_add(1, Ordering::SeqCst) + 1;
+        let busy = self.busy.load(Ordering::SeqCst);
+        if busy + queued > self.size && !self.saturated.swap(true, Ordering::SeqCst) {
+            log::warn!(
+                "ThreadPool saturated: {}/{} workers busy, {} jobs queued",
+                busy,
+                self.size,
+                queued
+            );
+        }
+    }
+
+    fn start(&self) {
+        self.queued.fetch_sub(1, Ordering::SeqCst);
+        self.busy.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn finish(&self) {
+        let busy = self.busy.fetch_sub(1, Ordering::SeqCst) - 1;
+        if busy < self.size
+            && self.queued.load(Ordering::SeqCst) == 0
+            && self.saturated.swap(false, Ordering::SeqCst)
+        {
+            log::info!("ThreadPool recovered: {}/{} workers busy", busy, self.size);
+        }
+    }
+}
+
+/// Message of panic payload
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Run `f`, panic is returned as error with message
+pub(crate) fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, String> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|e| panic_message(e.as_ref()))
+}
+
 impl ThreadPool {
     pub fn new(size: usize) -> ThreadPool {
         assert!(size > 0);
@@ -24,6 +85,10 @@ impl ThreadPool {
             workers: Vec::with_capacity(size),
             sender: Some(sender),
             receiver: Some(reciver),
+            stats: Arc::new(PoolStats {
+                size,
+                ..PoolStats::default()
+            }),
         };
         pool.add_workers(size);
         pool
@@ -34,6 +99,7 @@ impl ThreadPool {
                 i,
                 // И множим ссылку на получателя
                 Arc::clone(self.receiver.as_ref().unwrap()),
+                Arc::clone(&self.stats),
             );
             self.workers.push(worker);
         }
@@ -44,6 +110,7 @@ impl ThreadPool {
         F: FnOnce() + Send + 'static,
     {
         let job = Box::new(f);
+        self.stats.queue();
         self.sender
             .as_ref() // as_ref тут нужен чтобы достать в unwrap sender по ссылке, а не по значению (копирование)
             .unwrap()
@@ -51,6 +118,39 @@ impl ThreadPool {
             .send(job)
             .unwrap();
     }
+
+    /// Stop accepting jobs and wait queued and running jobs up to `timeout`.
+    ///
+    /// Returns `false` if some workers not finished in time, they are left detached
+    pub fn shutdown(mut self, timeout: Duration) -> bool {
+        log::trace!("ThreadPool shutdown(timeout={:?})", timeout);
+        // Воркеры доделают очередь и выйдут
+        drop(self.sender.take());
+        let deadline = Instant::now() + timeout;
+        loop {
+            let running = self
+                .workers
+                .iter()
+                .filter(|w| w.thread.as_ref().is_some_and(|t| !t.is_finished()))
+                .count();
+            if running == 0 {
+                break;
+            }
+            if Instant::now() >= deadline {
+                log::warn!("ThreadPool shutdown: {} workers still running", running);
+                // Оставляем потоки висеть, Drop не должен их ждать
+                for worker in &mut self.workers {
+                    if worker.thread.as_ref().is_some_and(|t| !t.is_finished()) {
+                        worker.thread.take();
+                    }
+                }
+                return false;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        // Оставшихся дождется Drop
+        true
+    }
 }
 
 impl Drop for ThreadPool {
@@ -76,16 +176,22 @@ struct Worker {
 }
 
 impl Worker {
-    fn new(id: usize, reciver: Arc<Mutex<mpsc::Receiver<Job>>>) -> Worker {
+    fn new(id: usize, reciver: Arc<Mutex<mpsc::Receiver<Job>>>, stats: Arc<PoolStats>) -> Worker {
         log::debug!("Worker {:?}: started", id);
         // Спавним поток
         let thread = thread::spawn(move // перемещаем ссылку reciver внутрь замыкания
             || loop { // И поток зависает в бесконечном цикле
-            let job = reciver.lock().unwrap().recv();
+            // Задачи выполняются вне блокировки, но на всякий случай игнорируем poison
+            let job = reciver.lock().unwrap_or_else(|e| e.into_inner()).recv();
             match job {
                 Ok(job) => {
                     log::debug!("Worker {:?}: got a job; executing.", id);
-                    job()
+                    stats.start();
+                    // Паника в задаче не должна убивать воркер
+                    if let Err(message) = catch_panic(job) {
+                        log::error!("Worker {:?}: job panicked: {}", id, message);
+                    }
+                    stats.finish();
                 }
                 Err(_) => {
                     // Когда дропнули ссылку на sender,
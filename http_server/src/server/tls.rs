@@ -0,0 +1,143 @@
+//! HTTPS listener on top of rustls
+use std::fs;
+use std::io;
+use std::io::{BufReader, Write};
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
+
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use rustls::{Certificate, PrivateKey, ServerConnection, StreamOwned};
+
+use crate::server::config::{ServerConfig, TlsConfig};
+use crate::server::http_server::{handle_connection, RunningCallbacks, ShutdownHandle};
+
+/// Modification time and size of cert and key files
+type FilesStamp = [Option<(SystemTime, u64)>; 2];
+
+fn file_stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Load certificate chain and private key from PEM files
+fn load_key(config: &TlsConfig) -> io::Result<CertifiedKey> {
+    let mut reader = BufReader::new(fs::File::open(&config.cert)?);
+    let certs: Vec<Certificate> = rustls_pemfile::certs(&mut reader)?
+        .into_iter()
+        .map(Certificate)
+        .collect();
+    if certs.is_empty() {
+        return Err(invalid_data(format!(
+            "No certificates in {:?}",
+            config.cert
+        )));
+    }
+
+    let mut reader = BufReader::new(fs::File::open(&config.key)?);
+    let key = loop {
+        match rustls_pemfile::read_one(&mut reader)? {
+            Some(
+                rustls_pemfile::Item::PKCS8Key(key)
+                | rustls_pemfile::Item::RSAKey(key)
+                | rustls_pemfile::Item::ECKey(key),
+            ) => break PrivateKey(key),
+            Some(_) => continue,
+            None => return Err(invalid_data(format!("No private key in {:?}", config.key))),
+        }
+    };
+    let key = rustls::sign::any_supported_type(&key)
+        .map_err(|e| invalid_data(format!("Unsupported private key: {e:?}")))?;
+    Ok(CertifiedKey::new(certs, key))
+}
+
+/// Certificate resolver, rereads files on handshake if they changed
+struct ReloadingCert {
+    config: TlsConfig,
+    state: RwLock<(FilesStamp, Arc<CertifiedKey>)>,
+}
+
+impl ReloadingCert {
+    fn new(config: &TlsConfig) -> io::Result<ReloadingCert> {
+        let stamp = [file_stamp(&config.cert), file_stamp(&config.key)];
+        let key = load_key(config)?;
+        Ok(ReloadingCert {
+            config: config.clone(),
+            state: RwLock::new((stamp, Arc::new(key))),
+        })
+    }
+
+    fn reload_if_changed(&self) {
+        let stamp = [file_stamp(&self.config.cert), file_stamp(&self.config.key)];
+        if self.state.read().unwrap_or_else(|e| e.into_inner()).0 == stamp {
+            return;
+        }
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        if state.0 == stamp {
+            // Уже перечитал другой поток
+            return;
+        }
+        // Запоминаем даже при ошибке, чтобы не перечитывать на каждом соединении
+        state.0 = stamp;
+        match load_key(&self.config) {
+            Ok(key) => {
+                log::info!("TLS certificate reloaded from {:?}", self.config.cert);
+                state.1 = Arc::new(key);
+            }
+            Err(e) => log::warn!("TLS certificate reload fail, keep previous: {:?}", e),
+        }
+    }
+}
+
+impl ResolvesServerCert for ReloadingCert {
+    fn resolve(&self, _client_hello: ClientHello) -> Option<Arc<CertifiedKey>> {
+        self.reload_if_changed();
+        Some(Arc::clone(
+            &self.state.read().unwrap_or_else(|e| e.into_inner()).1,
+        ))
+    }
+}
+
+pub(crate) fn server_config(config: &TlsConfig) -> io::Result<Arc<rustls::ServerConfig>> {
+    let resolver = ReloadingCert::new(config)?;
+    let mut tls_config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_cert_resolver(Arc::new(resolver));
+    tls_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(tls_config))
+}
+
+pub(crate) fn handle_client(
+    stream: TcpStream,
+    tls_config: Arc<rustls::ServerConfig>,
+    config: Arc<ServerConfig>,
+    shutdown: ShutdownHandle,
+    callbacks: RunningCallbacks,
+) {
+    // Клон сокета нужен для адресов и таймаутов, сам поток владеет rustls
+    let socket = match stream.try_clone() {
+        Ok(socket) => socket,
+        Err(e) => {
+            log::warn!("Clone socket fail: {:?}", e);
+            return;
+        }
+    };
+    let connection = match ServerConnection::new(tls_config) {
+        Ok(connection) => connection,
+        Err(e) => {
+            log::warn!("TLS connection fail: {:?}", e);
+            return;
+        }
+    };
+    let stream = StreamOwned::new(connection, stream);
+    let mut stream = handle_connection(&socket, stream, &config, &shutdown, &callbacks);
+    stream.conn.send_close_notify();
+    let _ = stream.flush();
+}
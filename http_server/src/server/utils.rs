@@ -17,19 +17,111 @@ use std::collections::HashMap;
 /// assert_eq!(c.unwrap().get("1").unwrap(), &Some("bar".to_string()));
 /// assert_eq!(parse_match_captures(&re, "/foo/baz/565"), None);
 /// ```
-/// currently no supported named captures https://github.com/rust-lang/regex/issues/955
+/// Named captures are available both by index and by name
+/// ```
+/// use http_server::utils::parse_match_captures;
+/// let re = regex::Regex::new("^/feed/(?P<slug>[^/]+)/$").unwrap();
+/// let c = parse_match_captures(&re, "/feed/foo/").unwrap();
+/// assert_eq!(c.get("1").unwrap(), &Some("foo".to_string()));
+/// assert_eq!(c.get("slug").unwrap(), &Some("foo".to_string()));
+/// ```
 pub fn parse_match_captures(
     re: &regex::Regex,
     text: &str,
 ) -> Option<HashMap<String, Option<String>>> {
     let cap = re.captures(text)?;
     let mut res: HashMap<String, Option<String>> = HashMap::with_capacity(cap.len());
-    for (i, c) in cap.iter().enumerate() {
-        res.insert(i.to_string(), c.map(|c| c.as_str().to_string()));
+    for ((i, c), name) in cap.iter().enumerate().zip(re.capture_names()) {
+        let value = c.map(|c| c.as_str().to_string());
+        if let Some(name) = name {
+            res.insert(name.to_string(), value.clone());
+        }
+        res.insert(i.to_string(), value);
     }
     Some(res)
 }
 
+/// Route pattern to regex: `{name}` is path segment, `{name:regex}` with custom regex.
+/// Everything else is regex as is
+/// ```
+/// use http_server::utils::pattern_to_regex;
+/// assert_eq!(pattern_to_regex("/feed/{slug}/"), "/feed/(?P<slug>[^/]+)/");
+/// assert_eq!(
+///     pattern_to_regex(r"/media/{id:\d{2,}}/(.+)"),
+///     r"/media/(?P<id>\d{2,})/(.+)"
+/// );
+/// // Quantifiers and escaped braces kept
+/// assert_eq!(pattern_to_regex(r"/a{2}/\{b}"), r"/a{2}/\{b}");
+/// // Unicode classes kept, also in custom regex
+/// assert_eq!(pattern_to_regex(r"/tag/\p{L}+/"), r"/tag/\p{L}+/");
+/// assert_eq!(
+///     pattern_to_regex(r"/tag/{name:\P{N}+}/"),
+///     r"/tag/(?P<name>\P{N}+)/"
+/// );
+/// regex::Regex::new(&pattern_to_regex(r"/tag/\p{L}+/{id}/")).unwrap();
+/// ```
+pub fn pattern_to_regex(pattern: &str) -> String {
+    let mut res = String::with_capacity(pattern.len() * 2);
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                res.push(c);
+                let Some(c) = chars.next() else {
+                    continue;
+                };
+                res.push(c);
+                if matches!(c, 'p' | 'P') && chars.peek() == Some(&'{') {
+                    // Класс юникода `\p{L}`, скобки не плейсхолдер
+                    for c in chars.by_ref() {
+                        res.push(c);
+                        if c == '}' {
+                            break;
+                        }
+                    }
+                }
+            }
+            '{' if chars.peek().is_some_and(|n| n.is_alphabetic() || *n == '_') => {
+                let mut name = String::new();
+                let mut re = String::new();
+                let mut in_re = false;
+                // Вложенные скобки в регулярке, например `\d{2}`
+                let mut depth = 0;
+                let mut escaped = false;
+                for c in chars.by_ref() {
+                    match c {
+                        _ if escaped => {
+                            escaped = false;
+                            re.push(c);
+                        }
+                        '\\' if in_re => {
+                            escaped = true;
+                            re.push(c);
+                        }
+                        '}' if depth == 0 => break,
+                        ':' if !in_re => in_re = true,
+                        _ if in_re => {
+                            match c {
+                                '{' => depth += 1,
+                                '}' => depth -= 1,
+                                _ => {}
+                            }
+                            re.push(c);
+                        }
+                        _ => name.push(c),
+                    }
+                }
+                if !in_re {
+                    re.push_str("[^/]+");
+                }
+                res.push_str(format!("(?P<{name}>{re})").as_str());
+            }
+            _ => res.push(c),
+        }
+    }
+    res
+}
+
 ///
 /// ```
 /// use std::collections::HashMap;
@@ -48,7 +140,11 @@ pub fn parse_match_captures(
 ///     ]);
 /// ```
 pub fn path_params_to_vec(path_params: &HashMap<String, Option<String>>) -> Vec<Option<&str>> {
-    let mut pairs: Vec<_> = path_params.iter().map(|(k, v)| (k, v)).collect();
+    // Только группы по индексу, именованные дублируют их
+    let mut pairs: Vec<(usize, &Option<String>)> = path_params
+        .iter()
+        .filter_map(|(k, v)| k.parse().ok().map(|i| (i, v)))
+        .collect();
     pairs.sort();
 
     pairs
@@ -57,3 +153,17 @@ pub fn path_params_to_vec(path_params: &HashMap<String, Option<String>>) -> Vec<
         .map(|o| o.as_ref().map(|v| v.as_str()))
         .collect()
 }
+
+/// Stable FNV-1a 64 hash
+/// ```
+/// use http_server::utils::fnv1a;
+/// assert_eq!(fnv1a(b"foo"), 0xdcb27518fed9d577);
+/// ```
+pub fn fnv1a(data: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in data {
+        hash ^= *b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
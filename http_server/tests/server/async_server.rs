@@ -0,0 +1,156 @@
+use crate::keep_alive::{get, read_response, request, spawn_server};
+use http_server::{AsyncServer, HTTPMethod, HTTPResponse, Route, ServerConfig};
+use std::io::{BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn routes() -> Vec<Route> {
+    vec![
+        Route::new("/blocking/{name}", |r| {
+            let name: String = r.path_param("name")?;
+            Ok(HTTPResponse::with_content(name.as_str()))
+        }),
+        Route::new_async("/async/{name}", |r| async move {
+            let name: String = r.path_param("name")?;
+            Ok(HTTPResponse::with_content(name.as_str()))
+        }),
+        Route::new_async("/sleep", |_| async {
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Ok(HTTPResponse::with_content("done"))
+        }),
+        Route::new_async("/panic", |r| async move {
+            let value: u8 = r.query_params.get("value").unwrap().parse().unwrap();
+            Ok(HTTPResponse::with_content(value.to_string().as_str()))
+        }),
+        Route::new_async("/hang", |_| async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok(HTTPResponse::with_content("done"))
+        }),
+        Route::new("/echo", |r| {
+            Ok(HTTPResponse::with_content(
+                r.body.as_deref().unwrap_or("empty"),
+            ))
+        })
+        .with_methods(&[HTTPMethod::POST]),
+        Route::new("/stream", |_| {
+            Ok(HTTPResponse::with_stream(
+                std::io::Cursor::new("x".repeat(100_000)),
+                None,
+            ))
+        }),
+    ]
+}
+
+fn start_server() -> SocketAddr {
+    let config = ServerConfig {
+        port: Some(0),
+        threads: Some(1),
+        request_timeout: Some(1),
+        routes: routes(),
+        ..ServerConfig::default()
+    };
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .enable_all()
+        .build()
+        .unwrap();
+    let server = runtime.block_on(AsyncServer::bind(config)).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || runtime.block_on(server.serve()).unwrap());
+    addr
+}
+
+#[test]
+fn async_server_routes() {
+    let addr = start_server();
+    assert_eq!(get(addr, "/blocking/foo").1, "foo");
+    assert_eq!(get(addr, "/async/bar").1, "bar");
+
+    let (head, body) = get(addr, "/not_found");
+    assert_eq!(head[0], "HTTP/1.1 404");
+    assert_eq!(body, "404 Not Found\n");
+
+    let (head, _) = get(addr, "/echo");
+    assert_eq!(head[0], "HTTP/1.1 405");
+    assert!(head.contains(&"Allow: POST".to_string()));
+
+    let (head, body) = request(
+        addr,
+        "POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n3\r\nbar\r\n0\r\n\r\n",
+    );
+    assert_eq!(head[0], "HTTP/1.1 200");
+    assert_eq!(body, "bar");
+}
+
+#[test]
+fn async_server_keep_alive_stream() {
+    let addr = start_server();
+    let stream = TcpStream::connect(addr).unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut stream = stream;
+    stream
+        .write_all(b"GET /async/foo HTTP/1.1\r\n\r\n")
+        .unwrap();
+    let (head, body) = read_response(&mut reader);
+    assert!(head.contains(&"Connection: keep-alive".to_string()));
+    assert_eq!(body, "foo");
+
+    // Streamed body of unknown length is sent until close for HTTP/1.0
+    stream.write_all(b"GET /stream HTTP/1.0\r\n\r\n").unwrap();
+    let mut response = String::new();
+    reader.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    assert!(head.contains("Connection: close"));
+    assert!(!head.contains("Content-Length"));
+    assert!(!head.contains("Transfer-Encoding"));
+    assert_eq!(body.len(), 100_000);
+}
+
+#[test]
+fn async_server_concurrent() {
+    let addr = start_server();
+    let start = Instant::now();
+    let clients: Vec<_> = (0..20)
+        .map(|_| thread::spawn(move || get(addr, "/sleep").1))
+        .collect();
+    for client in clients {
+        assert_eq!(client.join().unwrap(), "done");
+    }
+    // Один поток рантайма, запросы не ждут друг друга
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn async_server_panic_and_timeout() {
+    let addr = start_server();
+    let (head, body) = get(addr, "/panic");
+    assert_eq!(head[0], "HTTP/1.1 500");
+    assert_eq!(body, "500 Internal Server Error: Internal server error\n");
+
+    let start = Instant::now();
+    let (head, _) = get(addr, "/hang");
+    assert_eq!(head[0], "HTTP/1.1 504");
+    assert!(start.elapsed() < Duration::from_secs(3));
+    assert_eq!(get(addr, "/async/ok").1, "ok");
+}
+
+#[test]
+fn async_route_in_blocking_server() {
+    let config = ServerConfig {
+        port: Some(0),
+        routes: routes(),
+        ..ServerConfig::default()
+    };
+    let addr = spawn_server(config);
+    assert_eq!(get(addr, "/async/foo").1, "foo");
+    assert_eq!(get(addr, "/sleep").1, "done");
+}
+
+#[test]
+fn async_server_head_too_large() {
+    let addr = start_server();
+    let line = format!("GET /{}", "a".repeat(64 * 1024 - 4));
+    let (head, _) = request(addr, &line);
+    assert_eq!(head[0], "HTTP/1.1 431");
+}
@@ -0,0 +1,69 @@
+use crate::keep_alive::request;
+use http_server::{Auth, HTTPResponse, Route, Server, ServerConfig};
+use std::net::SocketAddr;
+use std::thread;
+
+fn get(addr: SocketAddr, path: &str, headers: &str) -> (Vec<String>, String) {
+    request(
+        addr,
+        format!("GET {path} HTTP/1.0\r\n{headers}\r\n").as_str(),
+    )
+}
+
+#[test]
+fn auth_routes() {
+    let view = |r: &http_server::HTTPRequest| {
+        Ok(HTTPResponse::with_content(
+            r.absolute_url("/media").as_str(),
+        ))
+    };
+    let config = ServerConfig {
+        port: Some(0),
+        auth: Some(Auth {
+            basic: Some(("user".to_string(), "pass".to_string())),
+            token: Some("secret".to_string()),
+        }),
+        routes: vec![
+            Route::new("/private", view),
+            Route::new("/public", view).with_auth(Auth::default()),
+            Route::new("/other", view).with_auth(Auth {
+                token: Some("other".to_string()),
+                ..Auth::default()
+            }),
+        ],
+        ..ServerConfig::default()
+    };
+    let server = Server::bind(config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let serving = thread::spawn(move || server.serve());
+
+    let (head, _) = get(addr, "/private", "");
+    assert_eq!(head[0], "HTTP/1.1 401");
+    assert!(head
+        .iter()
+        .any(|h| h.starts_with("WWW-Authenticate: Basic")));
+    let (head, _) = get(addr, "/private", "Authorization: Basic dXNlcjpwYXNz\r\n");
+    assert_eq!(head[0], "HTTP/1.1 200");
+    // Token kept in generated links
+    let (head, body) = get(addr, "/private?token=secret", "Host: example.com\r\n");
+    assert_eq!(head[0], "HTTP/1.1 200");
+    assert_eq!(body, "http://example.com/media?token=secret");
+
+    let (head, _) = get(addr, "/public", "");
+    assert_eq!(head[0], "HTTP/1.1 200");
+
+    // Override, global credentials not accepted
+    let (head, _) = get(addr, "/other?token=secret", "");
+    assert_eq!(head[0], "HTTP/1.1 401");
+    assert!(!head.iter().any(|h| h.starts_with("WWW-Authenticate")));
+    let (head, _) = get(addr, "/other?token=other", "");
+    assert_eq!(head[0], "HTTP/1.1 200");
+
+    // Not found without auth check
+    let (head, _) = get(addr, "/not_found", "");
+    assert_eq!(head[0], "HTTP/1.1 404");
+
+    handle.shutdown();
+    serving.join().unwrap().unwrap();
+}
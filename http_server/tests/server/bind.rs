@@ -0,0 +1,50 @@
+use crate::keep_alive::read_response;
+use http_server::{HTTPResponse, Route, Server, ServerConfig};
+use std::io::{BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+
+fn get_url(addr: SocketAddr, host: Option<&str>) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    let host = host.map(|h| format!("Host: {h}\r\n")).unwrap_or_default();
+    let request = format!("GET /url?a=1 HTTP/1.0\r\n{host}\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    let (_, body) = read_response(&mut BufReader::new(stream));
+    body
+}
+
+#[test]
+fn bind_multiple() {
+    let config = ServerConfig {
+        bind: vec!["127.0.0.1".to_string(), "[::1]".to_string()],
+        port: Some(0),
+        routes: vec![Route::new("/url", |r| {
+            Ok(HTTPResponse::with_content(r.url().as_str()))
+        })],
+        ..ServerConfig::default()
+    };
+    let server = Server::bind(config).unwrap();
+    let addrs = server.local_addrs();
+    assert_eq!(addrs.len(), 2);
+    assert!(addrs[0].is_ipv4());
+    assert!(addrs[1].is_ipv6());
+    let handle = server.shutdown_handle();
+    let serving = thread::spawn(move || server.serve());
+
+    // Без Host берется адрес соединения
+    for addr in &addrs {
+        assert_eq!(get_url(*addr, None), format!("http://{addr}/url?a=1"));
+    }
+    assert_eq!(
+        get_url(addrs[1], Some("feeds.lan:8080")),
+        "http://feeds.lan:8080/url?a=1"
+    );
+    // Invalid host ignored
+    assert_eq!(
+        get_url(addrs[0], Some("evil.com/x?")),
+        format!("http://{}/url?a=1", addrs[0])
+    );
+
+    handle.shutdown();
+    serving.join().unwrap().unwrap();
+}
@@ -0,0 +1,85 @@
+use flate2::read::GzDecoder;
+use http_server::{HTTPRequest, HTTPResponse};
+use std::io::Read;
+
+fn request(accept_encoding: Option<&str>) -> HTTPRequest<'static> {
+    let mut r = HTTPRequest::default();
+    if let Some(accept_encoding) = accept_encoding {
+        r.headers
+            .insert("Accept-Encoding".to_string(), accept_encoding.to_string());
+    }
+    r
+}
+
+fn feed_response() -> HTTPResponse {
+    HTTPResponse::with_content("<entry>foo</entry>".repeat(100).as_str())
+        .set_content_type("text/xml")
+}
+
+#[test]
+fn test_gzip() {
+    let r = feed_response().compress(&request(Some("gzip")));
+    assert_eq!(r.header("Content-Encoding"), Some("gzip"));
+    assert_eq!(r.header("Vary"), Some("Accept-Encoding"));
+    let content = r.content.as_ref().unwrap();
+    assert!(content.len() < 1800);
+
+    let mut decoded = String::new();
+    GzDecoder::new(&content[..])
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, "<entry>foo</entry>".repeat(100));
+}
+
+#[test]
+fn test_not_accepted() {
+    let r = feed_response().compress(&request(None));
+    assert_eq!(r.header("Content-Encoding"), None);
+    assert_eq!(r.header("Vary"), Some("Accept-Encoding"));
+    assert_eq!(r.content.unwrap().len(), 1800);
+}
+
+#[test]
+fn test_skip_media_and_small() {
+    let r = HTTPResponse::with_content("x".repeat(1000).as_str())
+        .set_content_type("image/jpeg")
+        .compress(&request(Some("gzip")));
+    assert_eq!(r.header("Content-Encoding"), None);
+    assert_eq!(r.header("Vary"), None);
+
+    let r = HTTPResponse::with_content("small").compress(&request(Some("gzip")));
+    assert_eq!(r.header("Content-Encoding"), None);
+}
+
+#[test]
+fn test_etag_with_encoding() {
+    let identity_etag = feed_response()
+        .with_etag()
+        .header("ETag")
+        .unwrap()
+        .to_string();
+    let r = feed_response().compress(&request(Some("gzip")));
+    let etag = r.header("ETag").unwrap().to_string();
+    assert_eq!(
+        etag,
+        format!("{}-gzip\"", identity_etag.trim_end_matches('"'))
+    );
+
+    let mut req = request(Some("gzip"));
+    req.headers.insert("If-None-Match".to_string(), etag);
+    let r = feed_response().compress(&req).conditional(&req);
+    assert_eq!(r.status, 304);
+    assert_eq!(r.header("Vary"), Some("Accept-Encoding"));
+}
+
+#[cfg(feature = "brotli")]
+#[test]
+fn test_brotli() {
+    let r = feed_response().compress(&request(Some("gzip, br")));
+    assert_eq!(r.header("Content-Encoding"), Some("br"));
+    let mut decoded = String::new();
+    brotli::Decompressor::new(&r.content.as_ref().unwrap()[..], 4096)
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, "<entry>foo</entry>".repeat(100));
+}
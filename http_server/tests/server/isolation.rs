@@ -0,0 +1,102 @@
+use crate::keep_alive::{get, spawn_server};
+use http_server::{HTTPResponse, Route, ServerConfig};
+use std::net::SocketAddr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn start_server(request_timeout: Option<u64>) -> SocketAddr {
+    let config = ServerConfig {
+        port: Some(0),
+        // Один воркер, чтобы проверить что он выжил
+        threads: Some(1),
+        request_timeout,
+        routes: vec![
+            Route::new("/panic", |r| {
+                let value: u8 = r.query_params.get("value").unwrap().parse().unwrap();
+                Ok(HTTPResponse::with_content(value.to_string().as_str()))
+            }),
+            Route::new("/hang", |_| {
+                thread::sleep(Duration::from_secs(3));
+                Ok(HTTPResponse::with_content("done"))
+            }),
+            Route::new("/ok", |_| Ok(HTTPResponse::with_content("ok"))),
+        ],
+        ..ServerConfig::default()
+    };
+    spawn_server(config)
+}
+
+#[test]
+fn panic_returns_500() {
+    for request_timeout in [None, Some(0)] {
+        let addr = start_server(request_timeout);
+        for _ in 0..3 {
+            let (head, body) = get(addr, "/panic");
+            assert_eq!(head[0], "HTTP/1.1 500");
+            assert_eq!(body, "500 Internal Server Error: Internal server error\n");
+        }
+        let (head, body) = get(addr, "/ok");
+        assert_eq!(head[0], "HTTP/1.1 200");
+        assert_eq!(body, "ok");
+    }
+}
+
+#[test]
+fn request_timeout() {
+    let addr = start_server(Some(1));
+    let start = Instant::now();
+    let (head, body) = get(addr, "/hang");
+    assert_eq!(head[0], "HTTP/1.1 504");
+    assert_eq!(body, "504 Gateway Timeout: Request timed out\n");
+    assert!(start.elapsed() < Duration::from_secs(3));
+    // Worker is free while callback still runs
+    let (head, _) = get(addr, "/ok");
+    assert_eq!(head[0], "HTTP/1.1 200");
+
+    // Second hung callback reaches limit of one worker
+    let (head, _) = get(addr, "/hang");
+    assert_eq!(head[0], "HTTP/1.1 504");
+    let start = Instant::now();
+    let (head, body) = get(addr, "/hang");
+    assert_eq!(head[0], "HTTP/1.1 503");
+    assert_eq!(body, "503 Service Unavailable: Server overloaded\n");
+    assert!(start.elapsed() < Duration::from_secs(1));
+    // Limit is per route, other routes are served
+    let (head, body) = get(addr, "/ok");
+    assert_eq!(head[0], "HTTP/1.1 200");
+    assert_eq!(body, "ok");
+
+    // Finished callbacks free their slots
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let (head, _) = get(addr, "/hang");
+        if head[0] != "HTTP/1.1 503" {
+            break;
+        }
+        assert!(Instant::now() < deadline, "callbacks not finished");
+        thread::sleep(Duration::from_millis(200));
+    }
+}
+
+#[test]
+fn concurrent_calls_of_route() {
+    let config = ServerConfig {
+        port: Some(0),
+        threads: Some(2),
+        routes: vec![Route::new("/sleep", |_| {
+            thread::sleep(Duration::from_millis(500));
+            Ok(HTTPResponse::with_content("done"))
+        })],
+        ..ServerConfig::default()
+    };
+    let addr = spawn_server(config);
+    let start = Instant::now();
+    let requests: Vec<_> = (0..2)
+        .map(|_| thread::spawn(move || get(addr, "/sleep")))
+        .collect();
+    for request in requests {
+        assert_eq!(request.join().unwrap().0[0], "HTTP/1.1 200");
+    }
+    // Calls of one route are not serialized
+    assert!(start.elapsed() < Duration::from_millis(900));
+}
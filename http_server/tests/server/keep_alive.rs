@@ -0,0 +1,132 @@
+use http_server::{HTTPMethod, HTTPResponse, Route, Server, ServerConfig};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::Duration;
+
+fn start_server(port: u16, keep_alive: Option<u64>) {
+    let config = ServerConfig {
+        port: Some(port),
+        threads: Some(2),
+        keep_alive,
+        routes: vec![Route::new("/echo", |r| {
+            Ok(HTTPResponse::with_content(
+                r.body.as_deref().unwrap_or("empty"),
+            ))
+        })
+        .with_methods(&[HTTPMethod::GET, HTTPMethod::POST])],
+        ..ServerConfig::default()
+    };
+    let server = Server::bind(config).unwrap();
+    thread::spawn(move || server.serve().unwrap());
+}
+
+/// Read response head and body by Content-Length
+pub(crate) fn read_response<R: BufRead>(reader: &mut R) -> (Vec<String>, String) {
+    let mut head: Vec<String> = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let line = line.trim_end().to_string();
+        if line.is_empty() {
+            break;
+        }
+        head.push(line);
+    }
+    let length: usize = head
+        .iter()
+        .find_map(|h| h.strip_prefix("Content-Length: "))
+        .map_or(0, |l| l.parse().unwrap());
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    (head, String::from_utf8(body).unwrap())
+}
+
+/// Serve blocking server in background thread, `port: Some(0)` for random port
+pub(crate) fn spawn_server(config: ServerConfig) -> SocketAddr {
+    let server = Server::bind(config).unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.serve().unwrap());
+    addr
+}
+
+/// Send raw request on new connection
+pub(crate) fn request(addr: SocketAddr, request: &str) -> (Vec<String>, String) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request.as_bytes()).unwrap();
+    read_response(&mut BufReader::new(stream))
+}
+
+pub(crate) fn get(addr: SocketAddr, path: &str) -> (Vec<String>, String) {
+    request(
+        addr,
+        format!("GET {path} HTTP/1.1\r\nConnection: close\r\n\r\n").as_str(),
+    )
+}
+
+#[test]
+fn keep_alive() {
+    start_server(12391, None);
+    let mut stream = TcpStream::connect("127.0.0.1:12391").unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+    stream
+        .write_all(b"POST /echo HTTP/1.1\r\nContent-Length: 3\r\n\r\nfoo")
+        .unwrap();
+    let (head, body) = read_response(&mut reader);
+    assert_eq!(head[0], "HTTP/1.1 200");
+    assert!(head.contains(&"Connection: keep-alive".to_string()));
+    assert_eq!(body, "foo");
+
+    // Same connection, chunked body
+    stream
+        .write_all(
+            b"POST /echo HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nbar\r\n0\r\n\r\n",
+        )
+        .unwrap();
+    let (_, body) = read_response(&mut reader);
+    assert_eq!(body, "bar");
+
+    stream
+        .write_all(b"GET /not_found HTTP/1.1\r\nConnection: close\r\n\r\n")
+        .unwrap();
+    let (head, body) = read_response(&mut reader);
+    assert_eq!(head[0], "HTTP/1.1 404");
+    assert!(head.contains(&"Connection: close".to_string()));
+    assert!(head.contains(&"Content-Length: 14".to_string()));
+    assert_eq!(body, "404 Not Found\n");
+    // Server closed connection
+    let mut rest = String::new();
+    assert_eq!(reader.read_to_string(&mut rest).unwrap(), 0);
+}
+
+#[test]
+fn keep_alive_disabled() {
+    start_server(12392, Some(0));
+    let mut stream = TcpStream::connect("127.0.0.1:12392").unwrap();
+    stream.write_all(b"GET /echo HTTP/1.1\r\n\r\n").unwrap();
+    let mut reader = BufReader::new(stream);
+    let (head, body) = read_response(&mut reader);
+    assert!(head.contains(&"Connection: close".to_string()));
+    assert_eq!(body, "empty");
+}
+
+#[test]
+fn head_too_large() {
+    let addr = spawn_server(ServerConfig {
+        port: Some(0),
+        ..ServerConfig::default()
+    });
+    // Строка без конца, на байт больше лимита
+    let line = format!("GET /{}", "a".repeat(64 * 1024 - 4));
+    let (head, body) = request(addr, &line);
+    assert_eq!(head[0], "HTTP/1.1 431");
+    assert!(head.contains(&"Connection: close".to_string()));
+    assert_eq!(
+        body,
+        "431 Request Header Fields Too Large: Request header fields too large\n"
+    );
+}
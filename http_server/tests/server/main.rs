@@ -1,2 +1,14 @@
+#[cfg(feature = "tokio")]
+mod async_server;
+mod auth;
+mod bind;
+mod compression;
+mod isolation;
+mod keep_alive;
+mod range;
 mod request;
 mod response;
+mod route;
+mod shutdown;
+#[cfg(feature = "tls")]
+mod tls;
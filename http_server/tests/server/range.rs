@@ -0,0 +1,66 @@
+use bytes::Bytes;
+use http_server::{HTTPRequest, HTTPResponse};
+
+fn request(headers: &[(&str, &str)]) -> HTTPRequest<'static> {
+    let mut r = HTTPRequest::default();
+    for (k, v) in headers {
+        r.headers.insert(k.to_string(), v.to_string());
+    }
+    r
+}
+
+fn media_response() -> HTTPResponse {
+    HTTPResponse::with_content("0123456789")
+        .set_content_type("video/mp4")
+        .set_header("accept-ranges", "bytes")
+}
+
+#[test]
+fn test_range() {
+    let r = media_response().range(&request(&[("Range", "bytes=2-5")]));
+    assert_eq!(r.status, 206);
+    assert_eq!(r.content, Some(Bytes::from("2345")));
+    assert_eq!(r.header("Content-Range"), Some("bytes 2-5/10"));
+
+    let r = media_response().range(&request(&[("Range", "bytes=-3")]));
+    assert_eq!(r.status, 206);
+    assert_eq!(r.content, Some(Bytes::from("789")));
+    assert_eq!(r.header("Content-Range"), Some("bytes 7-9/10"));
+}
+
+#[test]
+fn test_range_not_satisfiable() {
+    let r = media_response().range(&request(&[("Range", "bytes=10-")]));
+    assert_eq!(r.status, 416);
+    assert_eq!(r.content, None);
+    assert_eq!(r.header("Content-Range"), Some("bytes */10"));
+}
+
+#[test]
+fn test_range_ignored() {
+    // Without range
+    let r = media_response().range(&request(&[]));
+    assert_eq!(r.status, 200);
+    // Not advertised
+    let r = HTTPResponse::with_content("0123456789").range(&request(&[("Range", "bytes=2-5")]));
+    assert_eq!(r.status, 200);
+    // Multiple ranges
+    let r = media_response().range(&request(&[("Range", "bytes=0-1,3-4")]));
+    assert_eq!(r.status, 200);
+    assert_eq!(r.content, Some(Bytes::from("0123456789")));
+}
+
+#[test]
+fn test_if_range() {
+    let etag = media_response()
+        .with_etag()
+        .header("ETag")
+        .unwrap()
+        .to_string();
+    let r = media_response().range(&request(&[("Range", "bytes=2-5"), ("If-Range", &etag)]));
+    assert_eq!(r.status, 206);
+
+    let r = media_response().range(&request(&[("Range", "bytes=2-5"), ("If-Range", "\"old\"")]));
+    assert_eq!(r.status, 200);
+    assert_eq!(r.content, Some(Bytes::from("0123456789")));
+}
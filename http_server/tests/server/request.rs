@@ -1,4 +1,7 @@
-use http_server::{HTTPMethod, HTTPRequest};
+use http_server::{HTTPMethod, HTTPRequest, ServerConfig};
+use std::io::Cursor;
+use std::sync::Arc;
+use url::Url;
 
 #[test]
 fn parse_request() {
@@ -16,3 +19,84 @@ Accept: */*"#
 
 #[test]
 fn parse_path() {}
+
+fn request_url(head: &str, config: ServerConfig) -> String {
+    let lines: Vec<String> = head.lines().map(|l| l.into()).collect();
+    let mut request = HTTPRequest::parse(&lines).unwrap();
+    request.config = Some(Arc::new(config));
+    request.url().to_string()
+}
+
+#[test]
+fn url_forwarded() {
+    let url = request_url(
+        "GET /feed/?page=2 HTTP/1.1\nHost: 127.0.0.1:12345",
+        ServerConfig::default(),
+    );
+    assert_eq!(url, "http://127.0.0.1:12345/feed/?page=2");
+    let head = "GET /feed/?page=2 HTTP/1.1
+Host: 127.0.0.1:12345
+X-Forwarded-Proto: https
+X-Forwarded-Host: example.com
+X-Forwarded-Prefix: /any2feed";
+    // Без доверия к прокси заголовки игнорируются
+    let url = request_url(head, ServerConfig::default());
+    assert_eq!(url, "http://127.0.0.1:12345/feed/?page=2");
+    let config = ServerConfig {
+        trust_forwarded: true,
+        ..ServerConfig::default()
+    };
+    let url = request_url(head, config);
+    assert_eq!(url, "https://example.com/any2feed/feed/?page=2");
+}
+
+#[test]
+fn url_public_url() {
+    let config = ServerConfig {
+        public_url: Some(Url::parse("https://example.com/feeds/").unwrap()),
+        ..ServerConfig::default()
+    };
+    // Заголовки игнорируются
+    let url = request_url(
+        "GET /feed/ HTTP/1.1\nHost: 127.0.0.1:12345\nX-Forwarded-Host: evil.com",
+        config,
+    );
+    assert_eq!(url, "https://example.com/feeds/feed/");
+}
+
+fn read_body(head: &str, body: &str) -> Option<String> {
+    let lines: Vec<String> = head.lines().map(|l| l.into()).collect();
+    let mut request = HTTPRequest::parse(&lines).unwrap();
+    request.read_body(&mut Cursor::new(body)).unwrap();
+    request.body
+}
+
+#[test]
+fn read_body_content_length() {
+    let body = read_body("POST / HTTP/1.1\nContent-Length: 5", "hello, next request");
+    assert_eq!(body, Some("hello".to_string()));
+    assert_eq!(read_body("GET / HTTP/1.1", ""), None);
+}
+
+#[test]
+fn read_body_chunked() {
+    let body = read_body(
+        "POST / HTTP/1.1\nTransfer-Encoding: chunked",
+        "4\r\nWiki\r\n6;ext=1\r\npedia \r\nE\r\nin \r\n\r\nchunks.\r\n0\r\nTrailer: foo\r\n\r\n",
+    );
+    assert_eq!(body, Some("Wikipedia in \r\n\r\nchunks.".to_string()));
+}
+
+#[test]
+fn read_body_invalid() {
+    let lines: Vec<String> = vec!["POST / HTTP/1.1".into(), "Content-Length: 10".into()];
+    let mut r = HTTPRequest::parse(&lines).unwrap();
+    assert!(r.read_body(&mut Cursor::new("short")).is_err());
+
+    let lines: Vec<String> = vec![
+        "POST / HTTP/1.1".into(),
+        "Transfer-Encoding: chunked".into(),
+    ];
+    let mut r = HTTPRequest::parse(&lines).unwrap();
+    assert!(r.read_body(&mut Cursor::new("zz\r\n")).is_err());
+}
@@ -1,5 +1,7 @@
 use bytes::Bytes;
-use http_server::HTTPResponse;
+use http_server::{ContentStream, HTTPRequest, HTTPResponse};
+use std::io::Cursor;
+use std::time::{Duration, UNIX_EPOCH};
 
 #[test]
 fn test_response() {
@@ -14,3 +16,126 @@ fn test_response() {
     //
     // "#);
 }
+
+fn request(headers: &[&str]) -> HTTPRequest<'static> {
+    let mut r = HTTPRequest::default();
+    for h in headers {
+        let (k, v) = h.split_once(": ").unwrap();
+        r.headers.insert(k.to_string(), v.to_string());
+    }
+    r
+}
+
+#[test]
+fn test_conditional_etag() {
+    let r = HTTPResponse::with_content("OK").conditional(&request(&[]));
+    assert_eq!(r.status, 200);
+    let etag = r.header("ETag").unwrap().to_string();
+
+    let if_none_match = format!("If-None-Match: \"foo\", {etag}");
+    let r = HTTPResponse::with_content("OK")
+        .set_content_type("text/xml")
+        .conditional(&request(&[if_none_match.as_str()]));
+    assert_eq!(r.status, 304);
+    assert_eq!(r.content, None);
+    assert_eq!(r.header("etag"), Some(etag.as_str()));
+
+    let r = HTTPResponse::with_content("Changed").conditional(&request(&[if_none_match.as_str()]));
+    assert_eq!(r.status, 200);
+
+    let r = HTTPResponse::with_content("OK").conditional(&request(&["If-None-Match: *"]));
+    assert_eq!(r.status, 304);
+}
+
+#[test]
+fn test_conditional_last_modified() {
+    let modified = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    let response = || HTTPResponse::with_content("OK").set_last_modified(modified);
+    assert_eq!(
+        response().header("Last-Modified"),
+        Some("Sun, 09 Sep 2001 01:46:40 GMT")
+    );
+
+    let r = response().conditional(&request(&[
+        "If-Modified-Since: Sun, 09 Sep 2001 01:46:40 GMT",
+    ]));
+    assert_eq!(r.status, 304);
+    assert_eq!(
+        r.header("Last-Modified"),
+        Some("Sun, 09 Sep 2001 01:46:40 GMT")
+    );
+
+    let r = response().conditional(&request(&[
+        "If-Modified-Since: Sun, 09 Sep 2001 01:46:39 GMT",
+    ]));
+    assert_eq!(r.status, 200);
+
+    // If-None-Match has precedence
+    let r = response().conditional(&request(&[
+        "If-Modified-Since: Sun, 09 Sep 2001 01:46:40 GMT",
+        "If-None-Match: \"foo\"",
+    ]));
+    assert_eq!(r.status, 200);
+}
+
+#[test]
+fn test_conditional_only_ok() {
+    let mut r = HTTPResponse::with_content("OK");
+    r.status = 206;
+    let r = r.conditional(&request(&["If-None-Match: *"]));
+    assert_eq!(r.status, 206);
+}
+
+#[test]
+fn test_stream_chunked() {
+    let mut stream = ContentStream::new(Cursor::new("foo bar"), None);
+    let mut out: Vec<u8> = Vec::new();
+    assert_eq!(stream.write_to(&mut out).unwrap(), 7);
+    assert_eq!(out, b"7\r\nfoo bar\r\n0\r\n\r\n");
+}
+
+#[test]
+fn test_stream_length() {
+    let mut stream = ContentStream::new(Cursor::new("foo bar"), Some(3));
+    let mut out: Vec<u8> = Vec::new();
+    assert_eq!(stream.write_to(&mut out).unwrap(), 3);
+    assert_eq!(out, b"foo");
+
+    // Upstream closed early
+    let mut stream = ContentStream::new(Cursor::new("foo"), Some(10));
+    assert!(stream.write_to(&mut Vec::new()).is_err());
+}
+
+#[test]
+fn test_stream_until_close() {
+    let mut r = HTTPResponse::with_stream(Cursor::new("foo bar"), None);
+    r.content_stream.as_mut().unwrap().until_close = true;
+    let head = r.to_string();
+    assert!(!head.contains("Transfer-Encoding"));
+    assert!(!head.contains("Content-Length"));
+    let mut out: Vec<u8> = Vec::new();
+    let written = r.content_stream.unwrap().write_to(&mut out).unwrap();
+    assert_eq!(written, 7);
+    assert_eq!(out, b"foo bar");
+}
+
+#[test]
+fn test_stream_response() {
+    let r = HTTPResponse::with_stream(Cursor::new("foo"), None).set_content_type("video/mp4");
+    let head = r.to_string();
+    assert!(head.contains("Transfer-Encoding: chunked"));
+    assert!(!head.contains("Content-Length"));
+    // Not compressed and not ranged
+    let mut req = request(&["Accept-Encoding: gzip", "Range: bytes=0-1"]);
+    req.version = "HTTP/1.1".to_string();
+    let r = r
+        .set_header("Accept-Ranges", "bytes")
+        .compress(&req)
+        .range(&req);
+    assert_eq!(r.status, 200);
+    assert_eq!(r.header("Content-Encoding"), None);
+
+    let r = r.buffer_stream().unwrap();
+    assert_eq!(r.content, Some(Bytes::from("foo")));
+    assert!(r.to_string().contains("Content-Length: 3"));
+}
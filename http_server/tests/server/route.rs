@@ -0,0 +1,108 @@
+use crate::keep_alive::spawn_server;
+use http_server::{HTTPError, HTTPMethod, HTTPResponse, Route, ServerConfig};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpStream};
+
+fn start_server() -> SocketAddr {
+    let config = ServerConfig {
+        port: Some(0),
+        routes: vec![
+            Route::new("/feed/{slug}/{page:\\d+}/", |r| {
+                let slug: String = r.path_param("slug")?;
+                let page: usize = r.path_param("page")?;
+                let limit: Option<usize> = r.query_param("limit")?;
+                Ok(HTTPResponse::with_content(
+                    format!("{slug} {page} {limit:?}").as_str(),
+                ))
+            }),
+            Route::new("/item/", |_| Ok(HTTPResponse::with_content("get"))),
+            Route::new("/upstream/", |_| {
+                Err(HTTPError::bad_gateway("Upstream unavailable").with_cause("refused"))
+            }),
+            Route::new("/item/", |_| Ok(HTTPResponse::with_content("post")))
+                .with_methods(&[HTTPMethod::POST, HTTPMethod::DELETE]),
+        ],
+        ..ServerConfig::default()
+    };
+    spawn_server(config)
+}
+
+fn request(addr: SocketAddr, method: &str, path: &str) -> (Vec<String>, String) {
+    crate::keep_alive::request(
+        addr,
+        format!("{method} {path} HTTP/1.0\r\nContent-Length: 0\r\n\r\n").as_str(),
+    )
+}
+
+#[test]
+fn route_path_params() {
+    let addr = start_server();
+    let (head, body) = request(addr, "GET", "/feed/foo/2/?limit=10");
+    assert_eq!(head[0], "HTTP/1.1 200");
+    assert_eq!(body, "foo 2 Some(10)");
+    let (_, body) = request(addr, "GET", "/feed/foo/2/");
+    assert_eq!(body, "foo 2 None");
+    // Invalid query param is bad request, not panic
+    let (head, body) = request(addr, "GET", "/feed/foo/2/?limit=ten");
+    assert_eq!(head[0], "HTTP/1.1 400");
+    assert_eq!(body, "400 Bad Request\n");
+    // Placeholder regex not matched
+    let (head, _) = request(addr, "GET", "/feed/foo/bar/");
+    assert_eq!(head[0], "HTTP/1.1 404");
+}
+
+#[test]
+fn route_methods() {
+    let addr = start_server();
+    let (head, body) = request(addr, "GET", "/item/");
+    assert_eq!(head[0], "HTTP/1.1 200");
+    assert_eq!(body, "get");
+    let (_, body) = request(addr, "POST", "/item/");
+    assert_eq!(body, "post");
+
+    let (head, _) = request(addr, "PUT", "/item/");
+    assert_eq!(head[0], "HTTP/1.1 405");
+    assert!(head.iter().any(|h| h == "Allow: HEAD, GET, POST, DELETE"));
+    let (head, _) = request(addr, "POST", "/feed/foo/1/");
+    assert_eq!(head[0], "HTTP/1.1 405");
+    assert!(head.iter().any(|h| h == "Allow: HEAD, GET"));
+}
+
+#[test]
+fn route_head() {
+    let addr = start_server();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"HEAD /feed/foo/2/ HTTP/1.0\r\n\r\n")
+        .unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut head: Vec<String> = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim_end().is_empty() {
+            break;
+        }
+        head.push(line.trim_end().to_string());
+    }
+    assert_eq!(head[0], "HTTP/1.1 200");
+    // Same length as GET, without body
+    assert!(head.iter().any(|h| h == "Content-Length: 10"));
+    let mut rest = Vec::new();
+    reader.read_to_end(&mut rest).unwrap();
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn route_error() {
+    let addr = start_server();
+    let (head, body) = request(addr, "GET", "/upstream/");
+    assert_eq!(head[0], "HTTP/1.1 502");
+    assert!(head
+        .iter()
+        .any(|h| h == "Content-Type: text/plain; charset=utf-8"));
+    // Cause only in log
+    assert_eq!(body, "502 Bad Gateway: Upstream unavailable\n");
+    let (_, body) = request(addr, "GET", "/not_exists/");
+    assert_eq!(body, "404 Not Found\n");
+}
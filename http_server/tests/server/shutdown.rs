@@ -0,0 +1,72 @@
+use crate::keep_alive::read_response;
+use http_server::{HTTPResponse, Route, Server, ServerConfig};
+use std::io::{BufReader, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn slow_server() -> Server {
+    let config = ServerConfig {
+        port: Some(0),
+        threads: Some(2),
+        routes: vec![Route::new("/slow", |_| {
+            thread::sleep(Duration::from_millis(300));
+            Ok(HTTPResponse::with_content("done"))
+        })],
+        ..ServerConfig::default()
+    };
+    Server::bind(config).unwrap()
+}
+
+#[test]
+fn shutdown_finish_in_flight() {
+    let server = slow_server();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let serving = thread::spawn(move || server.serve());
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(b"GET /slow HTTP/1.1\r\n\r\n").unwrap();
+    // Запрос уже в работе
+    thread::sleep(Duration::from_millis(100));
+    assert!(handle.shutdown());
+    assert!(!handle.shutdown());
+
+    let mut reader = BufReader::new(stream);
+    let (head, body) = read_response(&mut reader);
+    assert_eq!(head[0], "HTTP/1.1 200");
+    // No more requests on this connection
+    assert!(head.contains(&"Connection: close".to_string()));
+    assert_eq!(body, "done");
+
+    serving.join().unwrap().unwrap();
+    assert!(TcpStream::connect(addr).is_err());
+}
+
+#[test]
+fn shutdown_timeout() {
+    let mut config = ServerConfig {
+        port: Some(0),
+        threads: Some(1),
+        shutdown_timeout: Some(0),
+        ..ServerConfig::default()
+    };
+    config.routes.push(Route::new("/hang", |_| {
+        thread::sleep(Duration::from_secs(5));
+        Ok(HTTPResponse::new(200))
+    }));
+    let server = Server::bind(config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let serving = thread::spawn(move || server.serve());
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(b"GET /hang HTTP/1.1\r\n\r\n").unwrap();
+    thread::sleep(Duration::from_millis(100));
+
+    let start = Instant::now();
+    handle.shutdown();
+    serving.join().unwrap().unwrap();
+    // Не ждем зависший запрос
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
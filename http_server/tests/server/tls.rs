@@ -0,0 +1,95 @@
+use crate::keep_alive::read_response;
+use http_server::{HTTPResponse, Route, Server, ServerConfig, TlsConfig};
+use rustls::{Certificate, ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+use std::fs;
+use std::io::{BufReader, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+fn client_config(cert: &str) -> Arc<ClientConfig> {
+    let pem = fs::read(fixture(cert)).unwrap();
+    let mut roots = RootCertStore::empty();
+    for der in rustls_pemfile::certs(&mut pem.as_slice()).unwrap() {
+        roots.add(&Certificate(der)).unwrap();
+    }
+    Arc::new(
+        ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    )
+}
+
+/// GET over https, trust only `cert`
+fn get(addr: SocketAddr, cert: &str) -> std::io::Result<(Vec<String>, String)> {
+    let connection =
+        ClientConnection::new(client_config(cert), "localhost".try_into().unwrap()).unwrap();
+    let socket = TcpStream::connect(addr)?;
+    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut stream = StreamOwned::new(connection, socket);
+    stream.write_all(b"GET /url HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")?;
+    stream.flush()?;
+    Ok(read_response(&mut BufReader::new(stream)))
+}
+
+#[test]
+fn tls_reload() {
+    let dir = std::env::temp_dir().join("http_server_test_tls");
+    fs::create_dir_all(&dir).unwrap();
+    let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+    fs::copy(fixture("cert_a.pem"), &cert).unwrap();
+    fs::copy(fixture("key_a.pem"), &key).unwrap();
+
+    let config = ServerConfig {
+        port: Some(0),
+        tls: Some(TlsConfig {
+            cert: cert.clone(),
+            key: key.clone(),
+        }),
+        routes: vec![Route::new("/url", |r| {
+            Ok(HTTPResponse::with_content(r.url().as_str()))
+        })],
+        ..ServerConfig::default()
+    };
+    let server = Server::bind(config).unwrap();
+    let addr = server.local_addr().unwrap();
+    let handle = server.shutdown_handle();
+    let serving = thread::spawn(move || server.serve());
+
+    let (head, body) = get(addr, "cert_a.pem").unwrap();
+    assert_eq!(head[0], "HTTP/1.1 200");
+    assert_eq!(body, "https://localhost/url");
+    assert!(get(addr, "cert_b.pem").is_err());
+
+    // Новый сертификат без перезапуска
+    fs::copy(fixture("cert_b.pem"), &cert).unwrap();
+    fs::copy(fixture("key_b.pem"), &key).unwrap();
+    let (_, body) = get(addr, "cert_b.pem").unwrap();
+    assert_eq!(body, "https://localhost/url");
+    assert!(get(addr, "cert_a.pem").is_err());
+
+    handle.shutdown();
+    serving.join().unwrap().unwrap();
+}
+
+#[test]
+fn tls_invalid_cert() {
+    let config = ServerConfig {
+        port: Some(0),
+        tls: Some(TlsConfig {
+            cert: fixture("key_a.pem"),
+            key: fixture("key_a.pem"),
+        }),
+        ..ServerConfig::default()
+    };
+    assert!(Server::bind(config).is_err());
+}
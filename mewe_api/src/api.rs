@@ -1,11 +1,14 @@
-use std::borrow::Borrow;
 use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
 use reqwest::blocking::Response;
+use reqwest::cookie::Cookie;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::StatusCode;
 use reqwest::{cookie::Jar, Url};
+use serde::de::DeserializeOwned;
 
 use reqwest_mozilla_cookie::{import_cookie_from_file, update_cookie_from_file};
 
@@ -13,135 +16,223 @@ use crate::json::MeweApiFeedListNextPageLink;
 use crate::utils::update_query;
 use crate::{json, MeweApiError};
 
-const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/110.0.0.0 Safari/537.36";
+pub(crate) const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/110.0.0.0 Safari/537.36";
+pub(crate) const TIMEOUT: Duration = Duration::from_secs(5);
+/// Pause between pages of feed
+pub(crate) const PAGE_DELAY: Duration = Duration::from_millis(100);
+pub(crate) const CONTACT_PAGES: usize = 20;
+pub(crate) const CONTACT_PAGE_LIMIT: usize = 21;
 
 macro_rules! api_mewe {
     () => {
         "https://mewe.com/api"
     };
 }
-const API_MEWE_IDENTIFY: &str = concat!(api_mewe!(), "/v3/auth/identify");
-const API_MEWE_ME_INFO: &str = concat!(api_mewe!(), "/v2/me/info");
+pub(crate) const API_MEWE_IDENTIFY: &str = concat!(api_mewe!(), "/v3/auth/identify");
+pub(crate) const API_MEWE_ME_INFO: &str = concat!(api_mewe!(), "/v2/me/info");
 // const API_MEWE_USER_INFO: &str = concat!(api_mewe!(), "/v2/mycontacts/user/");
 
 pub const API_MEWE_ALLFEED: &str = concat!(api_mewe!(), "/v2/home/allfeed");
 pub const API_MEWE_USER_FEED: &str = concat!(api_mewe!(), "/v2/home/user/{user_id}/postsfeed");
 pub const API_MEWE_GROUP_FEED: &str = concat!(api_mewe!(), "/v3/group/{group_id}/postsfeed");
 
-const API_MEWE_CONTACT_INFO: &str =
+pub(crate) const API_MEWE_CONTACT_INFO: &str =
     concat!(api_mewe!(), "/v2/mycontacts/user?inviteId={invite_id}");
-const API_MEWE_CONTACTS_ALL: &str = concat!(api_mewe!(), "/v2/mycontacts/all");
-const API_MEWE_CONTACTS_FAVORITES: &str = concat!(api_mewe!(), "/v2/mycontacts/closefriends");
-const API_MEWE_GROUPS: &str = concat!(api_mewe!(), "/v2/groups");
-const API_MEWE_GROUP_INFO: &str = concat!(api_mewe!(), "/v2/group/{group_id}");
+pub(crate) const API_MEWE_CONTACTS_ALL: &str = concat!(api_mewe!(), "/v2/mycontacts/all");
+pub(crate) const API_MEWE_CONTACTS_FAVORITES: &str =
+    concat!(api_mewe!(), "/v2/mycontacts/closefriends");
+pub(crate) const API_MEWE_GROUPS: &str = concat!(api_mewe!(), "/v2/groups");
+pub(crate) const API_MEWE_GROUP_INFO: &str = concat!(api_mewe!(), "/v2/group/{group_id}");
+
+/// Cookies and csrf token, shared by blocking and async clients
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Session {
+    cookies_path: String,
+    pub(crate) cookies: Arc<Jar>,
+    headers: Arc<Mutex<HashMap<String, String>>>,
+}
+
+impl Session {
+    pub(crate) fn new(cookies_path: &str) -> crate::Result<Session> {
+        let jar = import_cookie_from_file(&cookies_path.to_string())?;
+        Ok(Session {
+            cookies_path: cookies_path.to_string(),
+            cookies: Arc::new(jar),
+            ..Session::default()
+        })
+    }
+
+    /// Session headers with extra ones, e.g. `Range` for media
+    pub(crate) fn headers(&self, extra: &[(&str, &str)]) -> HeaderMap {
+        let headers = self.headers.lock().unwrap();
+        let session = headers.iter().map(|(k, v)| (k.as_str(), v.as_str()));
+        session
+            .chain(extra.iter().copied())
+            .filter_map(|(k, v)| {
+                Some((
+                    HeaderName::from_bytes(k.as_bytes()).ok()?,
+                    HeaderValue::from_str(v).ok()?,
+                ))
+            })
+            .collect()
+    }
+
+    /// Remember csrf token from response cookies, save cookies file if any cookie was set
+    pub(crate) fn update<'a>(&self, url: &str, cookies: impl Iterator<Item = Cookie<'a>>) {
+        let cookies: HashMap<String, String> = cookies
+            .map(|c| (c.name().to_lowercase(), c.value().to_string()))
+            .collect();
+        if let Some(csrf_token) = cookies.get("csrf-token") {
+            self.headers
+                .lock()
+                .unwrap()
+                .insert("x-csrf-token".to_string(), csrf_token.clone());
+        }
+        if !cookies.is_empty() {
+            // Если был какой либо set-cookie, сохраняем актуальный стор
+            if let Ok(url) = Url::parse(url) {
+                update_cookie_from_file(&self.cookies, &url, &self.cookies_path);
+            }
+        }
+    }
+}
+
+/// Error of unsuccessful response
+pub(crate) fn status_error(status: StatusCode, text: reqwest::Result<String>) -> MeweApiError {
+    log::error!("API ERROR! status={:?} text={:?}", status, text);
+    MeweApiError::ApiError {
+        kind: crate::ApiErrorKind::StatusError(status.as_u16()),
+    }
+}
+
+pub(crate) fn parse_json<T: DeserializeOwned>(body: &[u8]) -> crate::Result<T> {
+    serde_json::from_slice(body).map_err(|e| {
+        log::error!("API json FAIL: {:?}", e);
+        MeweApiError::from(e)
+    })
+}
+
+pub(crate) fn identify_result(json: json::MeweApiIdentify) -> crate::Result<bool> {
+    if json.authenticated {
+        log::debug!("identify {:?}", json);
+        Ok(true)
+    } else {
+        log::error!("FAIL identify {:?}", json);
+        Err(MeweApiError::ApiError {
+            kind: crate::ApiErrorKind::IdentifyFail,
+        })
+    }
+}
+
+pub(crate) fn feed_page_url(url: &str, limit: Option<usize>) -> Url {
+    let mut url = Url::parse(url).unwrap();
+    if let Some(limit) = limit {
+        let limit = limit.to_string();
+        let query = HashMap::from([("limit", limit.as_str())]);
+        update_query(&mut url, &query);
+    }
+    url
+}
+
+/// Url of next page, fills users and groups of posts
+pub(crate) fn next_feed_page(json: &mut json::MeweApiFeedList) -> Option<String> {
+    // Перераскидываем поля
+    json.fill_user_and_group();
+    match &json.links {
+        Some(MeweApiFeedListNextPageLink {
+            next_page: Some(page),
+        }) => Some(["https://mewe.com/", page.href.as_str()].join("")),
+        _ => None,
+    }
+}
+
+pub(crate) fn contact_page_url(url: &str, limit: usize, offset: Option<usize>) -> Url {
+    let mut url = Url::parse(url).unwrap();
+    url.query_pairs_mut()
+        .append_pair("maxResults", limit.to_string().as_str());
+    if let Some(offset) = offset {
+        url.query_pairs_mut()
+            .append_pair("offset", offset.to_string().as_str());
+    }
+    url
+}
+
+pub(crate) fn contacts_url(favorites: bool) -> &'static str {
+    if favorites {
+        API_MEWE_CONTACTS_FAVORITES
+    } else {
+        API_MEWE_CONTACTS_ALL
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct MeweApi {
-    cookies_path: String,
-    cookies: Arc<Jar>,
-    session: reqwest::blocking::Client,
-    headers: Arc<Mutex<HashMap<String, String>>>,
+    session: Session,
+    client: reqwest::blocking::Client,
     pub me_info: Option<json::MeweApiSelfProfileInfo>,
 }
 
 /// Подсматриваем туда https://github.com/goutsune/mewe-wrapper
 impl MeweApi {
     pub fn new(cookies_path: &str) -> crate::Result<MeweApi> {
-        let cookies_path = cookies_path.to_string();
-        let jar = import_cookie_from_file(&cookies_path)?;
-        let jar = Arc::new(jar);
-        let session = reqwest::blocking::Client::builder()
+        let session = Session::new(cookies_path)?;
+        let client = reqwest::blocking::Client::builder()
             .user_agent(USER_AGENT)
-            .cookie_provider(Arc::clone(&jar))
-            .timeout(Some(Duration::from_secs(5)))
+            .cookie_provider(Arc::clone(&session.cookies))
+            .timeout(Some(TIMEOUT))
             .build()
             .unwrap();
         let mut mewe_api = MeweApi {
-            cookies_path,
-            cookies: Arc::clone(&jar),
             session,
-            ..MeweApi::default()
+            client,
+            me_info: None,
         };
         mewe_api.identify()?;
         mewe_api.whoami()?;
         Ok(mewe_api)
     }
 
-    fn save_cookies(&self, scope_url: &str) -> Option<()> {
-        update_cookie_from_file(
-            self.cookies.borrow(),
-            &Url::parse(scope_url).ok()?,
-            &self.cookies_path,
-        )
+    /// Session of client, for [`crate::AsyncMeweApi`] sharing it
+    #[cfg(feature = "async")]
+    pub(crate) fn session(&self) -> &Session {
+        &self.session
     }
 
     pub fn get(&self, url: &str) -> crate::Result<Response> {
-        log::debug!("API get: url={:?}", url);
-        let mut rb = self.session.get(url);
-
-        for (k, v) in self.headers.lock().unwrap().iter() {
-            rb = rb.header(k, v);
-        }
-        let result = rb.send();
-        let Ok(result) = result else {
-            let err = result.unwrap_err();
-            log::error!("API GET FAIL: {:?}", err);
-            return Err(MeweApiError::from(err));
-        };
-        let cookies: HashMap<String, String> = result
-            .cookies()
-            .map(|c| (c.name().to_lowercase(), c.value().to_string()))
-            .collect();
-        let cookies_len = cookies.len();
-        let csrf_token: String = cookies
-            .into_iter()
-            .filter(|(n, _v)| n == "csrf-token")
-            .map(|(_, v)| v)
-            .take(1)
-            .collect();
+        self.get_with_headers(url, &[])
+    }
 
-        if !csrf_token.is_empty() {
-            self.headers
-                .lock()
-                .unwrap()
-                .insert("x-csrf-token".to_string(), csrf_token);
-        }
-        if cookies_len > 0 {
-            // Если был какой либо set-cookie, сохраняем актуальный стор
-            self.save_cookies(url);
-        }
-        if result.status() == 200 {
+    /// GET with extra headers, e.g. `Range` for media
+    pub fn get_with_headers(&self, url: &str, headers: &[(&str, &str)]) -> crate::Result<Response> {
+        log::debug!("API get: url={:?}", url);
+        let result = self
+            .client
+            .get(url)
+            .headers(self.session.headers(headers))
+            .send()
+            .inspect_err(|err| log::error!("API GET FAIL: {:?}", err))?;
+        self.session.update(url, result.cookies());
+        if result.status().is_success() {
             log::trace!("API get result={:?}", result);
             Ok(result)
         } else {
-            log::error!("API ERROR! result={:?}", &result);
-            log::error!("text={:?}", &result.text());
-            Err(crate::MeweApiError::ApiError {
-                kind: crate::ApiErrorKind::StatusError,
-            })
+            Err(status_error(result.status(), result.text()))
         }
     }
 
+    fn get_json<T: DeserializeOwned>(&self, url: &str) -> crate::Result<T> {
+        parse_json(&self.get(url)?.bytes()?)
+    }
+
     pub fn whoami(&mut self) -> crate::Result<json::MeweApiSelfProfileInfo> {
-        let info: json::MeweApiSelfProfileInfo = self.get(API_MEWE_ME_INFO)?.json()?;
+        let info: json::MeweApiSelfProfileInfo = self.get_json(API_MEWE_ME_INFO)?;
         self.me_info = Some(info.clone());
         log::debug!("whoami: {:?}", info);
         Ok(info)
     }
 
     pub fn identify(&self) -> crate::Result<bool> {
-        let json = self
-            .get(API_MEWE_IDENTIFY)?
-            .json::<json::MeweApiIdentify>()?;
-        if json.authenticated {
-            log::debug!("identify {:?}", json);
-            Ok(true)
-        } else {
-            log::error!("FAIL identify {:?}", json);
-            Err(crate::MeweApiError::ApiError {
-                kind: crate::ApiErrorKind::IdentifyFail,
-            })
-        }
+        identify_result(self.get_json(API_MEWE_IDENTIFY)?)
     }
 
     pub fn fetch_feed(
@@ -150,14 +241,7 @@ impl MeweApi {
         limit: Option<usize>,
     ) -> crate::Result<json::MeweApiFeedList> {
         log::debug!("fetch_feed: url={:?} limit={:?}", url, limit);
-        let mut url = Url::parse(url).unwrap();
-        if let Some(limit) = limit {
-            let limit = limit.to_string();
-            let query = HashMap::from([("limit", limit.as_str())]);
-            update_query(&mut url, &query);
-        }
-        let response = self.get(url.as_str())?;
-        Ok(response.json::<json::MeweApiFeedList>().unwrap())
+        self.get_json(feed_page_url(url, limit).as_str())
     }
 
     // Todo iterator
@@ -181,17 +265,12 @@ impl MeweApi {
         for i in 0..pages {
             if i > 0 {
                 // Не дрочим
-                thread::sleep(Duration::from_millis(100));
+                thread::sleep(PAGE_DELAY);
             }
             let mut json = self.fetch_feed(next_page.as_str(), limit)?;
-            if let Some(MeweApiFeedListNextPageLink {
-                next_page: Some(page),
-            }) = &json.links
-            {
-                next_page = ["https://mewe.com/", page.href.as_str()].join("");
+            if let Some(page) = next_feed_page(&mut json) {
+                next_page = page;
             }
-            // Перераскидываем поля
-            json.fill_user_and_group();
             result.push(json)
         }
 
@@ -247,22 +326,21 @@ impl MeweApi {
 
     pub fn fetch_groups(&self) -> crate::Result<json::MeweApiGroupList> {
         log::debug!("fetch_groups");
-        let response = self.get(API_MEWE_GROUPS)?;
-        Ok(response.json::<json::MeweApiGroupList>().unwrap())
+        self.get_json(API_MEWE_GROUPS)
     }
 
     pub fn fetch_group_info(&self, group_id: &str) -> crate::Result<json::MeweApiGroup> {
         log::debug!("fetch_group_info {:?}", group_id);
-        let url = API_MEWE_GROUP_INFO.replace("{group_id}", group_id);
-        let response = self.get(url.as_str())?;
-        Ok(response.json::<json::MeweApiGroup>().unwrap())
+        self.get_json(API_MEWE_GROUP_INFO.replace("{group_id}", group_id).as_str())
     }
 
     pub fn fetch_contact_info(&self, invite_id: &str) -> crate::Result<json::MeweApiContactUser> {
         log::debug!("fetch_contact_info {:?}", invite_id);
-        let url = API_MEWE_CONTACT_INFO.replace("{invite_id}", invite_id);
-        let response = self.get(url.as_str())?;
-        Ok(response.json::<json::MeweApiContactUser>().unwrap())
+        self.get_json(
+            API_MEWE_CONTACT_INFO
+                .replace("{invite_id}", invite_id)
+                .as_str(),
+        )
     }
 
     pub fn fetch_contact_page(
@@ -277,15 +355,8 @@ impl MeweApi {
             limit,
             offset
         );
-        let mut url = Url::parse(url).unwrap();
-        url.query_pairs_mut()
-            .append_pair("maxResults", limit.to_string().as_str());
-        if let Some(offset) = offset {
-            url.query_pairs_mut()
-                .append_pair("offset", offset.to_string().as_str());
-        }
-        let response = self.get(url.as_str())?;
-        let json = response.json::<json::MeweApiContactList>().unwrap();
+        let json: json::MeweApiContactList =
+            self.get_json(contact_page_url(url, limit, offset).as_str())?;
         log::trace!("fetch_contact_page result: {:?}", json);
         Ok(json)
     }
@@ -302,8 +373,8 @@ impl MeweApi {
             limit,
             pages
         );
-        let pages = pages.unwrap_or(20);
-        let limit = limit.unwrap_or(21);
+        let pages = pages.unwrap_or(CONTACT_PAGES);
+        let limit = limit.unwrap_or(CONTACT_PAGE_LIMIT);
         let mut res: Vec<json::MeweApiContactUser> = Vec::with_capacity(limit * pages);
         for i in 0..pages {
             let offset = if i == 0 { None } else { Some(i * limit) };
@@ -319,12 +390,7 @@ impl MeweApi {
 
     pub fn get_contacts(&self, favorites: bool) -> crate::Result<Vec<json::MeweApiContactUser>> {
         log::debug!("get_contacts: favorites={:?}", favorites);
-        let url = if favorites {
-            API_MEWE_CONTACTS_FAVORITES
-        } else {
-            API_MEWE_CONTACTS_ALL
-        };
-        self.fetch_contacts(url, None, None)
+        self.fetch_contacts(contacts_url(favorites), None, None)
     }
 }
 
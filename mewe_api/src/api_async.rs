@@ -0,0 +1,268 @@
+use std::sync::Arc;
+
+use reqwest::Response;
+use serde::de::DeserializeOwned;
+
+use crate::api::{
+    contact_page_url, contacts_url, feed_page_url, identify_result, next_feed_page, parse_json,
+    status_error, Session, API_MEWE_ALLFEED, API_MEWE_CONTACT_INFO, API_MEWE_GROUPS,
+    API_MEWE_GROUP_FEED, API_MEWE_GROUP_INFO, API_MEWE_IDENTIFY, API_MEWE_ME_INFO,
+    API_MEWE_USER_FEED, CONTACT_PAGES, CONTACT_PAGE_LIMIT, PAGE_DELAY, TIMEOUT, USER_AGENT,
+};
+use crate::{json, MeweApi};
+
+/// Async variant of [`crate::MeweApi`], clones share session and cookies
+#[derive(Debug, Clone)]
+pub struct AsyncMeweApi {
+    session: Session,
+    client: reqwest::Client,
+    pub me_info: Option<json::MeweApiSelfProfileInfo>,
+}
+
+impl AsyncMeweApi {
+    pub async fn new(cookies_path: &str) -> crate::Result<AsyncMeweApi> {
+        let mut mewe_api = AsyncMeweApi::with_session(Session::new(cookies_path)?, None);
+        mewe_api.identify().await?;
+        mewe_api.whoami().await?;
+        Ok(mewe_api)
+    }
+
+    /// Client with session of already identified blocking client, without runtime
+    pub fn from_blocking(api: &MeweApi) -> AsyncMeweApi {
+        AsyncMeweApi::with_session(api.session().clone(), api.me_info.clone())
+    }
+
+    fn with_session(session: Session, me_info: Option<json::MeweApiSelfProfileInfo>) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .cookie_provider(Arc::clone(&session.cookies))
+            .timeout(TIMEOUT)
+            .build()
+            .unwrap();
+        AsyncMeweApi {
+            session,
+            client,
+            me_info,
+        }
+    }
+
+    pub async fn get(&self, url: &str) -> crate::Result<Response> {
+        self.get_with_headers(url, &[]).await
+    }
+
+    /// GET with extra headers, e.g. `Range` for media
+    pub async fn get_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+    ) -> crate::Result<Response> {
+        log::debug!("API get: url={:?}", url);
+        let result = self
+            .client
+            .get(url)
+            .headers(self.session.headers(headers))
+            .send()
+            .await
+            .inspect_err(|err| log::error!("API GET FAIL: {:?}", err))?;
+        self.session.update(url, result.cookies());
+        if result.status().is_success() {
+            log::trace!("API get result={:?}", result);
+            Ok(result)
+        } else {
+            let status = result.status();
+            Err(status_error(status, result.text().await))
+        }
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> crate::Result<T> {
+        parse_json(&self.get(url).await?.bytes().await?)
+    }
+
+    pub async fn whoami(&mut self) -> crate::Result<json::MeweApiSelfProfileInfo> {
+        let info: json::MeweApiSelfProfileInfo = self.get_json(API_MEWE_ME_INFO).await?;
+        self.me_info = Some(info.clone());
+        log::debug!("whoami: {:?}", info);
+        Ok(info)
+    }
+
+    pub async fn identify(&self) -> crate::Result<bool> {
+        identify_result(self.get_json(API_MEWE_IDENTIFY).await?)
+    }
+
+    pub async fn fetch_feed(
+        &self,
+        url: &str,
+        limit: Option<usize>,
+    ) -> crate::Result<json::MeweApiFeedList> {
+        log::debug!("fetch_feed: url={:?} limit={:?}", url, limit);
+        self.get_json(feed_page_url(url, limit).as_str()).await
+    }
+
+    pub async fn fetch_feeds(
+        &self,
+        url: &str,
+        limit: Option<usize>,
+        pages: Option<usize>,
+    ) -> crate::Result<Vec<json::MeweApiFeedList>> {
+        log::debug!(
+            "fetch_feeds: url={:?} limit={:?} pages={:?}",
+            url,
+            limit,
+            pages
+        );
+        self.identify().await?;
+        let pages = pages.unwrap_or(1);
+
+        let mut result: Vec<json::MeweApiFeedList> = Vec::with_capacity(pages);
+        let mut next_page = url.to_string();
+        for i in 0..pages {
+            if i > 0 {
+                tokio::time::sleep(PAGE_DELAY).await;
+            }
+            let mut json = self.fetch_feed(next_page.as_str(), limit).await?;
+            if let Some(page) = next_feed_page(&mut json) {
+                next_page = page;
+            }
+            result.push(json)
+        }
+
+        Ok(result)
+    }
+
+    pub async fn get_my_feeds(
+        &self,
+        limit: Option<usize>,
+        pages: Option<usize>,
+    ) -> crate::Result<Vec<json::MeweApiFeedList>> {
+        log::debug!("get_my_feeds: limit={:?} pages={:?}", limit, pages);
+        self.fetch_feeds(API_MEWE_ALLFEED, limit, pages).await
+    }
+
+    pub async fn get_user_feed(
+        &self,
+        user_id: &str,
+        limit: Option<usize>,
+        pages: Option<usize>,
+    ) -> crate::Result<Vec<json::MeweApiFeedList>> {
+        log::debug!(
+            "get_user_feed: user_id={:?} limit={:?} pages={:?}",
+            user_id,
+            limit,
+            pages
+        );
+        let url = API_MEWE_USER_FEED.replace("{user_id}", user_id);
+        self.fetch_feeds(url.as_str(), limit, pages).await
+    }
+
+    pub async fn get_group_feed(
+        &self,
+        group_id: &str,
+        limit: Option<usize>,
+        pages: Option<usize>,
+    ) -> crate::Result<Vec<json::MeweApiFeedList>> {
+        log::debug!(
+            "get_group_feed: group_id={:?} limit={:?} pages={:?}",
+            group_id,
+            limit,
+            pages
+        );
+        let url = API_MEWE_GROUP_FEED.replace("{group_id}", group_id);
+        self.fetch_feeds(url.as_str(), limit, pages).await
+    }
+
+    pub async fn fetch_groups(&self) -> crate::Result<json::MeweApiGroupList> {
+        log::debug!("fetch_groups");
+        self.get_json(API_MEWE_GROUPS).await
+    }
+
+    pub async fn fetch_group_info(&self, group_id: &str) -> crate::Result<json::MeweApiGroup> {
+        log::debug!("fetch_group_info {:?}", group_id);
+        let url = API_MEWE_GROUP_INFO.replace("{group_id}", group_id);
+        self.get_json(url.as_str()).await
+    }
+
+    pub async fn fetch_contact_info(
+        &self,
+        invite_id: &str,
+    ) -> crate::Result<json::MeweApiContactUser> {
+        log::debug!("fetch_contact_info {:?}", invite_id);
+        let url = API_MEWE_CONTACT_INFO.replace("{invite_id}", invite_id);
+        self.get_json(url.as_str()).await
+    }
+
+    pub async fn fetch_contact_page(
+        &self,
+        url: &str,
+        limit: usize,
+        offset: Option<usize>,
+    ) -> crate::Result<json::MeweApiContactList> {
+        log::debug!(
+            "fetch_contact_page: url={:?} limit={:?} offset={:?}",
+            url,
+            limit,
+            offset
+        );
+        let url = contact_page_url(url, limit, offset);
+        let json: json::MeweApiContactList = self.get_json(url.as_str()).await?;
+        log::trace!("fetch_contact_page result: {:?}", json);
+        Ok(json)
+    }
+
+    pub async fn fetch_contacts(
+        &self,
+        url: &str,
+        limit: Option<usize>,
+        pages: Option<usize>,
+    ) -> crate::Result<Vec<json::MeweApiContactUser>> {
+        log::debug!(
+            "fetch_contacts: url={:?} limit={:?} pages={:?}",
+            url,
+            limit,
+            pages
+        );
+        let pages = pages.unwrap_or(CONTACT_PAGES);
+        let limit = limit.unwrap_or(CONTACT_PAGE_LIMIT);
+        let mut res: Vec<json::MeweApiContactUser> = Vec::with_capacity(limit * pages);
+        for i in 0..pages {
+            let offset = if i == 0 { None } else { Some(i * limit) };
+            let json = self.fetch_contact_page(url, limit, offset).await?;
+            if json.contacts.is_empty() {
+                break;
+            }
+            res.extend(json.contacts.iter().map(|c| c.user.clone()));
+        }
+        res.shrink_to_fit();
+        Ok(res)
+    }
+
+    pub async fn get_contacts(
+        &self,
+        favorites: bool,
+    ) -> crate::Result<Vec<json::MeweApiContactUser>> {
+        log::debug!("get_contacts: favorites={:?}", favorites);
+        self.fetch_contacts(contacts_url(favorites), None, None)
+            .await
+    }
+}
+
+#[cfg(feature = "test_local")]
+#[cfg(test)]
+mod test {
+    use crate::AsyncMeweApi;
+
+    const COOKIE_PATH: &str = "/home/apkawa/Downloads/mewe.com_cookies.txt";
+
+    #[test]
+    fn test_async_get_feeds() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let feeds = runtime.block_on(async {
+            let mewe = AsyncMeweApi::new(COOKIE_PATH).await.unwrap();
+            mewe.get_my_feeds(Some(5), Some(2)).await.unwrap()
+        });
+        assert_eq!(feeds.len(), 2);
+        assert_eq!(feeds[0].feed.len(), 5);
+    }
+}
@@ -8,7 +8,7 @@ pub type Result<T> = std::result::Result<T, MeweApiError>;
 #[derive(Debug)]
 pub enum ApiErrorKind {
     IdentifyFail,
-    StatusError,
+    StatusError(u16),
 }
 
 #[derive(Debug)]
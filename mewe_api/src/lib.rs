@@ -1,10 +1,14 @@
 pub mod api;
+#[cfg(feature = "async")]
+pub mod api_async;
 pub mod error;
 pub mod json;
 pub mod markdown;
 pub mod utils;
 
 pub use api::MeweApi;
+#[cfg(feature = "async")]
+pub use api_async::AsyncMeweApi;
 // Reexport
 pub use error::ApiErrorKind;
 pub use error::MeweApiError;
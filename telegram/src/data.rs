@@ -1,3 +1,5 @@
+use std::collections::HashSet;
+
 #[derive(Debug, Default)]
 pub struct Channel {
     pub slug: String,
@@ -5,6 +7,8 @@ pub struct Channel {
     pub description: String,
     pub image_url: String,
     pub posts: Vec<ChannelPost>,
+    /// Id for fetch older posts via `?before={id}`, `None` if older posts not exists
+    pub before: Option<usize>,
 }
 
 impl Channel {
@@ -14,6 +18,31 @@ impl Channel {
     pub fn url(&self) -> String {
         format!("https://t.me/{}", self.slug)
     }
+
+    /// Merge posts from other page without duplicates, keep order by post id.
+    /// Returns count of new posts
+    /// ```
+    /// use telegram::data::{Channel, ChannelPost};
+    /// let post = |id: &str| ChannelPost { id: id.to_string(), ..ChannelPost::default() };
+    /// let mut channel = Channel {
+    ///     posts: vec![post("foo/3"), post("foo/4")],
+    ///     ..Channel::default()
+    /// };
+    /// assert_eq!(channel.merge_posts(vec![post("foo/1"), post("foo/2"), post("foo/3")]), 2);
+    /// assert_eq!(
+    ///     channel.posts.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(),
+    ///     vec!["foo/1", "foo/2", "foo/3", "foo/4"]
+    /// );
+    /// assert_eq!(channel.merge_posts(vec![post("foo/2")]), 0);
+    /// ```
+    pub fn merge_posts(&mut self, posts: Vec<ChannelPost>) -> usize {
+        let len = self.posts.len();
+        let ids: HashSet<String> = self.posts.iter().map(|p| p.id.clone()).collect();
+        self.posts
+            .extend(posts.into_iter().filter(|p| !ids.contains(&p.id)));
+        self.posts.sort_by_key(|p| p.post_id());
+        self.posts.len() - len
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -41,6 +70,15 @@ impl ChannelPost {
     pub fn url(&self) -> String {
         format!("https://t.me/{}", self.id)
     }
+    /// Numeric part of id
+    /// ```
+    /// use telegram::data::ChannelPost;
+    /// let post = ChannelPost { id: "channel_slug/123".to_string(), ..ChannelPost::default() };
+    /// assert_eq!(post.post_id(), Some(123));
+    /// ```
+    pub fn post_id(&self) -> Option<usize> {
+        self.id.rsplit_once('/').and_then(|(_, id)| id.parse().ok())
+    }
     pub fn get_media_list_mut(&mut self) -> Vec<&mut Media> {
         let link_preview_media = self.link_preview.as_mut().and_then(|p| p.media.as_mut());
         self.media.as_mut().map_or(Vec::new(), |m| {
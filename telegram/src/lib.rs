@@ -2,6 +2,8 @@ pub mod data;
 pub mod error;
 pub mod parse;
 pub mod preview_api;
+#[cfg(feature = "async")]
+pub mod preview_api_async;
 
 pub use error::Result;
 pub use error::TelegramApiErrorKind;
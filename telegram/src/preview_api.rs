@@ -1,4 +1,6 @@
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use reqwest::blocking::Response;
 use reqwest::cookie::Jar;
@@ -12,7 +14,57 @@ use crate::error::TelegramApiError;
 use crate::parse::parse_message;
 use crate::TelegramApiErrorKind::StatusError;
 
-const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/110.0.0.0 Safari/537.36";
+pub(crate) const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/110.0.0.0 Safari/537.36";
+
+pub(crate) fn preview_url(slug: &str) -> String {
+    format!("https://t.me/s/{slug}")
+}
+
+pub(crate) fn preview_page_url(slug: &str, before: Option<usize>) -> String {
+    match before {
+        Some(before) => format!("{}?before={before}", preview_url(slug)),
+        None => preview_url(slug),
+    }
+}
+
+pub(crate) fn embedded_post_url(slug: &str, id: usize) -> String {
+    format!("https://t.me/{slug}/{id}?embed=1&mode=tme&userpic=true")
+}
+
+/// Channel meta and posts of preview or embedded post page
+pub(crate) fn parse_html_page(slug: &str, html: &str) -> error::Result<Channel> {
+    let parser = scraper::Html::parse_document(html);
+    let mut channel = Channel {
+        slug: slug.to_string(),
+        ..Channel::default()
+    };
+    for el in parser.select(&Selector::parse("meta[property^='og:']").unwrap()) {
+        let v = el.value();
+        let prop = v.attr("property").unwrap();
+        match prop[3..].as_ref() {
+            "title" => channel.title = v.attr("content").unwrap().to_string(),
+            "image" => channel.image_url = v.attr("content").unwrap().to_string(),
+            "description" => channel.description = v.attr("content").unwrap().to_string(),
+            _ => {}
+        }
+    }
+    log::trace!("parsed meta {:?}", channel);
+    channel.before = parser
+        .select(&Selector::parse(".js-messages_more[data-before]").unwrap())
+        .next()
+        .and_then(|el| el.value().attr("data-before"))
+        .and_then(|b| b.parse().ok());
+    for el_ref in parser.select(&Selector::parse(".js-widget_message").unwrap()) {
+        log::debug!(
+            "start parse message id={:?}",
+            el_ref.value().attr("data-post")
+        );
+        let post = parse_message(el_ref.html().as_str()).unwrap();
+        log::trace!("parsed message {:?}", post);
+        channel.posts.push(post);
+    }
+    Ok(channel)
+}
 
 #[derive(Default, Debug)]
 pub struct TelegramChannelPreviewApi {
@@ -38,13 +90,14 @@ impl TelegramChannelPreviewApi {
     }
 
     pub fn preview_url(&self) -> String {
-        format!("https://t.me/s/{}", self.slug)
+        preview_url(&self.slug)
+    }
+    /// Preview page with posts older than `before`
+    pub fn preview_page_url(&self, before: Option<usize>) -> String {
+        preview_page_url(&self.slug, before)
     }
     pub fn embedded_post_url(&self, id: usize) -> String {
-        format!(
-            "https://t.me/{}/{}?embed=1&mode=tme&userpic=true",
-            self.slug, id
-        )
+        embedded_post_url(&self.slug, id)
     }
 
     pub fn request(&self, method: Method, url: &str) -> reqwest::blocking::RequestBuilder {
@@ -68,32 +121,7 @@ impl TelegramChannelPreviewApi {
     }
 
     pub fn parse_html_page(&self, html: &str) -> error::Result<Channel> {
-        let parser = scraper::Html::parse_document(html);
-        let mut channel = Channel {
-            slug: self.slug.clone(),
-            ..Channel::default()
-        };
-        for el in parser.select(&Selector::parse("meta[property^='og:']").unwrap()) {
-            let v = el.value();
-            let prop = v.attr("property").unwrap();
-            match prop[3..].as_ref() {
-                "title" => channel.title = v.attr("content").unwrap().to_string(),
-                "image" => channel.image_url = v.attr("content").unwrap().to_string(),
-                "description" => channel.description = v.attr("content").unwrap().to_string(),
-                _ => {}
-            }
-        }
-        log::trace!("parsed meta {:?}", channel);
-        for el_ref in parser.select(&Selector::parse(".js-widget_message").unwrap()) {
-            log::debug!(
-                "start parse message id={:?}",
-                el_ref.value().attr("data-post")
-            );
-            let post = parse_message(el_ref.html().as_str()).unwrap();
-            log::trace!("parsed message {:?}", post);
-            channel.posts.push(post);
-        }
-        Ok(channel)
+        parse_html_page(&self.slug, html)
     }
     /// Пытаемся получить новый урл.
     pub fn try_get_new_media_url(&self, post_id: usize, media_index: usize, field: &str) -> String {
@@ -116,12 +144,41 @@ impl TelegramChannelPreviewApi {
         self.parse_html_page(html.as_str())
     }
 
-    pub fn fetch(&self, _pages: Option<usize>) -> error::Result<Channel> {
-        log::debug!("fetch pages={:?}", _pages);
-        // TODO handle pages
-        let html = self.get(self.preview_url().as_str())?.text()?;
+    pub fn fetch_page(&self, before: Option<usize>) -> error::Result<Channel> {
+        log::debug!("fetch_page before={:?}", before);
+        let html = self.get(self.preview_page_url(before).as_str())?.text()?;
         self.parse_html_page(html.as_str())
     }
+
+    /// Fetch `pages` pages walking back through older posts
+    pub fn fetch(&self, pages: Option<usize>) -> error::Result<Channel> {
+        self.fetch_before(None, pages)
+    }
+
+    /// Fetch `pages` pages starting with posts older than `before`
+    pub fn fetch_before(
+        &self,
+        before: Option<usize>,
+        pages: Option<usize>,
+    ) -> error::Result<Channel> {
+        log::debug!("fetch_before before={:?} pages={:?}", before, pages);
+        let pages = pages.unwrap_or(1);
+        let mut channel = self.fetch_page(before)?;
+        for _ in 1..pages {
+            let Some(before) = channel.before else {
+                // Более старых постов нет
+                break;
+            };
+            // Немного подождем чтоб не словить бан
+            thread::sleep(Duration::from_millis(100));
+            let page = self.fetch_page(Some(before))?;
+            channel.before = page.before;
+            if channel.merge_posts(page.posts) == 0 {
+                break;
+            }
+        }
+        Ok(channel)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use reqwest::cookie::Jar;
+use reqwest::{Method, Response};
+
+use crate::data::Channel;
+use crate::error;
+use crate::error::TelegramApiError;
+use crate::preview_api::{embedded_post_url, parse_html_page, preview_page_url, USER_AGENT};
+use crate::TelegramApiErrorKind::StatusError;
+
+/// Async variant of [`crate::preview_api::TelegramChannelPreviewApi`].
+/// Parsing is sync and cheap, only requests are awaited
+#[derive(Debug, Clone)]
+pub struct AsyncTelegramChannelPreviewApi {
+    session: reqwest::Client,
+    pub slug: String,
+    pub name: Option<String>,
+}
+
+impl AsyncTelegramChannelPreviewApi {
+    pub fn new(slug: &str) -> AsyncTelegramChannelPreviewApi {
+        Self::with_client(Self::build_client(), slug)
+    }
+
+    /// Client with browser user agent and cookies
+    pub fn build_client() -> reqwest::Client {
+        reqwest::Client::builder()
+            .user_agent(USER_AGENT)
+            .cookie_provider(Arc::new(Jar::default()))
+            .build()
+            .unwrap()
+    }
+
+    /// Share one client (and its connection pool) between channels
+    pub fn with_client(session: reqwest::Client, slug: &str) -> AsyncTelegramChannelPreviewApi {
+        AsyncTelegramChannelPreviewApi {
+            session,
+            slug: slug.to_string(),
+            name: None,
+        }
+    }
+
+    pub fn request(&self, method: Method, url: &str) -> reqwest::RequestBuilder {
+        self.session.request(method, url)
+    }
+
+    pub async fn get(&self, url: &str) -> crate::Result<Response> {
+        log::debug!("get url={:?}", url);
+        let result = self.request(Method::GET, url).send().await?;
+        let status = result.status().as_u16();
+        log::debug!("get [{:?}]", status);
+        log::trace!("get result={:?}", &result);
+        if status >= 400 {
+            log::trace!("ERROR text={:?}", &result.text().await);
+            Err(TelegramApiError::ApiError {
+                kind: StatusError(status),
+            })
+        } else {
+            Ok(result)
+        }
+    }
+
+    pub async fn fetch_post(&self, id: usize) -> error::Result<Channel> {
+        log::debug!("fetch_post id={:?}", id);
+        let url = embedded_post_url(&self.slug, id);
+        let html = self.get(url.as_str()).await?.text().await?;
+        parse_html_page(&self.slug, html.as_str())
+    }
+
+    pub async fn fetch_page(&self, before: Option<usize>) -> error::Result<Channel> {
+        log::debug!("fetch_page before={:?}", before);
+        let url = preview_page_url(&self.slug, before);
+        let html = self.get(url.as_str()).await?.text().await?;
+        parse_html_page(&self.slug, html.as_str())
+    }
+
+    /// Fetch `pages` pages walking back through older posts
+    pub async fn fetch(&self, pages: Option<usize>) -> error::Result<Channel> {
+        self.fetch_before(None, pages).await
+    }
+
+    /// Fetch `pages` pages starting with posts older than `before`
+    pub async fn fetch_before(
+        &self,
+        before: Option<usize>,
+        pages: Option<usize>,
+    ) -> error::Result<Channel> {
+        log::debug!("fetch_before before={:?} pages={:?}", before, pages);
+        let pages = pages.unwrap_or(1);
+        let mut channel = self.fetch_page(before).await?;
+        for _ in 1..pages {
+            let Some(before) = channel.before else {
+                // Более старых постов нет
+                break;
+            };
+            // Немного подождем чтоб не словить бан
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let page = self.fetch_page(Some(before)).await?;
+            channel.before = page.before;
+            if channel.merge_posts(page.posts) == 0 {
+                break;
+            }
+        }
+        Ok(channel)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::preview_api_async::AsyncTelegramChannelPreviewApi;
+
+    #[test]
+    fn test_async_preview_api() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let api = AsyncTelegramChannelPreviewApi::new("fighter_bomber");
+        let channel = runtime.block_on(api.fetch(None)).unwrap();
+        assert!(!channel.posts.is_empty());
+        let channel = runtime.block_on(api.fetch_post(11245)).unwrap();
+        assert_eq!(channel.posts.len(), 1);
+    }
+}
@@ -17,6 +17,18 @@ fn test_parse_html() {
     assert!(!result.posts.is_empty());
 }
 
+#[test]
+fn test_parse_html_before() {
+    let api = TelegramChannelPreviewApi::new("foo");
+    let html = load_fixture("telegram_preview/full.html");
+    let result = api.parse_html_page(html.as_str()).unwrap();
+    assert_eq!(result.before, Some(70989));
+    assert_eq!(
+        api.preview_page_url(result.before),
+        "https://t.me/s/foo?before=70989"
+    );
+}
+
 #[test]
 fn test_parse_text() {
     let html = load_fixture("telegram_preview/message_text.html");